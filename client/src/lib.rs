@@ -1 +1,2 @@
-
+pub mod positions;
+pub mod session;
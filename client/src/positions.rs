@@ -0,0 +1,110 @@
+//! Receiving position updates from the unreliable channel.
+//!
+//! `UpdateEntityPositionAndDirection` arrives as datagrams, which may be
+//! late, duplicated or reordered. Only an update newer than the last
+//! applied one of the same entity gets through.
+//!
+//! Ticks are only comparable within a connection: a restarted server
+//! counts from zero again, so every `LoginSuccess` starts over.
+
+use common::net::{
+    datagram::{self, StaleFilter, Tick},
+    packets::{Packet, UpdateEntityPositionAndDirection, UpdateEntityPositionAndDirectionData},
+};
+use protocol::{context::DecodeContext, error::ProtocolError};
+
+#[derive(Debug, Default)]
+pub struct PositionUpdates {
+    /// Keyed by the entity id
+    filter: StaleFilter<i64>,
+}
+
+impl PositionUpdates {
+    pub fn new() -> PositionUpdates {
+        PositionUpdates::default()
+    }
+
+    /// Decodes the datagram, `None` if the update is stale
+    pub fn receive(
+        &mut self,
+        datagram: &[u8],
+        ctx: &DecodeContext,
+    ) -> Result<Option<UpdateEntityPositionAndDirectionData>, ProtocolError> {
+        let (tick, packet) = datagram::decode_datagram(datagram)?;
+        self.receive_packet(tick, packet, ctx)
+    }
+
+    /// The same for a datagram already split into the tick and the packet
+    pub fn receive_packet(
+        &mut self,
+        tick: Tick,
+        packet: &[u8],
+        ctx: &DecodeContext,
+    ) -> Result<Option<UpdateEntityPositionAndDirectionData>, ProtocolError> {
+        let data = UpdateEntityPositionAndDirection::decode(packet, ctx)?;
+        match self.filter.accept(data.id.0, tick) {
+            true => Ok(Some(data)),
+            false => Ok(None),
+        }
+    }
+
+    /// Must be called on `RemoveEntities`, an id may come back
+    /// with a resumed session
+    pub fn remove(&mut self, entity_id: i64) {
+        self.filter.forget(&entity_id);
+    }
+
+    /// Must be called on `LoginSuccess`, the ticks of the server
+    /// may have started over since the previous connection
+    pub fn reset(&mut self) {
+        self.filter.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::net::packets::Id;
+    use protocol::primitives::{byte::Byte, uvarint::UVarInt};
+
+    fn update(tick: Tick, id: i64, x: u32) -> Vec<u8> {
+        let packet =
+            UpdateEntityPositionAndDirection::encode(&UpdateEntityPositionAndDirectionData {
+                id: Id::from(id),
+                x: UVarInt(x),
+                y: UVarInt(0),
+                direction: Byte(0),
+            })
+            .unwrap();
+        datagram::encode_datagram(tick, &packet).unwrap()
+    }
+
+    #[test]
+    fn positions_drop_stale() -> Result<(), ProtocolError> {
+        let mut positions = PositionUpdates::new();
+        let ctx = DecodeContext::DEFAULT;
+
+        assert_eq!(positions.receive(&update(5, 1, 50), &ctx)?.unwrap().x.0, 50);
+        // Reordered on the way
+        assert!(positions.receive(&update(4, 1, 40), &ctx)?.is_none());
+        assert!(positions.receive(&update(4, 2, 40), &ctx)?.is_some());
+        assert!(positions.receive(&update(6, 1, 60), &ctx)?.is_some());
+
+        positions.remove(1);
+        assert!(positions.receive(&update(1, 1, 10), &ctx)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn positions_after_server_restart() -> Result<(), ProtocolError> {
+        let mut positions = PositionUpdates::new();
+        let ctx = DecodeContext::DEFAULT;
+        assert!(positions.receive(&update(500, 1, 50), &ctx)?.is_some());
+
+        // The session is resumed on a restarted server, its ticks start at 0
+        positions.reset();
+        assert_eq!(positions.receive(&update(1, 1, 51), &ctx)?.unwrap().x.0, 51);
+        assert!(positions.receive(&update(2, 1, 52), &ctx)?.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Client-side part of the session.
+//!
+//! The server issues a reconnect token at `LoginSuccess`. If the connection
+//! drops, logging in again with that token within a few seconds resumes
+//! control of the same snake instead of spawning a new one.
+//!
+//! `Login` also carries the protocol version and the features of this build,
+//! `LoginSuccess` answers with the features the server agreed to use.
+//! The username and the auth token are the player's, the server checks
+//! both and refuses the login with a reason if they are no good.
+//!
+//! With `Feature::Compression`, `SetCompression` follows and every later
+//! frame is written and read with `Session::compression` in both directions.
+//! Nothing may be sent between `Login` and `SetCompression`, the server
+//! expects the compression flag in every frame after `Login` then.
+
+use common::net::{
+    packets::{LoginData, LoginSuccessData, ReconnectToken, SetCompressionData},
+    username::{UsernameError, validate_username},
+    version::{Feature, Features, PROTOCOL_VERSION},
+};
+use protocol::{
+    compression::Compression,
+    primitives::{string::StringProto, uvarint::UVarInt},
+};
+
+/// Sent in `Login`, so the server logs show which build connected
+pub const CLIENT_NAME: &str = concat!("venomized-client/", env!("CARGO_PKG_VERSION"));
+
+/// Until the player picks one
+pub const DEFAULT_USERNAME: &str = "player";
+
+#[derive(Debug)]
+pub struct Session {
+    client_name: String,
+
+    /// See `common::net::username` for the rules
+    username: String,
+
+    /// Empty unless the server requires one
+    auth_token: String,
+
+    /// `None` until the first successful login
+    reconnect_token: Option<i64>,
+
+    /// Negotiated at the last login, nothing before it
+    features: Features,
+
+    /// Enabled by `SetCompression`, every connection starts without it
+    compression: Option<Compression>,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session {
+            client_name: CLIENT_NAME.to_string(),
+            username: DEFAULT_USERNAME.to_string(),
+            auth_token: String::new(),
+            reconnect_token: None,
+            features: Features::none(),
+            compression: None,
+        }
+    }
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Overrides `CLIENT_NAME`, e.g. for `viz`
+    pub fn with_client_name(mut self, client_name: impl Into<String>) -> Session {
+        self.client_name = client_name.into();
+        self
+    }
+
+    /// Refuses names the server would refuse anyway
+    pub fn with_username(mut self, username: impl Into<String>) -> Result<Session, UsernameError> {
+        let username = username.into();
+        validate_username(&username)?;
+        self.username = username;
+        Ok(self)
+    }
+
+    /// The shared secret of the server or the token of the username
+    pub fn with_auth_token(mut self, auth_token: impl Into<String>) -> Session {
+        self.auth_token = auth_token.into();
+        self
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Payload of the `Login` packet, carrying the token if there is one
+    pub fn login_data(&self) -> LoginData {
+        LoginData {
+            protocol_version: UVarInt(PROTOCOL_VERSION),
+            client_name: StringProto(self.client_name.clone()),
+            username: StringProto(self.username.clone()),
+            auth_token: StringProto(self.auth_token.clone()),
+            features: Features::all(),
+            reconnect_token: ReconnectToken::from(self.reconnect_token.unwrap_or(0)),
+        }
+    }
+
+    /// Remembers the token, the server may issue a new one on every login
+    pub fn on_login_success(&mut self, data: &LoginSuccessData) {
+        self.reconnect_token = match data.reconnect_token.0 {
+            0 => None,
+            token => Some(token),
+        };
+        self.features = data.features.clone();
+        self.compression = None;
+    }
+
+    /// The threshold is the server's, the limit of inflated frames is ours
+    pub fn on_set_compression(&mut self, data: &SetCompressionData) {
+        self.compression = Some(Compression::new(data.threshold.0 as usize));
+    }
+
+    /// For the frames after `SetCompression`
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Whether the server agreed to use the feature
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(feature)
+    }
+
+    /// Whether the next login will try to resume the previous snake
+    pub fn can_resume(&self) -> bool {
+        self.reconnect_token.is_some()
+    }
+
+    /// Forgets the token, e.g. when the snake died and there is nothing to resume
+    pub fn reset(&mut self) {
+        self.reconnect_token = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_keeps_token() {
+        let mut session = Session::new();
+        assert_eq!(session.login_data().reconnect_token.0, 0);
+
+        session.on_login_success(&LoginSuccessData {
+            reconnect_token: ReconnectToken::from(-42),
+            features: Features::from_iter([Feature::SessionResume]),
+        });
+        assert!(session.can_resume());
+        assert!(session.supports(Feature::SessionResume));
+        assert_eq!(session.login_data().reconnect_token.0, -42);
+
+        session.reset();
+        assert!(!session.can_resume());
+    }
+
+    #[test]
+    fn session_login_data() {
+        let session = Session::new()
+            .with_client_name("viz")
+            .with_username("viper")
+            .unwrap()
+            .with_auth_token("secret");
+        let data = session.login_data();
+
+        assert_eq!(data.protocol_version.0, PROTOCOL_VERSION);
+        assert_eq!(data.client_name.0, "viz");
+        assert_eq!(data.username.0, "viper");
+        assert_eq!(data.auth_token.0, "secret");
+        assert_eq!(data.features, Features::all());
+
+        assert_eq!(
+            Session::new().with_username("no").unwrap_err(),
+            UsernameError::TooShort
+        );
+    }
+
+    #[test]
+    fn session_compression_per_connection() {
+        let mut session = Session::new();
+        let login_success = LoginSuccessData {
+            reconnect_token: ReconnectToken::from(0),
+            features: Features::all(),
+        };
+        session.on_login_success(&login_success);
+        assert_eq!(session.compression(), None);
+
+        session.on_set_compression(&SetCompressionData {
+            threshold: UVarInt(64),
+        });
+        assert_eq!(session.compression(), Some(Compression::new(64)));
+
+        // A reconnect starts uncompressed
+        session.on_login_success(&login_success);
+        assert_eq!(session.compression(), None);
+    }
+}
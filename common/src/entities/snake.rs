@@ -1,17 +1,66 @@
 use std::collections::VecDeque;
 
+use protocol::{
+    codec::Codec,
+    context::DecodeContext,
+    error::{ProtocolError, ProtocolViolation},
+    primitives::{byte::Byte, prefixed_array::PrefixedArray},
+};
+
 use crate::world::types::GridPos;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
 pub enum Direction {
-    North,
-    South,
-    West,
-    East,
+    North = 0,
+    South = 1,
+    West = 2,
+    East = 3,
+}
+
+impl Direction {
+    /// Turning there makes the head run into the neck
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+            Direction::East => Direction::West,
+        }
+    }
+}
+
+/// Packets carry directions as a `Byte`
+impl TryFrom<u8> for Direction {
+    type Error = ProtocolError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Direction::North),
+            1 => Ok(Direction::South),
+            2 => Ok(Direction::West),
+            3 => Ok(Direction::East),
+            _ => Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownVariant,
+            )),
+        }
+    }
+}
+
+impl Codec for Direction {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        Byte(*self as u8).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Direction::try_from(Byte::decode(reader)?.0)
+    }
 }
 
 /// The main entity in the game,
 /// which is stored on both the
 /// client and server sides with the same structure.
+#[derive(Debug)]
 pub struct Snake {
     /// A variable that is necessary in many cases,
     /// indicating the direction in which our snake is moving.
@@ -24,6 +73,12 @@ pub struct Snake {
     pub body: VecDeque<GridPos>,
 }
 
+impl Default for Snake {
+    fn default() -> Self {
+        Snake::new()
+    }
+}
+
 impl Snake {
     pub fn new() -> Snake {
         // TODO: randomize direction??? or propagation direction
@@ -34,33 +89,141 @@ impl Snake {
         }
     }
 
-    /// Absolutely genius and simple function which
-    /// Have a O(1) time for operation, and do ALL
-    /// logical of movement for game tick
-    pub fn move_forward(&mut self) {
+    /// Where the head goes with the next move, `None` if the move
+    /// would take it below 0 or past `u32::MAX`.
+    pub fn next_head(&self) -> Option<GridPos> {
         let head = self.body.front().expect("Snake has no head!");
 
-        let new_head = match self.direction {
-            Direction::North => GridPos {
+        match self.direction {
+            Direction::North => Some(GridPos {
                 x: head.x,
-                y: head.y - 1,
-            },
-            Direction::South => GridPos {
+                y: head.y.checked_sub(1)?,
+            }),
+            Direction::South => Some(GridPos {
                 x: head.x,
-                y: head.y + 1,
-            },
-            Direction::East => GridPos {
-                x: head.x + 1,
+                y: head.y.checked_add(1)?,
+            }),
+            Direction::East => Some(GridPos {
+                x: head.x.checked_add(1)?,
                 y: head.y,
-            },
-            Direction::West => GridPos {
-                x: head.x - 1,
+            }),
+            Direction::West => Some(GridPos {
+                x: head.x.checked_sub(1)?,
                 y: head.y,
-            },
-        };
+            }),
+        }
+    }
+
+    /// Absolutely genius and simple function which
+    /// Have a O(1) time for operation, and do ALL
+    /// logical of movement for game tick
+    pub fn move_forward(&mut self) {
+        let new_head = self.next_head().expect("Snake left the coordinates!");
 
         self.body.push_front(new_head);
 
         self.body.pop_back();
     }
+
+    /// The direction of the last move, read from the head and the neck.
+    /// Unlike `direction`, it doesn't change with turns until the next move.
+    /// `None` without a neck.
+    pub fn heading(&self) -> Option<Direction> {
+        let head = self.body.front()?;
+        let neck = self.body.get(1)?;
+        match (head.x.wrapping_sub(neck.x), head.y.wrapping_sub(neck.y)) {
+            (0, u32::MAX) => Some(Direction::North),
+            (0, 1) => Some(Direction::South),
+            (u32::MAX, 0) => Some(Direction::West),
+            (1, 0) => Some(Direction::East),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for Snake {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        self.direction.encode(writer)?;
+        PrefixedArray::from(Vec::from(self.body.clone())).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(
+        reader: &mut impl buffer::Buffer,
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError> {
+        let direction = Direction::decode(reader)?;
+        let body: PrefixedArray<GridPos> = PrefixedArray::decode_with(reader, ctx)?;
+
+        Ok(Snake {
+            direction,
+            body: VecDeque::from(body.data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn snake_roundtrip() -> Result<(), ProtocolError> {
+        let mut snake = Snake::new();
+        snake.direction = Direction::West;
+        snake
+            .body
+            .extend([GridPos { x: 5, y: 3 }, GridPos { x: 6, y: 3 }]);
+
+        let mut writer: Vec<u8> = Vec::new();
+        snake.encode(&mut writer)?;
+        let mut buf = &writer[..];
+        let snake_trip = Snake::decode(&mut buf)?;
+
+        assert_eq!(snake_trip.direction, Direction::West);
+        assert_eq!(snake_trip.body, snake.body);
+        Ok(())
+    }
+
+    #[test]
+    fn snake_next_head_at_zero() {
+        let mut snake = Snake::new();
+        snake.body.push_back(GridPos { x: 0, y: 0 });
+
+        assert_eq!(snake.next_head(), None);
+        snake.direction = Direction::West;
+        assert_eq!(snake.next_head(), None);
+        snake.direction = Direction::South;
+        assert_eq!(snake.next_head(), Some(GridPos { x: 0, y: 1 }));
+    }
+
+    #[test]
+    fn snake_heading_from_neck() {
+        let mut snake = Snake::new();
+        snake.body.push_back(GridPos { x: 5, y: 5 });
+        assert_eq!(snake.heading(), None);
+
+        snake.body.push_back(GridPos { x: 5, y: 6 });
+        snake.direction = Direction::West;
+        assert_eq!(snake.heading(), Some(Direction::North));
+
+        snake.move_forward();
+        assert_eq!(snake.heading(), Some(Direction::West));
+    }
+
+    #[test]
+    fn direction_decode_fail_unknown_variant() {
+        let buf: [u8; 1] = [4];
+        let mut reader: &[u8] = &buf;
+
+        assert_matches!(
+            Direction::decode(&mut reader),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownVariant
+            ))
+        );
+    }
 }
@@ -0,0 +1,120 @@
+//! The unreliable channel.
+//!
+//! Most packets go over a reliable, ordered stream. Updates that are
+//! useless once a newer one exists (positions) go as datagrams instead:
+//! a lost one is not resent, and nothing waits behind it.
+//!
+//! A datagram carries exactly one packet and the tick it was made at:
+//!
+//! ```text
+//! tick    u32 BE   server tick the packet describes
+//! packet  [u8]     packet id + payload, never compressed
+//! ```
+//!
+//! Datagrams may arrive late, twice, or in another order. `StaleFilter`
+//! lets the receiver drop everything older than what it already applied.
+
+use std::{collections::HashMap, hash::Hash};
+
+use protocol::{codec::Codec, error::ProtocolError, primitives::fixed::U32};
+
+/// Number of the server tick, 10 per second, so it won't wrap for years
+pub type Tick = u32;
+
+/// How a packet travels, see `Packet::CHANNEL`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// In order and without losses, on the stream
+    Reliable,
+
+    /// As a datagram, may be lost or reordered
+    Unreliable,
+}
+
+/// Prepends the tick to the packet
+pub fn encode_datagram(tick: Tick, packet: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+    let mut datagram = Vec::with_capacity(packet.len() + 4);
+    U32(tick).encode(&mut datagram)?;
+    datagram.extend_from_slice(packet);
+    Ok(datagram)
+}
+
+/// Splits the datagram into the tick and the packet
+pub fn decode_datagram(datagram: &[u8]) -> Result<(Tick, &[u8]), ProtocolError> {
+    let mut reader = datagram;
+    let tick = U32::decode(&mut reader)?.0;
+    Ok((tick, reader))
+}
+
+/// Remembers the latest tick applied per key (e.g. per entity)
+/// and rejects anything that is not newer
+#[derive(Debug)]
+pub struct StaleFilter<K> {
+    latest: HashMap<K, Tick>,
+}
+
+impl<K: Hash + Eq> Default for StaleFilter<K> {
+    fn default() -> Self {
+        StaleFilter {
+            latest: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Hash + Eq> StaleFilter<K> {
+    pub fn new() -> StaleFilter<K> {
+        StaleFilter::default()
+    }
+
+    /// Whether an update of `key` made at `tick` should be applied.
+    /// Duplicates of the latest update are rejected too.
+    pub fn accept(&mut self, key: K, tick: Tick) -> bool {
+        match self.latest.get(&key) {
+            Some(latest) if *latest >= tick => false,
+            _ => {
+                self.latest.insert(key, tick);
+                true
+            }
+        }
+    }
+
+    /// Drops the state of a key, e.g. when the entity is removed
+    pub fn forget(&mut self, key: &K) {
+        self.latest.remove(key);
+    }
+
+    /// Drops the state of every key, e.g. when the ticks start over
+    pub fn clear(&mut self) {
+        self.latest.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datagram_roundtrip() -> Result<(), ProtocolError> {
+        let datagram = encode_datagram(0x01020304, &[9, 8])?;
+        assert_eq!(datagram, [1, 2, 3, 4, 9, 8]);
+
+        assert_eq!(decode_datagram(&datagram)?, (0x01020304, &[9, 8][..]));
+        assert!(decode_datagram(&[1, 2]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stale_filter_drops_old_and_duplicates() {
+        let mut filter = StaleFilter::new();
+
+        assert!(filter.accept(1, 10));
+        assert!(!filter.accept(1, 10));
+        assert!(!filter.accept(1, 9));
+        // Keys don't affect each other
+        assert!(filter.accept(2, 5));
+        assert!(filter.accept(1, 12));
+
+        filter.forget(&1);
+        assert!(filter.accept(1, 3));
+    }
+}
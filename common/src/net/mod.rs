@@ -1 +1,5 @@
+pub mod datagram;
 pub mod packets;
+pub mod spec;
+pub mod username;
+pub mod version;
@@ -1,162 +1,413 @@
 //! All IDs that will be used by the server and client
 //! to exchange information are stored here.
 //!
-//! Also provide decode/encode methods for packets
+//! The packets are described once, in the `protocol!` spec below.
+//! Ids, payload structs, their codecs and `docs/protocol.md`
+//! are generated from it (see `net::spec`).
 
-use protocol::primitives::{
-    byte::Byte, prefixed_array::PrefixedArray, uvarint::UVarInt, varint::VarInt, varlong::VarLong,
+use buffer::{Buffer, BufferMut};
+use protocol::{
+    codec::Codec,
+    context::DecodeContext,
+    error::{ProtocolError, ProtocolViolation, ResultExt},
+    primitives::{
+        byte::Byte, prefixed_array::PrefixedArray, string::StringProto, uvarint::UVarInt,
+        varint::VarInt, varlong::VarLong,
+    },
+};
+
+use crate::{
+    net::{
+        datagram::Channel,
+        spec::{self, protocol},
+        version::{Features, PROTOCOL_VERSION},
+    },
+    world::world::World,
 };
 
 /// An alias for Entity Id in the game, to remove magic number
 pub type Id = VarLong;
 
-// #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, num_enum::TryFromPrimitive)]
-pub enum LoginServerbound {
-    /// Initiating dialogue with the server
-    /// and providing basic information
-    Login = 0,
-}
+/// An alias for the token that lets a player resume their session
+/// after a short disconnect. `0` means there is no token.
+pub type ReconnectToken = VarLong;
 
-pub enum ConfigureServerbound {
-    /// The package needed for the server to calculate
-    /// the number of chunks that need to be sent to the client.
-    ///
-    /// **TODO:** It is assumed that later I will be able
-    /// to calculate the window size on the client and send
-    /// this value to the server at the `Configure` and `Play`
-    /// stages when the window size changes.
-    ///
-    /// *window - refers to the size of a regular terminal/stdout window.
-    SetDrawDistance = 0,
-}
+/// Longest `ChatMessage` the server accepts, in characters
+pub const MAX_CHAT_MESSAGE_LEN: usize = 256;
 
-pub enum PlayServerbound {
-    /// Sent by the client in order to turn the snake
-    TurnSnake = 0,
-}
+/// Most entries the server puts into `Leaderboard`
+pub const LEADERBOARD_SIZE: usize = 10;
 
-pub enum LoginClientbound {
-    /// The package is required to switch
-    /// the state to the `Configuration` stage.
-    LoginSuccess = 0,
-}
+/// Binds the compile-time marker of a packet to its id and payload,
+/// so a packet can't be sent with the payload of another one.
+pub trait Packet {
+    const ID: i32;
+    /// For error messages and logs, e.g. `PlayClientbound::Disconnect`
+    const NAME: &'static str;
+    type Data: Codec;
+    /// Datagrams are sent with `datagram::encode_datagram`
+    const CHANNEL: Channel = Channel::Reliable;
 
-pub enum ConfigureClientbound {
-    /// The package is required to switch
-    /// the state to the `Play` stage.
-    ConfigureAcknowledged = 0,
-}
+    /// Packet id followed by the payload.
+    /// The length prefix of the frame is up to the transport.
+    fn encode(data: &Self::Data) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf = Vec::new();
+        VarInt(Self::ID).encode(&mut buf)?;
+        data.encode(&mut buf)?;
+        Ok(buf)
+    }
 
-pub enum PlayClientbound {
-    /// For the client *(player)*, all other players are Entities.
-    /// They have their own ID, which the player's client can see,
-    /// but the player does not know their own ID, nor does it
-    /// know the IDs when sending TurnSnake packets.
-    SynchonizeSnakePositionAndDirection = 0,
-
-    /// The package is sent to the client if another player (entity) enters their loading zone.
-    SpawnEntity = 1,
-
-    /// Remove entities by provide prefiexed array of id's
-    RemoveEntities = 2,
-
-    /// Applies to all players except oneself
-    UpdateEntityPositionAndDirection = 3,
-
-    /// When another player (entity, snake) dies for any reason,
-    /// all the apples they have eaten fall into the game world.
-    /// This package is needed to deliver information about this
-    /// to customers in a compact form.
-    AppleSpawnButch = 4,
-
-    /// The package needed for the server to calculate
-    /// the number of chunks that need to be sent to the client.
-    ///
-    /// **TODO:** It is assumed that later I will be able
-    /// to calculate the window size on the client and send
-    /// this value to the server at the `Configure` and `Play`
-    /// stages when the window size changes.
-    ///
-    /// *window - refers to the size of a regular terminal/stdout window.
-    SetDrawDistance = 5,
+    /// Decodes a whole packet: the id must be `Self::ID` and the payload
+    /// must be consumed completely. Errors carry the name of the packet,
+    /// the failed field and the byte offset in the packet.
+    fn decode(packet: &[u8], ctx: &DecodeContext) -> Result<Self::Data, ProtocolError> {
+        let mut reader = packet;
+        let result = VarInt::decode(&mut reader).and_then(|id| {
+            if id.0 != Self::ID {
+                return Err(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::UnknownPacket,
+                ));
+            }
+            let data = Self::Data::decode_with(&mut reader, ctx)?;
+            if !reader.is_empty() {
+                return Err(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::TrailingBytes,
+                ));
+            }
+            Ok(data)
+        });
+        result.map_err(|err| {
+            err.context(Self::NAME)
+                .at_offset(packet.len() - reader.len())
+        })
+    }
 }
 
-// -- Type-safety aliases --
-// (empty structs haven't weight, its just like marker in compile time)
-pub struct Login;
-pub struct SetDrawDistanceConfigure;
-pub struct TurnSnake;
-
-pub struct LoginSuccess;
-pub struct ConfigureAcknowledged;
-pub struct SynchonizePositionAndDirection;
-pub struct SpawnEntity;
-pub struct RemoveEntities;
-pub struct UpdateEntityPositionAndDirection;
-pub struct AppleSpawnButch;
-pub struct SetDrawDistancePlay;
-// -- Type-safety aliases end --
-
-// -- Packet payloads --
-pub struct LoginData {}
-pub struct SetDrawDistanceConfigureData {}
-pub struct TurnSnakeData {}
-
-pub struct LoginSuccessData;
-pub struct ConfigureAcknowledgedData;
-pub struct SynchonizePositionAndDirectionData {
-    pub x: UVarInt,
-    pub y: UVarInt,
-    pub direction: Byte,
-}
-pub struct SpawnEntityData {
-    pub id: Id,
-    pub x: UVarInt,
-    pub y: UVarInt,
-    pub direction: Byte,
+protocol! {
+    payloads {
+        LoginData {
+            /// Must stay the first field in every version,
+            /// the server checks it before decoding the rest
+            protocol_version: UVarInt,
+            /// Name and version of the client build, for logs
+            client_name: StringProto,
+            /// Shown to other players, see `common::net::username` for the rules.
+            /// Must not be taken by another player online.
+            username: StringProto,
+            /// The shared secret or the token of `username`,
+            /// if the server requires one, empty otherwise
+            auth_token: StringProto,
+            /// Features the client understands
+            features: Features,
+            /// Token from a previous `LoginSuccess`, `0` for a new session
+            reconnect_token: ReconnectToken,
+        }
+        SetDrawDistanceConfigureData {}
+        TurnSnakeData {
+            /// `0` north, `1` south, `2` west, `3` east.
+            /// Turning back into the own body is ignored.
+            direction: Byte,
+            /// Of the head at the moment of the turn, as the client sees it.
+            /// The server rejects positions the snake can't be at.
+            x: UVarInt,
+            /// Of the head at the moment of the turn, as the client sees it
+            y: UVarInt,
+        }
+        ChatMessageData {
+            /// At most 256 characters (`MAX_CHAT_MESSAGE_LEN`)
+            message: StringProto,
+        }
+
+        LoginSuccessData {
+            /// Must be kept by the client to resume the session after a disconnect.
+            /// `0` unless `Feature::SessionResume` was negotiated.
+            reconnect_token: ReconnectToken,
+            /// Features supported by both sides, the server uses only them
+            features: Features,
+        }
+        ConfigureAcknowledgedData {}
+        WorldInfoData {
+            /// In tiles
+            width: UVarInt,
+            /// In tiles
+            height: UVarInt,
+            /// In tiles, `width` is a multiple of it
+            chunk_width: UVarInt,
+            /// In tiles, `height` is a multiple of it
+            chunk_height: UVarInt,
+        }
+        SetCompressionData {
+            /// Bodies of at least this many bytes are compressed by the server.
+            /// The client is free to choose its own threshold.
+            threshold: UVarInt,
+        }
+        SynchonizePositionAndDirectionData {
+            x: UVarInt,
+            y: UVarInt,
+            direction: Byte,
+        }
+        SpawnEntityData {
+            id: Id,
+            /// Of the head
+            x: UVarInt,
+            /// Of the head
+            y: UVarInt,
+            direction: Byte,
+            /// Of the player controlling the snake, for the label
+            username: StringProto,
+        }
+        RemoveEntitiesData {
+            entities: PrefixedArray<Id>,
+        }
+        UpdateEntityPositionAndDirectionData {
+            id: Id,
+            /// Of the head
+            x: UVarInt,
+            /// Of the head
+            y: UVarInt,
+            direction: Byte,
+        }
+        AppleSpawnButchData {}
+        ChatBroadcastData {
+            /// Username of the author, empty for announcements of the server
+            sender: StringProto,
+            /// Filtered by the server, shown as it is
+            message: StringProto,
+        }
+        /// A row of `LeaderboardData`
+        LeaderboardEntryData {
+            id: Id,
+            username: StringProto,
+            /// In tiles
+            length: UVarInt,
+            apples: UVarInt,
+            kills: UVarInt,
+            /// Ticks since the snake spawned
+            survived: UVarInt,
+        }
+        LeaderboardData {
+            /// Best first, at most `LEADERBOARD_SIZE`.
+            /// `length` is in tiles, `survived` in ticks since the spawn.
+            entries: PrefixedArray<LeaderboardEntryData>,
+        }
+        SetDrawDistancePlayData {}
+        /// Shared by the `Disconnect` packets of all stages
+        DisconnectData {
+            /// Human-readable, shown to the player
+            reason: StringProto,
+        }
+    }
+
+    packets {
+        LoginServerbound {
+            /// Initiating dialogue with the server
+            /// and providing basic information
+            Login = 0 => Login(LoginData),
+        }
+
+        ConfigureServerbound {
+            /// The package needed for the server to calculate
+            /// the number of chunks that need to be sent to the client.
+            ///
+            /// **TODO:** It is assumed that later I will be able
+            /// to calculate the window size on the client and send
+            /// this value to the server at the `Configure` and `Play`
+            /// stages when the window size changes.
+            ///
+            /// *window - refers to the size of a regular terminal/stdout window.
+            SetDrawDistance = 0 => SetDrawDistanceConfigure(SetDrawDistanceConfigureData),
+        }
+
+        PlayServerbound {
+            /// Sent by the client in order to turn the snake.
+            /// Only a few turns per tick are accepted, the server drops
+            /// invalid ones and kicks clients that keep sending them.
+            TurnSnake = 0 => TurnSnake(TurnSnakeData),
+
+            /// A line for the chat, only with `Feature::Chat`.
+            /// The server drops messages sent too often and
+            /// tells the author why.
+            ChatMessage = 1 => ChatMessage(ChatMessageData),
+        }
+
+        LoginClientbound {
+            /// The package is required to switch
+            /// the state to the `Configuration` stage.
+            LoginSuccess = 0 => LoginSuccess(LoginSuccessData),
+
+            /// The server refuses the login, the connection is closed after it.
+            Disconnect = 1 => DisconnectLogin(DisconnectData),
+        }
+
+        ConfigureClientbound {
+            /// The package is required to switch
+            /// the state to the `Play` stage.
+            ConfigureAcknowledged = 0 => ConfigureAcknowledged(ConfigureAcknowledgedData),
+
+            /// Dimensions of the world and of its chunks.
+            /// Must be sent before `ConfigureAcknowledged`, because the client
+            /// can't place received chunks without knowing their size.
+            WorldInfo = 1 => WorldInfo(WorldInfoData),
+
+            /// The server closes the connection, e.g. because it shuts down.
+            Disconnect = 2 => DisconnectConfigure(DisconnectData),
+
+            /// Sent right after `LoginSuccess` if `Feature::Compression`
+            /// was negotiated, as the first packet of `Configure`. Every later
+            /// frame in both directions carries the compression flag, see
+            /// `protocol::compression`.
+            ///
+            /// The server reads frames with the flag from the moment it sends
+            /// this packet, so the client must not send anything after `Login`
+            /// until it has received it. A frame pipelined before would be
+            /// read with the wrong header.
+            SetCompression = 3 => SetCompression(SetCompressionData),
+        }
+
+        PlayClientbound {
+            /// For the client *(player)*, all other players are Entities.
+            /// They have their own ID, which the player's client can see,
+            /// but the player does not know their own ID, nor does it
+            /// know the IDs when sending TurnSnake packets.
+            SynchonizeSnakePositionAndDirection = 0
+                => SynchonizePositionAndDirection(SynchonizePositionAndDirectionData),
+
+            /// The package is sent to the client if another player (entity) enters their loading zone.
+            ///
+            /// For now the whole arena is the loading zone: a client entering
+            /// `Play` gets one for every snake, and the others get one for its snake.
+            SpawnEntity = 1 => SpawnEntity(SpawnEntityData),
+
+            /// Remove entities by provide prefiexed array of id's
+            ///
+            /// Sent to everybody in `Play` when snakes die, their own player included.
+            /// The player stays in `Play` without a snake, turns are ignored.
+            RemoveEntities = 2 => RemoveEntities(RemoveEntitiesData),
+
+            /// Applies to all players except oneself.
+            ///
+            /// Sent every tick the entity moves. A lost update is not resent,
+            /// the next one replaces it anyway.
+            UpdateEntityPositionAndDirection = 3
+                => UpdateEntityPositionAndDirection(UpdateEntityPositionAndDirectionData)
+                via Unreliable,
+
+            /// When another player (entity, snake) dies for any reason,
+            /// all the apples they have eaten fall into the game world.
+            /// This package is needed to deliver information about this
+            /// to customers in a compact form.
+            AppleSpawnButch = 4 => AppleSpawnButch(AppleSpawnButchData),
+
+            /// The package needed for the server to calculate
+            /// the number of chunks that need to be sent to the client.
+            ///
+            /// **TODO:** It is assumed that later I will be able
+            /// to calculate the window size on the client and send
+            /// this value to the server at the `Configure` and `Play`
+            /// stages when the window size changes.
+            ///
+            /// *window - refers to the size of a regular terminal/stdout window.
+            SetDrawDistance = 5 => SetDrawDistancePlay(SetDrawDistancePlayData),
+
+            /// The server closes the connection, e.g. because it shuts down.
+            /// With the reconnect token from `LoginSuccess` the player
+            /// can resume controlling the same snake for a few seconds.
+            Disconnect = 6 => DisconnectPlay(DisconnectData),
+
+            /// A chat line for everybody with `Feature::Chat`: a message
+            /// of a player, or an announcement of the server (joins, deaths).
+            /// Replies to a dropped `ChatMessage` go to its author only.
+            ChatBroadcast = 7 => ChatBroadcast(ChatBroadcastData),
+
+            /// The best players, every second for everybody with
+            /// `Feature::Leaderboard`. Ranked by kills, then length,
+            /// then survival time. Replaces the previous one as a whole.
+            Leaderboard = 8 => Leaderboard(LeaderboardData),
+        }
+    }
 }
-pub struct RemoveEntitiesData {
-    pub entities: PrefixedArray<Id>,
+
+/// The reference of all packets, as in `docs/protocol.md`
+pub fn protocol_markdown() -> String {
+    spec::markdown(PROTOCOL_VERSION, &packet_docs())
 }
-pub struct UpdateEntityPositionAndDirectionData {}
-pub struct AppleSpawnButchData {}
-pub struct SetDrawDistancePlayData {}
-// -- Packet payloads end --
-
-/*
-struct Login {
-    username: String,
+
+impl From<&World> for WorldInfoData {
+    fn from(world: &World) -> Self {
+        WorldInfoData {
+            width: UVarInt(world.width),
+            height: UVarInt(world.height),
+            chunk_width: UVarInt(world.chunk_size.width),
+            chunk_height: UVarInt(world.chunk_size.height),
+        }
+    }
 }
 
-struct LoginSuccess;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// talk position of player
-struct SynchonizePositionAndDirection {
-    x: u32, // 1-4 bytes
-    y: u32, // 1-4 bytes
-    direction: Direction // 1 bytes always
-}
+    #[test]
+    fn packet_encode_id_and_payload() -> Result<(), ProtocolError> {
+        let data = DisconnectData {
+            reason: StringProto("bye".to_string()),
+        };
+        let packet = DisconnectPlay::encode(&data)?;
+
+        let mut reader = &packet[..];
+        assert_eq!(
+            VarInt::decode(&mut reader)?.0,
+            PlayClientbound::Disconnect as i32
+        );
+        assert_eq!(DisconnectData::decode(&mut reader)?.reason.0, "bye");
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn packet_decode_error_context() -> Result<(), ProtocolError> {
+        let data = WorldInfoData {
+            width: UVarInt(64),
+            height: UVarInt(64),
+            chunk_width: UVarInt(16),
+            chunk_height: UVarInt(300),
+        };
+        let mut packet = WorldInfo::encode(&data)?;
+        // The second byte of `chunk_height` is missing
+        packet.pop();
+
+        let err = match WorldInfo::decode(&packet, &DecodeContext::DEFAULT) {
+            Ok(_) => panic!("truncated packet was decoded"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            err.path(),
+            Some("ConfigureClientbound::WorldInfo.chunk_height")
+        );
+        assert_eq!(err.offset(), Some(packet.len()));
+        assert!(matches!(err.root(), ProtocolError::Io(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn packet_decode_fail_wrong_id() -> Result<(), ProtocolError> {
+        let packet = LoginSuccess::encode(&LoginSuccessData {
+            reconnect_token: ReconnectToken::from(0),
+            features: Features::none(),
+        })?;
 
-struct SpawnSnake {
-    id: u64 // 4-8 bytes
-    x:  u32 // 1-4 bytes
-    y:  u32 // 1 - 4
-    direction: Direction // 1
-} // 7-17 bytes
-
-struct RemoveSnake {
-    array_of_ids: Array<id>
-} // id = u64 = 4 bytes +-
-
-struct UpdateApple {
-    pos_x: u32 //(1-4 bytes)
-    pos_y: u32 //(1-4 bytes)
-    state: //Eaten or Spawn 0 or 1 (1 bytes)
-} // 3-9 bytes
-
-struct AppleSpawnButch {
-    pos_x: u32,
-    pos_y: u32,
-    directions: Vec<u8> // 2 bits = 1 flag Direction
+        let err = match DisconnectLogin::decode(&packet, &DecodeContext::DEFAULT) {
+            Ok(_) => panic!("packet with a wrong id was decoded"),
+            Err(err) => err,
+        };
+        assert!(matches!(
+            err.violation(),
+            Some(ProtocolViolation::UnknownPacket)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "LoginClientbound::Disconnect at byte 1: unknown or unexpected packet"
+        );
+        Ok(())
+    }
 }
-*/
@@ -0,0 +1,353 @@
+//! The `protocol!` macro: one declarative description of the protocol,
+//! from which the packet ids, payload structs, their `Codec`s
+//! and the documentation are generated.
+//!
+//! ```ignore
+//! protocol! {
+//!     payloads {
+//!         /// Doc of the payload
+//!         WorldInfoData {
+//!             /// Doc of the field
+//!             width: UVarInt,
+//!             height: UVarInt,
+//!         }
+//!     }
+//!     packets {
+//!         /// Doc of the stage and direction
+//!         ConfigureClientbound {
+//!             /// Doc of the packet
+//!             WorldInfo = 1 => WorldInfo(WorldInfoData),
+//!             /// Packets are reliable unless marked otherwise
+//!             Position = 2 => Position(PositionData) via Unreliable,
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! For every payload it generates the struct with public fields and a
+//! `Codec` that writes the fields in the declared order. Decoding errors
+//! get the name of the failed field as context. Every field type must
+//! implement `Codec` and `Layout`. Payloads implement both themselves,
+//! so one payload can be a field of another, e.g. the entries of a list.
+//!
+//! For every group of packets it generates the id enum, and for every
+//! packet a marker struct with its `Packet` impl. Payloads may be shared
+//! by several packets.
+//!
+//! `packet_docs()` returns the description for `markdown`.
+//! `visit_packets()` calls a `PacketVisitor` with every packet and
+//! `PACKET_COUNT` is their amount, so checks over all packets, like the
+//! fuzz target, can't miss a new one.
+
+use protocol::layout::Layout;
+
+use crate::net::{datagram::Channel, packets::Packet};
+
+/// A payload generated by `protocol!`
+pub trait Payload {
+    const NAME: &'static str;
+
+    fn fields() -> Vec<FieldDoc>;
+}
+
+pub struct FieldDoc {
+    pub name: &'static str,
+    /// The type as written in the spec, e.g. `ReconnectToken`
+    pub ty: &'static str,
+    /// See `Layout`
+    pub layout: String,
+    pub doc: &'static str,
+}
+
+pub struct PacketDoc {
+    pub id: i32,
+    pub variant: &'static str,
+    pub marker: &'static str,
+    pub payload: &'static str,
+    pub channel: Channel,
+    pub doc: &'static str,
+    pub fields: Vec<FieldDoc>,
+}
+
+/// Packets of one stage and direction, e.g. `LoginServerbound`
+pub struct GroupDoc {
+    pub name: &'static str,
+    pub doc: &'static str,
+    pub packets: Vec<PacketDoc>,
+}
+
+/// Generic over the packet, unlike a closure
+pub trait PacketVisitor {
+    fn visit<P: Packet>(&mut self);
+}
+
+/// Makes the layout available to the generated docs without
+/// requiring the field types to be known at the macro definition
+pub fn layout_of<T: Layout>() -> String {
+    T::layout()
+}
+
+macro_rules! protocol {
+    (
+        payloads {
+            $(
+                $(#[doc = $payload_doc:literal])*
+                $payload:ident {
+                    $(
+                        $(#[doc = $field_doc:literal])*
+                        $field:ident : $field_ty:ty
+                    ),* $(,)?
+                }
+            )*
+        }
+        packets {
+            $(
+                $(#[doc = $group_doc:literal])*
+                $group:ident {
+                    $(
+                        $(#[doc = $packet_doc:literal])*
+                        $variant:ident = $id:literal => $marker:ident($data:ident)
+                            $(via $channel:ident)?
+                    ),* $(,)?
+                }
+            )*
+        }
+    ) => {
+        $(
+            $(#[doc = $payload_doc])*
+            pub struct $payload {
+                $(
+                    $(#[doc = $field_doc])*
+                    pub $field: $field_ty,
+                )*
+            }
+
+            impl Codec for $payload {
+                #[allow(unused_variables)]
+                fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+                    $( self.$field.encode(writer)?; )*
+                    Ok(())
+                }
+
+                fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+                    Self::decode_with(reader, &DecodeContext::DEFAULT)
+                }
+
+                #[allow(unused_variables)]
+                fn decode_with(
+                    reader: &mut impl Buffer,
+                    ctx: &DecodeContext,
+                ) -> Result<Self, ProtocolError> {
+                    Ok($payload {
+                        $(
+                            $field: <$field_ty as Codec>::decode_with(reader, ctx)
+                                .context(stringify!($field))?,
+                        )*
+                    })
+                }
+            }
+
+            impl ::protocol::layout::Layout for $payload {
+                fn layout() -> String {
+                    $crate::net::spec::nested_layout::<$payload>()
+                }
+            }
+
+            impl $crate::net::spec::Payload for $payload {
+                const NAME: &'static str = stringify!($payload);
+
+                fn fields() -> Vec<$crate::net::spec::FieldDoc> {
+                    vec![
+                        $(
+                            $crate::net::spec::FieldDoc {
+                                name: stringify!($field),
+                                ty: stringify!($field_ty),
+                                layout: $crate::net::spec::layout_of::<$field_ty>(),
+                                doc: concat!($($field_doc, "\n"),*),
+                            },
+                        )*
+                    ]
+                }
+            }
+        )*
+
+        $(
+            $(#[doc = $group_doc])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub enum $group {
+                $(
+                    $(#[doc = $packet_doc])*
+                    $variant = $id,
+                )*
+            }
+
+            $(
+                $(#[doc = $packet_doc])*
+                pub struct $marker;
+
+                impl Packet for $marker {
+                    const ID: i32 = $group::$variant as i32;
+                    const NAME: &'static str = concat!(stringify!($group), "::", stringify!($variant));
+                    type Data = $data;
+                    $(
+                        const CHANNEL: $crate::net::datagram::Channel =
+                            $crate::net::datagram::Channel::$channel;
+                    )?
+                }
+            )*
+        )*
+
+        /// Amount of packets in all groups
+        pub const PACKET_COUNT: usize = [$($(stringify!($marker),)*)*].len();
+
+        /// Calls `visitor` with every packet, in the order of the spec
+        pub fn visit_packets(visitor: &mut impl $crate::net::spec::PacketVisitor) {
+            $($(visitor.visit::<$marker>();)*)*
+        }
+
+        /// Description of every packet, in the order of the spec
+        pub fn packet_docs() -> Vec<$crate::net::spec::GroupDoc> {
+            use $crate::net::spec::Payload;
+
+            vec![
+                $(
+                    $crate::net::spec::GroupDoc {
+                        name: stringify!($group),
+                        doc: concat!($($group_doc, "\n"),*),
+                        packets: vec![
+                            $(
+                                $crate::net::spec::PacketDoc {
+                                    id: $id,
+                                    variant: stringify!($variant),
+                                    marker: stringify!($marker),
+                                    payload: <$data as Payload>::NAME,
+                                    channel: <$marker as Packet>::CHANNEL,
+                                    doc: concat!($($packet_doc, "\n"),*),
+                                    fields: <$data as Payload>::fields(),
+                                },
+                            )*
+                        ],
+                    },
+                )*
+            ]
+        }
+    };
+}
+
+pub(crate) use protocol;
+
+/// The layout of a payload used as a field: its fields in order
+pub fn nested_layout<P: Payload>() -> String {
+    let fields: Vec<String> = P::fields()
+        .iter()
+        .map(|field| format!("{}: {}", field.name, field.ty))
+        .collect();
+    format!("{} {{ {} }}", P::NAME, fields.join(", "))
+}
+
+/// Joins the lines of a doc comment into one paragraph per blank line
+fn paragraphs(doc: &str) -> String {
+    doc.lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .split("\n\n")
+        .map(|paragraph| paragraph.split('\n').collect::<Vec<_>>().join(" "))
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Renders the packets as a Markdown reference
+pub fn markdown(version: u32, groups: &[GroupDoc]) -> String {
+    let mut out = String::new();
+    out.push_str("# Venomized protocol\n\n");
+    out.push_str(
+        "<!-- Generated from `common/src/net/packets.rs`, do not edit. \
+         Regenerate with `UPDATE_PROTOCOL_DOCS=1 cargo test -p common --test protocol_docs`. -->\n\n",
+    );
+    out.push_str(&format!("Protocol version: **{version}**\n\n"));
+    out.push_str(
+        "Every packet is sent in a frame: a `VarInt` length of the body, \
+         then the body. The body is the `VarInt` packet id followed by the payload fields \
+         in the listed order. The meaning of an id depends on the stage of the connection \
+         (`Login`, `Configure`, `Play`) and on the direction.\n\n",
+    );
+    out.push_str(
+        "After `SetCompression` the frame header gets a `u8` flag after the length, \
+         in the frames of the server after that packet and in all frames of the client \
+         after `Login`, which must wait for it. With `0` the packet follows as is. With `1` a `VarInt` uncompressed length follows, \
+         then the packet compressed with zlib. Receivers refuse to inflate bodies \
+         above their limit (8 MiB by default).\n\n",
+    );
+    out.push_str(
+        "Packets sent as a datagram travel outside of the stream, without frames \
+         and without compression: a `u32` big-endian tick, then the packet. \
+         They may be lost or reordered, receivers drop the ones older than the \
+         latest applied. Ticks start over when the server restarts, so receivers \
+         forget them at every `LoginSuccess`.\n\n",
+    );
+
+    for group in groups {
+        out.push_str(&format!("## {}\n\n", group.name));
+        let doc = paragraphs(group.doc);
+        if !doc.is_empty() {
+            out.push_str(&format!("{doc}\n\n"));
+        }
+
+        for packet in &group.packets {
+            out.push_str(&format!("### `0x{:02X}` {}\n\n", packet.id, packet.variant));
+            let doc = paragraphs(packet.doc);
+            if !doc.is_empty() {
+                out.push_str(&format!("{doc}\n\n"));
+            }
+            out.push_str(&format!(
+                "Marker `{}`, payload `{}`.",
+                packet.marker, packet.payload
+            ));
+            if packet.channel == Channel::Unreliable {
+                out.push_str(" Sent as a datagram.");
+            }
+            out.push_str("\n\n");
+
+            if packet.fields.is_empty() {
+                out.push_str("No fields.\n\n");
+                continue;
+            }
+            out.push_str("| Field | Type | Wire layout | Description |\n");
+            out.push_str("|---|---|---|---|\n");
+            for field in &packet.fields {
+                out.push_str(&format!(
+                    "| `{}` | `{}` | {} | {} |\n",
+                    field.name,
+                    field.ty,
+                    field.layout,
+                    paragraphs(field.doc)
+                        .replace("\n\n", "<br>")
+                        .replace('|', "\\|"),
+                ));
+            }
+            out.push('\n');
+        }
+    }
+
+    // Exactly one trailing newline
+    let len = out.trim_end().len();
+    out.truncate(len);
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraphs_join_lines() {
+        assert_eq!(
+            paragraphs(" First line\n second line\n\n Next one\n"),
+            "First line second line\n\nNext one"
+        );
+        assert_eq!(paragraphs(""), "");
+    }
+}
@@ -0,0 +1,79 @@
+//! Rules for the usernames sent in `Login`.
+//!
+//! Both sides use them: the client to refuse a bad name before connecting,
+//! the server because it never trusts the client. Names are shown above
+//! the snakes of other players, so they are short and plain ASCII.
+//! Uniqueness is up to the server, ignoring the case, so `Bob` and `bob`
+//! can't play at the same time.
+
+use std::fmt;
+
+pub const MIN_USERNAME_LEN: usize = 3;
+pub const MAX_USERNAME_LEN: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsernameError {
+    TooShort,
+    TooLong,
+    /// Only ASCII letters, digits, `_` and `-` are allowed
+    InvalidChar(char),
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsernameError::TooShort => {
+                write!(f, "shorter than {MIN_USERNAME_LEN} characters")
+            }
+            UsernameError::TooLong => write!(f, "longer than {MAX_USERNAME_LEN} characters"),
+            UsernameError::InvalidChar(c) => write!(f, "{c:?} is not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for UsernameError {}
+
+pub fn validate_username(username: &str) -> Result<(), UsernameError> {
+    if let Some(c) = username
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_' && *c != '-')
+    {
+        return Err(UsernameError::InvalidChar(c));
+    }
+    // All ASCII from here on, bytes are characters
+    match username.len() {
+        len if len < MIN_USERNAME_LEN => Err(UsernameError::TooShort),
+        len if len > MAX_USERNAME_LEN => Err(UsernameError::TooLong),
+        _ => Ok(()),
+    }
+}
+
+/// Whether two names belong to the same player
+pub fn same_username(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_rules() {
+        assert_eq!(validate_username("snake_42-x"), Ok(()));
+        assert_eq!(validate_username("ab"), Err(UsernameError::TooShort));
+        assert_eq!(
+            validate_username(&"a".repeat(MAX_USERNAME_LEN + 1)),
+            Err(UsernameError::TooLong)
+        );
+        assert_eq!(
+            validate_username("snake 1"),
+            Err(UsernameError::InvalidChar(' '))
+        );
+        // Looks like ASCII, but isn't
+        assert_eq!(
+            validate_username("snаke"),
+            Err(UsernameError::InvalidChar('а'))
+        );
+        assert!(same_username("Bob", "bOB"));
+    }
+}
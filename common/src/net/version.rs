@@ -0,0 +1,151 @@
+//! Protocol versioning and feature negotiation.
+//!
+//! The client announces its `PROTOCOL_VERSION` and the `Features` it
+//! understands in `Login`. The server refuses versions it can't speak,
+//! and answers with the features both sides support in `LoginSuccess`.
+//!
+//! A new packet type is rolled out as a feature: the server sends it
+//! only to clients that announced the feature, so older builds keep
+//! working without knowing the packet. A change that can't be expressed
+//! as a feature (a changed layout of an existing packet) bumps the version.
+
+use buffer::{Buffer, BufferMut};
+use protocol::{
+    codec::Codec, context::DecodeContext, error::ProtocolError, layout::Layout,
+    primitives::bitset::BitSet,
+};
+
+/// Version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The oldest version this build still understands.
+/// Version 2 added the username to `Login` and `SpawnEntity`.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+pub fn is_supported(version: u32) -> bool {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version)
+}
+
+/// Optional capabilities, the discriminant is the bit in `Features`.
+/// Discriminants must never be reused, even after a feature is removed.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// The client keeps the reconnect token of `LoginSuccess`
+    /// and resumes its snake after a short disconnect
+    SessionResume = 0,
+
+    /// Large frames are compressed after `SetCompression`
+    Compression = 1,
+
+    /// `ChatMessage` and `ChatBroadcast` in `Play`
+    Chat = 2,
+
+    /// `Leaderboard` in `Play`
+    Leaderboard = 3,
+}
+
+impl Feature {
+    /// Every feature known to this build
+    pub const ALL: [Feature; 4] = [
+        Feature::SessionResume,
+        Feature::Compression,
+        Feature::Chat,
+        Feature::Leaderboard,
+    ];
+}
+
+/// A set of features. Unknown bits from newer builds are kept while
+/// decoding and dropped by `intersection` with the local features.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Features(BitSet);
+
+impl Features {
+    pub fn none() -> Features {
+        Features::default()
+    }
+
+    /// Everything this build supports
+    pub fn all() -> Features {
+        Features::from_iter(Feature::ALL)
+    }
+
+    pub fn contains(&self, feature: Feature) -> bool {
+        self.0.get(feature as usize)
+    }
+
+    pub fn insert(&mut self, feature: Feature) {
+        self.0.set(feature as usize, true);
+    }
+
+    pub fn remove(&mut self, feature: Feature) {
+        self.0.set(feature as usize, false);
+    }
+
+    /// Features supported by both sides
+    pub fn intersection(&self, other: &Features) -> Features {
+        Features(self.0.iter().filter(|bit| other.0.get(*bit)).collect())
+    }
+}
+
+impl FromIterator<Feature> for Features {
+    fn from_iter<I: IntoIterator<Item = Feature>>(iter: I) -> Self {
+        Features(iter.into_iter().map(|feature| feature as usize).collect())
+    }
+}
+
+/// Same layout as `BitSet`
+impl Codec for Features {
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        self.0.encode(writer)
+    }
+
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(reader: &mut impl Buffer, ctx: &DecodeContext) -> Result<Self, ProtocolError> {
+        Ok(Features(BitSet::decode_with(reader, ctx)?))
+    }
+}
+
+impl Layout for Features {
+    fn layout() -> String {
+        BitSet::layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_support() {
+        assert!(is_supported(PROTOCOL_VERSION));
+        assert!(!is_supported(PROTOCOL_VERSION + 1));
+        assert!(!is_supported(MIN_PROTOCOL_VERSION - 1));
+    }
+
+    #[test]
+    fn features_intersection_drops_unknown() -> Result<(), ProtocolError> {
+        // A newer client with a feature this build doesn't know (bit 42)
+        let mut buf: Vec<u8> = Vec::new();
+        BitSet::from_iter([Feature::SessionResume as usize, 42]).encode(&mut buf)?;
+        let client = Features::decode(&mut &buf[..])?;
+
+        let negotiated = Features::all().intersection(&client);
+        assert_eq!(negotiated, Features::from_iter([Feature::SessionResume]));
+        assert!(Features::none().intersection(&client) == Features::none());
+        Ok(())
+    }
+
+    #[test]
+    fn features_insert_remove() {
+        let mut features = Features::none();
+        features.insert(Feature::SessionResume);
+        assert!(features.contains(Feature::SessionResume));
+
+        features.remove(Feature::SessionResume);
+        assert_eq!(features, Features::none());
+    }
+}
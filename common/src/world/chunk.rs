@@ -1,22 +1,46 @@
-use crate::world::types::{GridPos, HEIGHT, WIDTH};
+use protocol::{
+    codec::Codec,
+    error::{ProtocolError, ProtocolViolation},
+    primitives::byte::Byte,
+};
+
+use crate::world::types::{ChunkSize, GridPos};
 
 /// Enumeration for the deterministic designation of a cell and its state.
 /// An excellent solution for ensuring that the client knows
 /// how to render using any of the possible chars/methods.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
 pub enum Tile {
     /// An empty tile with nothing in it
-    Empty,
+    Empty = 0,
 
     /// The boundaries of the world,
     /// upon colliding with which
     /// the snake instantly perishes.
-    Wall,
+    Wall = 1,
 
     /// In a standard snake game,
     /// the snake grows as it eats apples,
     /// and it's the same here.
-    Apple,
+    Apple = 2,
+}
+
+impl Codec for Tile {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        Byte(*self as u8).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        match Byte::decode(reader)?.0 {
+            0 => Ok(Tile::Empty),
+            1 => Ok(Tile::Wall),
+            2 => Ok(Tile::Apple),
+            _ => Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownVariant,
+            )),
+        }
+    }
 }
 
 /// A structure to have associative меthods (without &mut self)
@@ -35,20 +59,19 @@ pub struct Chunk {
     /// Plus, Vec<Tile> won't be reallocated.
     /// It'll be created once and will be like a regular array, which is perfect
     pub grid: Vec<Tile>,
-    // Required in order to correctly iterate through a one-dimensional array
-    // pub width: u32, <- deprecated field, we already know is 16
+
+    /// Required in order to correctly iterate through a one-dimensional array.
+    /// Every chunk of the world shares the same size, see `World::chunk_size`.
+    pub size: ChunkSize,
 }
 
 // TODO: refactoring cuz new system
 impl Chunk {
     /// Generate game field with Tile::Wall along the edges of the map
     /// TODO: apple?
-    pub fn new() -> Chunk {
-        let grid = vec![Tile::Empty; (WIDTH * HEIGHT) as usize];
-        Chunk {
-            grid: grid,
-            // width: WIDTH, deprecated
-        }
+    pub fn new(size: ChunkSize) -> Chunk {
+        let grid = vec![Tile::Empty; size.area() as usize];
+        Chunk { grid, size }
         // TODO: some another logic, walls, basic apple's
     }
 
@@ -65,8 +88,8 @@ impl Chunk {
     }
     // -- Internal magic --
     fn pos(&self, pos: GridPos) -> Option<usize> {
-        if pos.x < WIDTH && pos.y < HEIGHT {
-            Some((pos.y * WIDTH + pos.x) as usize)
+        if pos.x < self.size.width && pos.y < self.size.height {
+            Some((pos.y * self.size.width + pos.x) as usize)
         } else {
             None
         }
@@ -78,12 +101,22 @@ impl Chunk {
 mod tests {
 
     use super::*;
+    use crate::world::types::DEFAULT_CHUNK_SIZE;
 
     #[test]
     fn chunk_new_success_size() {
-        let chunk = Chunk::new();
+        let chunk = Chunk::new(DEFAULT_CHUNK_SIZE);
+
+        assert_eq!(chunk.grid.len(), DEFAULT_CHUNK_SIZE.area() as usize)
+    }
+
+    #[test]
+    fn chunk_new_custom_size() {
+        let chunk = Chunk::new(ChunkSize::new(8, 4));
 
-        assert_eq!(chunk.grid.len(), (HEIGHT * WIDTH) as usize)
+        assert_eq!(chunk.grid.len(), 32);
+        assert_eq!(chunk.pos(GridPos { x: 7, y: 3 }), Some(31));
+        assert_eq!(chunk.pos(GridPos { x: 8, y: 0 }), None);
     }
 
     // TODO all
@@ -95,7 +128,7 @@ mod tests {
 
     #[test]
     fn test_pos_to_index_conversion() {
-        let chunk = Chunk::new();
+        let chunk = Chunk::new(DEFAULT_CHUNK_SIZE);
 
         assert_eq!(chunk.pos(GridPos { x: 0, y: 0 }), Some(0));
         assert_eq!(chunk.pos(GridPos { x: 9, y: 0 }), Some(9));
@@ -103,7 +136,7 @@ mod tests {
 
     #[test]
     fn chunk_pos_fail() {
-        let chunk = Chunk::new();
+        let chunk = Chunk::new(DEFAULT_CHUNK_SIZE);
 
         assert_eq!(chunk.pos(GridPos { x: 17, y: 5 }), None);
     }
@@ -0,0 +1,456 @@
+//! Seeded procedural generation of arenas.
+//!
+//! The generator produces a `Map`, the same intermediate representation
+//! the map loader uses, so a generated arena goes through `Map::into_world`
+//! just like a hand-drawn one.
+//!
+//! Structures are placed in a fixed order: rooms, maze corridors, and
+//! finally noise-based rocks which top the arena up to the requested
+//! obstacle density. After that, every empty pocket that can't be reached
+//! from the biggest open area is filled with walls, so no player can spawn
+//! or find an apple in a place nobody else can get to.
+
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::world::{
+    chunk::Tile,
+    map::{Map, MapError},
+    types::GridPos,
+    world::{MAX_WORLD_AREA, WorldError},
+};
+
+/// Parameters of the generated arena.
+/// The same config with the same seed always produces the same arena.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub seed: u64,
+
+    /// Target share of wall tiles, from `0.0` to `1.0`.
+    /// Filling unreachable pockets may push the result slightly above it.
+    pub obstacle_density: f32,
+
+    /// Amount of hollow rectangular rooms with doorways
+    pub rooms: u32,
+
+    /// Amount of maze regions with one-tile corridors
+    pub mazes: u32,
+
+    /// Size of rock clusters in tiles, bigger values give smoother blobs
+    pub rock_scale: u32,
+
+    /// Surround the arena with walls
+    pub border: bool,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            seed: 0,
+            obstacle_density: 0.2,
+            rooms: 4,
+            mazes: 1,
+            rock_scale: 6,
+            border: true,
+        }
+    }
+}
+
+pub struct Generator {
+    config: GeneratorConfig,
+    rng: StdRng,
+    width: u32,
+    height: u32,
+    tiles: Vec<Tile>,
+}
+
+impl Generator {
+    /// Fails for arenas without tiles and for more than `MAX_WORLD_AREA` tiles,
+    /// the same limit as the world they end up in
+    pub fn new(width: u32, height: u32, config: GeneratorConfig) -> Result<Generator, MapError> {
+        if width == 0 || height == 0 {
+            return Err(MapError::Empty);
+        }
+        let area = width
+            .checked_mul(height)
+            .filter(|area| *area <= MAX_WORLD_AREA)
+            .ok_or(MapError::World(WorldError::TooLarge))?;
+
+        Ok(Generator {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            width,
+            height,
+            tiles: vec![Tile::Empty; area as usize],
+        })
+    }
+
+    /// Shortcut for `Generator::new(..)?.generate()`
+    pub fn generate_map(width: u32, height: u32, config: GeneratorConfig) -> Result<Map, MapError> {
+        Ok(Generator::new(width, height, config)?.generate())
+    }
+
+    pub fn generate(mut self) -> Map {
+        if self.config.border {
+            self.place_border();
+        }
+        for _ in 0..self.config.rooms {
+            self.place_room();
+        }
+        for _ in 0..self.config.mazes {
+            self.place_maze();
+        }
+        self.place_rocks();
+        self.fill_unreachable();
+
+        Map {
+            width: self.width,
+            height: self.height,
+            tiles: self.tiles,
+            spawn_points: Vec::new(),
+        }
+    }
+
+    // -- Structures --
+    fn place_border(&mut self) {
+        for x in 0..self.width {
+            self.set(x, 0, Tile::Wall);
+            self.set(x, self.height - 1, Tile::Wall);
+        }
+        for y in 0..self.height {
+            self.set(0, y, Tile::Wall);
+            self.set(self.width - 1, y, Tile::Wall);
+        }
+    }
+
+    /// A hollow rectangle with one doorway on every side
+    fn place_room(&mut self) {
+        let Some((x0, y0, w, h)) = self.random_rect(5, 14) else {
+            return;
+        };
+        let (x1, y1) = (x0 + w - 1, y0 + h - 1);
+
+        for x in x0..=x1 {
+            self.set(x, y0, Tile::Wall);
+            self.set(x, y1, Tile::Wall);
+        }
+        for y in y0..=y1 {
+            self.set(x0, y, Tile::Wall);
+            self.set(x1, y, Tile::Wall);
+        }
+
+        // Doorways are never in the corners, otherwise they'd lead nowhere
+        let top = self.rng.gen_range(x0 + 1..x1);
+        let bottom = self.rng.gen_range(x0 + 1..x1);
+        let left = self.rng.gen_range(y0 + 1..y1);
+        let right = self.rng.gen_range(y0 + 1..y1);
+        self.set(top, y0, Tile::Empty);
+        self.set(bottom, y1, Tile::Empty);
+        self.set(x0, left, Tile::Empty);
+        self.set(x1, right, Tile::Empty);
+    }
+
+    /// A maze carved by a randomized depth-first search.
+    /// Cells sit on odd offsets inside the region, walls are between them.
+    fn place_maze(&mut self) {
+        let Some((x0, y0, w, h)) = self.random_rect(7, 21) else {
+            return;
+        };
+        let (cells_w, cells_h) = ((w - 1) / 2, (h - 1) / 2);
+
+        for y in y0..y0 + cells_h * 2 + 1 {
+            for x in x0..x0 + cells_w * 2 + 1 {
+                self.set(x, y, Tile::Wall);
+            }
+        }
+
+        let cell_pos = |cx: u32, cy: u32| (x0 + cx * 2 + 1, y0 + cy * 2 + 1);
+        let mut visited = vec![false; (cells_w * cells_h) as usize];
+        let mut stack = vec![(0u32, 0u32)];
+        visited[0] = true;
+        let (x, y) = cell_pos(0, 0);
+        self.set(x, y, Tile::Empty);
+
+        while let Some(&(cx, cy)) = stack.last() {
+            let mut neighbours = Vec::with_capacity(4);
+            if cx > 0 {
+                neighbours.push((cx - 1, cy));
+            }
+            if cy > 0 {
+                neighbours.push((cx, cy - 1));
+            }
+            if cx + 1 < cells_w {
+                neighbours.push((cx + 1, cy));
+            }
+            if cy + 1 < cells_h {
+                neighbours.push((cx, cy + 1));
+            }
+            neighbours.retain(|&(nx, ny)| !visited[(ny * cells_w + nx) as usize]);
+
+            if neighbours.is_empty() {
+                stack.pop();
+                continue;
+            }
+
+            let (nx, ny) = neighbours[self.rng.gen_range(0..neighbours.len())];
+            visited[(ny * cells_w + nx) as usize] = true;
+
+            let (from_x, from_y) = cell_pos(cx, cy);
+            let (to_x, to_y) = cell_pos(nx, ny);
+            self.set((from_x + to_x) / 2, (from_y + to_y) / 2, Tile::Empty);
+            self.set(to_x, to_y, Tile::Empty);
+            stack.push((nx, ny));
+        }
+
+        // Entrances in the middle of the left and right sides
+        let (_, entrance_y) = cell_pos(0, cells_h / 2);
+        self.set(x0, entrance_y, Tile::Empty);
+        self.set(x0 + cells_w * 2, entrance_y, Tile::Empty);
+    }
+
+    /// Value noise thresholded so that the arena reaches `obstacle_density`
+    fn place_rocks(&mut self) {
+        let total = self.tiles.len();
+        let target = (self.config.obstacle_density.clamp(0.0, 1.0) * total as f32) as usize;
+        let walls = self
+            .tiles
+            .iter()
+            .filter(|tile| **tile == Tile::Wall)
+            .count();
+        if walls >= target {
+            return;
+        }
+
+        let scale = self.config.rock_scale.max(1);
+        let lattice_w = self.width / scale + 2;
+        let lattice_h = self.height / scale + 2;
+        let lattice: Vec<f32> = (0..lattice_w * lattice_h)
+            .map(|_| self.rng.r#gen::<f32>())
+            .collect();
+
+        // (noise, index) of every empty tile, the noisiest become rocks
+        let mut candidates: Vec<(f32, usize)> = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y * self.width + x) as usize;
+                if self.tiles[index] != Tile::Empty {
+                    continue;
+                }
+                let noise = Self::sample(&lattice, lattice_w, x, y, scale);
+                candidates.push((noise, index));
+            }
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for (_, index) in candidates.into_iter().take(target - walls) {
+            self.tiles[index] = Tile::Wall;
+        }
+    }
+
+    /// Bilinear interpolation between the lattice points around the tile
+    fn sample(lattice: &[f32], lattice_w: u32, x: u32, y: u32, scale: u32) -> f32 {
+        let (lx, ly) = (x / scale, y / scale);
+        let tx = (x % scale) as f32 / scale as f32;
+        let ty = (y % scale) as f32 / scale as f32;
+        let at = |lx: u32, ly: u32| lattice[(ly * lattice_w + lx) as usize];
+
+        let top = at(lx, ly) * (1.0 - tx) + at(lx + 1, ly) * tx;
+        let bottom = at(lx, ly + 1) * (1.0 - tx) + at(lx + 1, ly + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    /// Keeps the biggest open area and walls off everything else
+    fn fill_unreachable(&mut self) {
+        let mut component = vec![usize::MAX; self.tiles.len()];
+        let mut sizes: Vec<usize> = Vec::new();
+
+        for start in 0..self.tiles.len() {
+            if self.tiles[start] == Tile::Wall || component[start] != usize::MAX {
+                continue;
+            }
+            let id = sizes.len();
+            sizes.push(self.flood(start, id, &mut component));
+        }
+
+        let Some(biggest) = (0..sizes.len()).max_by_key(|&id| sizes[id]) else {
+            return;
+        };
+        for (index, tile) in self.tiles.iter_mut().enumerate() {
+            if *tile != Tile::Wall && component[index] != biggest {
+                *tile = Tile::Wall;
+            }
+        }
+    }
+
+    /// Marks the area around `start` with `id`, returns the amount of tiles in it
+    fn flood(&self, start: usize, id: usize, component: &mut [usize]) -> usize {
+        let mut queue = VecDeque::from([start]);
+        component[start] = id;
+        let mut size = 0;
+
+        while let Some(index) = queue.pop_front() {
+            size += 1;
+            let pos = GridPos {
+                x: index as u32 % self.width,
+                y: index as u32 / self.width,
+            };
+            for next in self.neighbours(&pos) {
+                if self.tiles[next] != Tile::Wall && component[next] == usize::MAX {
+                    component[next] = id;
+                    queue.push_back(next);
+                }
+            }
+        }
+        size
+    }
+    // -- Structures end --
+
+    // -- Internal magic --
+    fn set(&mut self, x: u32, y: u32, tile: Tile) {
+        if x < self.width && y < self.height {
+            self.tiles[(y * self.width + x) as usize] = tile;
+        }
+    }
+
+    fn neighbours(&self, pos: &GridPos) -> impl Iterator<Item = usize> + use<> {
+        let (x, y, width, height) = (pos.x, pos.y, self.width, self.height);
+        [
+            (x > 0).then(|| (x - 1, y)),
+            (y > 0).then(|| (x, y - 1)),
+            (x + 1 < width).then_some((x + 1, y)),
+            (y + 1 < height).then_some((x, y + 1)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(move |(x, y)| (y * width + x) as usize)
+    }
+
+    /// Random rectangle `(x, y, width, height)` inside the border
+    /// with sides in `min..=max`, `None` if the arena is too small
+    fn random_rect(&mut self, min: u32, max: u32) -> Option<(u32, u32, u32, u32)> {
+        let inner_w = self.width.checked_sub(2)?;
+        let inner_h = self.height.checked_sub(2)?;
+        if inner_w < min || inner_h < min {
+            return None;
+        }
+
+        let w = self.rng.gen_range(min..=max.min(inner_w));
+        let h = self.rng.gen_range(min..=max.min(inner_h));
+        let x = self.rng.gen_range(1..=1 + inner_w - w);
+        let y = self.rng.gen_range(1..=1 + inner_h - h);
+        Some((x, y, w, h))
+    }
+    // -- Internal magic end --
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    fn config(seed: u64) -> GeneratorConfig {
+        GeneratorConfig {
+            seed,
+            ..GeneratorConfig::default()
+        }
+    }
+
+    fn walls(map: &Map) -> usize {
+        map.tiles.iter().filter(|tile| **tile == Tile::Wall).count()
+    }
+
+    #[test]
+    fn generator_same_seed_same_arena() {
+        let a = Generator::generate_map(64, 64, config(42)).unwrap();
+        let b = Generator::generate_map(64, 64, config(42)).unwrap();
+        let c = Generator::generate_map(64, 64, config(43)).unwrap();
+
+        assert_eq!(a.tiles, b.tiles);
+        assert_ne!(a.tiles, c.tiles);
+    }
+
+    #[test]
+    fn generator_every_empty_tile_reachable() {
+        for seed in 0..20 {
+            let map = Generator::generate_map(64, 48, config(seed)).unwrap();
+            let generator = Generator {
+                tiles: map.tiles.clone(),
+                ..Generator::new(64, 48, config(seed)).unwrap()
+            };
+
+            let mut component = vec![usize::MAX; map.tiles.len()];
+            let start = map
+                .tiles
+                .iter()
+                .position(|tile| *tile != Tile::Wall)
+                .unwrap();
+            let reached = generator.flood(start, 0, &mut component);
+
+            assert_eq!(reached, map.tiles.len() - walls(&map), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn generator_obstacle_density() {
+        let map = Generator::generate_map(
+            128,
+            128,
+            GeneratorConfig {
+                obstacle_density: 0.3,
+                ..config(7)
+            },
+        )
+        .unwrap();
+        let density = walls(&map) as f32 / map.tiles.len() as f32;
+
+        assert!(density >= 0.3, "density {density}");
+        assert!(density < 0.4, "density {density}");
+    }
+
+    #[test]
+    fn generator_border() {
+        let map = Generator::generate_map(32, 32, config(1)).unwrap();
+
+        for x in 0..32 {
+            assert_eq!(map.tiles[x], Tile::Wall);
+            assert_eq!(map.tiles[31 * 32 + x], Tile::Wall);
+        }
+    }
+
+    #[test]
+    fn generator_tiny_arena() {
+        // Too small for any structure, but must not panic
+        let map = Generator::generate_map(4, 4, config(3)).unwrap();
+
+        assert_eq!(map.tiles.len(), 16);
+
+        let map = Generator::generate_map(1, 1, config(3)).unwrap();
+        assert_eq!(map.tiles, [Tile::Wall]);
+    }
+
+    #[test]
+    fn generator_fail_empty() {
+        assert_matches!(
+            Generator::generate_map(0, 32, config(1)),
+            Err(MapError::Empty)
+        );
+        assert_matches!(
+            Generator::generate_map(32, 0, config(1)),
+            Err(MapError::Empty)
+        );
+    }
+
+    #[test]
+    fn generator_fail_too_large() {
+        // The area doesn't even fit into u32
+        assert_matches!(
+            Generator::generate_map(65536, 65536, config(1)),
+            Err(MapError::World(WorldError::TooLarge))
+        );
+        assert_matches!(
+            Generator::generate_map(8192, 4096, config(1)),
+            Err(MapError::World(WorldError::TooLarge))
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Loading of hand-made world layouts.
+//!
+//! The plain-text format is a grid of symbols, one line per row:
+//!
+//! ```text
+//! ################
+//! #S............@#
+//! #....####......#
+//! ...
+//! ```
+//!
+//! - `#` - wall
+//! - `.` - empty tile
+//! - `@` - apple
+//! - `S` - spawn point (an empty tile where new snakes may appear)
+//!
+//! With the `png` feature, the same layout can be drawn as an image,
+//! where every pixel is one tile (see `Map::from_png` for the palette).
+//!
+//! The dimensions of the map must be multiples of the chunk size,
+//! because the map is mapped one-to-one into the chunks of a `World`.
+
+use std::{fs, io, path::Path};
+
+use crate::world::{
+    chunk::Tile,
+    types::{ChunkSize, GridPos},
+    world::{World, WorldError},
+};
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(io::Error),
+
+    /// The map has no rows or no columns
+    Empty,
+
+    /// A row has a different length than the first one
+    RaggedRow {
+        row: u32,
+    },
+
+    /// A symbol (or pixel color) that doesn't mean anything
+    UnknownSymbol {
+        symbol: char,
+        pos: GridPos,
+    },
+
+    /// The map doesn't fit into whole chunks or is too large
+    World(WorldError),
+
+    #[cfg(feature = "png")]
+    Png(png::DecodingError),
+}
+
+impl From<io::Error> for MapError {
+    fn from(value: io::Error) -> Self {
+        MapError::Io(value)
+    }
+}
+
+impl From<WorldError> for MapError {
+    fn from(value: WorldError) -> Self {
+        MapError::World(value)
+    }
+}
+
+#[cfg(feature = "png")]
+impl From<png::DecodingError> for MapError {
+    fn from(value: png::DecodingError) -> Self {
+        MapError::Png(value)
+    }
+}
+
+/// An intermediate representation of a loaded layout,
+/// independent of the chunk size it will be split into.
+#[derive(Debug)]
+pub struct Map {
+    pub width: u32,
+    pub height: u32,
+
+    /// Row-major tiles, the same index arithmetic as in `Chunk`
+    pub tiles: Vec<Tile>,
+
+    pub spawn_points: Vec<GridPos>,
+}
+
+impl Map {
+    pub const WALL: char = '#';
+    pub const EMPTY: char = '.';
+    pub const APPLE: char = '@';
+    pub const SPAWN: char = 'S';
+
+    /// Parses the plain-text map format.
+    /// Trailing empty lines and `\r` from Windows line endings are ignored.
+    pub fn from_ascii(text: &str) -> Result<Map, MapError> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .collect();
+        let rows = match rows.iter().rposition(|row| !row.is_empty()) {
+            Some(last) => &rows[..=last],
+            None => return Err(MapError::Empty),
+        };
+
+        let width = rows[0].chars().count() as u32;
+        let height = rows.len() as u32;
+        let mut tiles = Vec::with_capacity(width as usize * height as usize);
+        let mut spawn_points = Vec::new();
+
+        for (y, row) in rows.iter().enumerate() {
+            let y = y as u32;
+            if row.chars().count() as u32 != width {
+                return Err(MapError::RaggedRow { row: y });
+            }
+
+            for (x, symbol) in row.chars().enumerate() {
+                let pos = GridPos { x: x as u32, y };
+                let tile = match symbol {
+                    Self::WALL => Tile::Wall,
+                    Self::EMPTY => Tile::Empty,
+                    Self::APPLE => Tile::Apple,
+                    Self::SPAWN => {
+                        spawn_points.push(pos);
+                        Tile::Empty
+                    }
+                    _ => return Err(MapError::UnknownSymbol { symbol, pos }),
+                };
+                tiles.push(tile);
+            }
+        }
+
+        Ok(Map {
+            width,
+            height,
+            tiles,
+            spawn_points,
+        })
+    }
+
+    pub fn load_ascii(path: impl AsRef<Path>) -> Result<Map, MapError> {
+        Map::from_ascii(&fs::read_to_string(path)?)
+    }
+
+    /// Decodes a map drawn as an image, one pixel per tile:
+    ///
+    /// - black `#000000` - wall
+    /// - white `#FFFFFF` - empty tile
+    /// - red `#FF0000` - apple
+    /// - green `#00FF00` - spawn point
+    ///
+    /// Alpha is ignored. Any other color is reported as
+    /// `MapError::UnknownSymbol` with `?` as the symbol.
+    #[cfg(feature = "png")]
+    pub fn from_png(reader: impl io::Read) -> Result<Map, MapError> {
+        let mut decoder = png::Decoder::new(reader);
+        // Palette and 16-bit images are normalized to 8-bit channels
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        let (width, height) = (info.width, info.height);
+        if width == 0 || height == 0 {
+            return Err(MapError::Empty);
+        }
+        let channels = info.color_type.samples();
+
+        let mut tiles = Vec::with_capacity(width as usize * height as usize);
+        let mut spawn_points = Vec::new();
+
+        for y in 0..height {
+            let row = &buf[(y as usize * info.line_size)..][..info.line_size];
+            for x in 0..width {
+                let pixel = &row[(x as usize * channels)..][..channels];
+                let rgb = match info.color_type {
+                    png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+                        (pixel[0], pixel[0], pixel[0])
+                    }
+                    _ => (pixel[0], pixel[1], pixel[2]),
+                };
+
+                let pos = GridPos { x, y };
+                let tile = match rgb {
+                    (0, 0, 0) => Tile::Wall,
+                    (255, 255, 255) => Tile::Empty,
+                    (255, 0, 0) => Tile::Apple,
+                    (0, 255, 0) => {
+                        spawn_points.push(pos);
+                        Tile::Empty
+                    }
+                    _ => return Err(MapError::UnknownSymbol { symbol: '?', pos }),
+                };
+                tiles.push(tile);
+            }
+        }
+
+        Ok(Map {
+            width,
+            height,
+            tiles,
+            spawn_points,
+        })
+    }
+
+    #[cfg(feature = "png")]
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Map, MapError> {
+        Map::from_png(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Picks the format by the file extension: `.png` images
+    /// (with the `png` feature), plain text otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Map, MapError> {
+        let path = path.as_ref();
+        #[cfg(feature = "png")]
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        {
+            return Map::load_png(path);
+        }
+        Map::load_ascii(path)
+    }
+
+    /// Maps the layout into the chunks of a new `World`
+    pub fn into_world(self, chunk_size: ChunkSize) -> Result<World, MapError> {
+        let mut world = World::new(self.width, self.height, chunk_size)?;
+
+        for (index, tile) in self.tiles.into_iter().enumerate() {
+            let pos = GridPos {
+                x: index as u32 % self.width,
+                y: index as u32 / self.width,
+            };
+            // Can't fail, the world has exactly the same dimensions
+            world.set_tile(&pos, tile);
+        }
+        world.spawn_points = self.spawn_points;
+
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::types::ChunkSize;
+    use assert_matches::assert_matches;
+
+    const ARENA: &str = "\
+########
+#S....@#
+#..##..#
+########
+";
+
+    #[test]
+    fn map_from_ascii_success() -> Result<(), MapError> {
+        let map = Map::from_ascii(ARENA)?;
+
+        assert_eq!(map.width, 8);
+        assert_eq!(map.height, 4);
+        assert_eq!(map.tiles.len(), 32);
+        assert_eq!(map.tiles[0], Tile::Wall);
+        assert_eq!(map.tiles[8 + 6], Tile::Apple);
+        assert_eq!(map.spawn_points, vec![GridPos { x: 1, y: 1 }]);
+        // Spawn point itself is an empty tile
+        assert_eq!(map.tiles[8 + 1], Tile::Empty);
+        Ok(())
+    }
+
+    #[test]
+    fn map_from_ascii_crlf() -> Result<(), MapError> {
+        let map = Map::from_ascii("##\r\n.@\r\n\r\n")?;
+
+        assert_eq!((map.width, map.height), (2, 2));
+        assert_eq!(
+            map.tiles,
+            vec![Tile::Wall, Tile::Wall, Tile::Empty, Tile::Apple]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn map_from_ascii_fail() {
+        assert_matches!(Map::from_ascii("\n\n"), Err(MapError::Empty));
+        assert_matches!(
+            Map::from_ascii("###\n##\n"),
+            Err(MapError::RaggedRow { row: 1 })
+        );
+        assert_matches!(
+            Map::from_ascii("#.\n.x\n"),
+            Err(MapError::UnknownSymbol {
+                symbol: 'x',
+                pos: GridPos { x: 1, y: 1 }
+            })
+        );
+    }
+
+    #[test]
+    fn map_into_world_success() -> Result<(), MapError> {
+        let world = Map::from_ascii(ARENA)?.into_world(ChunkSize::new(4, 2))?;
+
+        assert_eq!(world.chunks.len(), 4);
+        assert_eq!(world.get_tile(&GridPos { x: 6, y: 1 }), Some(&Tile::Apple));
+        assert_eq!(world.get_tile(&GridPos { x: 4, y: 2 }), Some(&Tile::Wall));
+        assert_eq!(world.get_tile(&GridPos { x: 5, y: 2 }), Some(&Tile::Empty));
+        assert_eq!(world.spawn_points, vec![GridPos { x: 1, y: 1 }]);
+        Ok(())
+    }
+
+    #[test]
+    fn map_into_world_fail_chunk_size() -> Result<(), MapError> {
+        let res = Map::from_ascii(ARENA)?.into_world(ChunkSize::new(16, 16));
+
+        assert_matches!(
+            res,
+            Err(MapError::World(WorldError::NotMultipleOfChunkSize))
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn map_from_png_success() -> Result<(), MapError> {
+        let pixels: [[u8; 3]; 4] = [[0, 0, 0], [255, 255, 255], [255, 0, 0], [0, 255, 0]];
+        let mut image = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut image, 2, 2);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(pixels.as_flattened()).unwrap();
+        }
+
+        let map = Map::from_png(&image[..])?;
+
+        assert_eq!(
+            map.tiles,
+            vec![Tile::Wall, Tile::Empty, Tile::Apple, Tile::Empty]
+        );
+        assert_eq!(map.spawn_points, vec![GridPos { x: 1, y: 1 }]);
+        Ok(())
+    }
+}
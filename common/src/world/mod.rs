@@ -1,3 +1,6 @@
 pub mod chunk;
+pub mod generator;
+pub mod map;
 pub mod types;
+#[allow(clippy::module_inception)]
 pub mod world;
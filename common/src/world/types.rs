@@ -1,8 +1,65 @@
+use protocol::{codec::Codec, error::ProtocolError, primitives::uvarint::UVarInt};
+
 #[derive(PartialEq, Clone, Debug)]
 pub struct GridPos {
     pub x: u32,
     pub y: u32,
 }
 
-pub const HEIGHT: u32 = 16;
-pub const WIDTH: u32 = 16;
+/// Dimensions of a single chunk in tiles.
+///
+/// The chunk size is a property of the `World`, so the server can
+/// run with 8x8 or 32x32 chunks without recompiling, and clients
+/// learn about it during the `Configure` stage.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct ChunkSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ChunkSize {
+    pub const fn new(width: u32, height: u32) -> ChunkSize {
+        ChunkSize { width, height }
+    }
+
+    /// Total amount of tiles in one chunk
+    pub const fn area(&self) -> u32 {
+        self.width * self.height
+    }
+}
+
+impl Default for ChunkSize {
+    fn default() -> Self {
+        DEFAULT_CHUNK_SIZE
+    }
+}
+
+pub const DEFAULT_CHUNK_SIZE: ChunkSize = ChunkSize::new(16, 16);
+
+impl Codec for GridPos {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        UVarInt(self.x).encode(writer)?;
+        UVarInt(self.y).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Ok(GridPos {
+            x: UVarInt::decode(reader)?.0,
+            y: UVarInt::decode(reader)?.0,
+        })
+    }
+}
+
+impl Codec for ChunkSize {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        UVarInt(self.width).encode(writer)?;
+        UVarInt(self.height).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Ok(ChunkSize {
+            width: UVarInt::decode(reader)?.0,
+            height: UVarInt::decode(reader)?.0,
+        })
+    }
+}
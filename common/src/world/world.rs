@@ -1,15 +1,32 @@
 use std::collections::HashMap;
 
+use protocol::{
+    codec::Codec,
+    error::{ProtocolError, ProtocolViolation},
+    primitives::{prefixed_array::PrefixedArray, uvarint::UVarInt},
+};
+
 use crate::world::{
-    chunk::Chunk,
-    types::{GridPos, HEIGHT, WIDTH},
+    chunk::{Chunk, Tile},
+    types::{ChunkSize, GridPos},
 };
 
 #[derive(Debug)]
 pub enum WorldError {
-    NotMultipleOf16Error,
+    /// One of the chunk dimensions is zero
+    InvalidChunkSize,
+
+    /// The world dimensions can't be split into whole chunks
+    NotMultipleOfChunkSize,
+
+    /// More than `MAX_WORLD_AREA` tiles
+    TooLarge,
 }
 
+/// Most tiles a world may have, guards the allocation
+/// against dimensions from a corrupt snapshot or map
+pub const MAX_WORLD_AREA: u32 = 4096 * 4096;
+
 pub type ChunkId = u32; // Remove magic numbers
 
 type ChunkMap = HashMap<ChunkId, Chunk>;
@@ -30,60 +47,156 @@ pub struct World {
     /// Global height of the playing field
     pub height: u32,
 
+    /// Size of every chunk in the world, sent to clients during `Configure`
+    pub chunk_size: ChunkSize,
+
     pub chunks: ChunkMap,
+
+    /// Fixed positions where new snakes may appear.
+    /// Empty for generated worlds, filled in by the map loader.
+    pub spawn_points: Vec<GridPos>,
 }
 
 impl World {
-    /// #### Important! The dimensions of the world must be multiples of 16.
+    /// #### Important! The dimensions of the world must be multiples of the chunk size.
     ///
-    /// This is because one chunk = 16x16, so if we make the `width` 256 and
-    /// the `height` 128, everything will be fine, but if we make it 255 and
-    /// 127, there will be an error.
-    pub fn new(width: u32, height: u32) -> Result<World, WorldError> {
-        if width % WIDTH == 0 && height % HEIGHT == 0 {
+    /// With 16x16 chunks, if we make the `width` 256 and the `height` 128,
+    /// everything will be fine, but if we make it 255 and 127, there will be an error.
+    pub fn new(width: u32, height: u32, chunk_size: ChunkSize) -> Result<World, WorldError> {
+        if chunk_size.width == 0 || chunk_size.height == 0 {
+            return Err(WorldError::InvalidChunkSize);
+        }
+        if width
+            .checked_mul(height)
+            .is_none_or(|area| area > MAX_WORLD_AREA)
+        {
+            return Err(WorldError::TooLarge);
+        }
+
+        if width.is_multiple_of(chunk_size.width) && height.is_multiple_of(chunk_size.height) {
             let mut chunks: ChunkMap = HashMap::new();
 
-            let chunks_total = (width / WIDTH) * (height / HEIGHT);
+            let chunks_total = (width / chunk_size.width) * (height / chunk_size.height);
 
             for i in 0..chunks_total {
-                let chunk = Chunk::new();
+                let chunk = Chunk::new(chunk_size);
                 chunks.insert(i, chunk);
             }
             Ok(World {
-                width: width,
-                height: height,
-                chunks: chunks,
+                width,
+                height,
+                chunk_size,
+                chunks,
+                spawn_points: Vec::new(),
             })
         } else {
-            Err(WorldError::NotMultipleOf16Error)
+            Err(WorldError::NotMultipleOfChunkSize)
         }
     }
 
+    /// Amount of chunks in one row of the world
+    pub fn chunks_per_row(&self) -> u32 {
+        self.width / self.chunk_size.width
+    }
+
+    /// Total amount of chunks in the world
+    pub fn chunks_total(&self) -> u32 {
+        self.chunks_per_row() * (self.height / self.chunk_size.height)
+    }
+
     /// Returns the `ChunkId` based on global coordinates.
     /// Note: This method now takes an immutable reference `&self`
     /// as it only needs to read the world's dimensions.
+    /// The position must be inside the world, see `get_local_pos`.
     pub fn chunk_at(&self, global_pos: &GridPos) -> ChunkId {
-        let chunks_per_row = self.width / WIDTH;
-        let chunk_x = global_pos.x / WIDTH;
-        let chunk_y = global_pos.y / HEIGHT;
+        let chunk_x = global_pos.x / self.chunk_size.width;
+        let chunk_y = global_pos.y / self.chunk_size.height;
+
+        chunk_y * self.chunks_per_row() + chunk_x
+    }
+
+    /// Provides the local location on the grid based on global coordinates,
+    /// together with the chunk the location belongs to.
+    /// Returns `None` if the position is outside the world.
+    pub fn get_local_pos(&self, global_pos: &GridPos) -> Option<(ChunkId, GridPos)> {
+        if global_pos.x >= self.width || global_pos.y >= self.height {
+            return None;
+        }
+
+        let local = GridPos {
+            x: global_pos.x % self.chunk_size.width,
+            y: global_pos.y % self.chunk_size.height,
+        };
+        Some((self.chunk_at(global_pos), local))
+    }
 
-        chunk_y * chunks_per_row + chunk_x
+    pub fn get_tile(&self, global_pos: &GridPos) -> Option<&Tile> {
+        let (chunk_id, local) = self.get_local_pos(global_pos)?;
+        self.chunks.get(&chunk_id)?.get_tile(local)
     }
 
-    // Provides the local location on the grid based on global coordinates
-    /* pub fn get_local_pos(&mut self, global_pos: GridPos) -> (GridPos, Chunk) {
-        // TODO: temp
-    } */
+    pub fn set_tile(&mut self, global_pos: &GridPos, tile: Tile) -> Option<()> {
+        let (chunk_id, local) = self.get_local_pos(global_pos)?;
+        self.chunks.get_mut(&chunk_id)?.set_tile(local, tile)
+    }
+}
+
+/// Chunks are written in the order of their ids, tile by tile,
+/// so the decoder doesn't need to store the ids themselves.
+impl Codec for World {
+    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
+        UVarInt(self.width).encode(writer)?;
+        UVarInt(self.height).encode(writer)?;
+        self.chunk_size.encode(writer)?;
+
+        for chunk_id in 0..self.chunks_total() {
+            let chunk = self
+                .chunks
+                .get(&chunk_id)
+                .ok_or(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::InvalidValue,
+                ))?;
+            for tile in chunk.grid.iter() {
+                tile.encode(writer)?;
+            }
+        }
+
+        PrefixedArray::from(self.spawn_points.clone()).encode(writer)
+    }
+
+    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        let width = UVarInt::decode(reader)?.0;
+        let height = UVarInt::decode(reader)?.0;
+        let chunk_size = ChunkSize::decode(reader)?;
+
+        let mut world = World::new(width, height, chunk_size)
+            .map_err(|_| ProtocolError::ProtocolViolation(ProtocolViolation::InvalidValue))?;
+
+        for chunk_id in 0..world.chunks_total() {
+            // Always exists, the world was just created with these dimensions
+            if let Some(chunk) = world.chunks.get_mut(&chunk_id) {
+                for tile in chunk.grid.iter_mut() {
+                    *tile = Tile::decode(reader)?;
+                }
+            }
+        }
+
+        let spawn_points: PrefixedArray<GridPos> = PrefixedArray::decode(reader)?;
+        world.spawn_points = spawn_points.data;
+
+        Ok(world)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::world::types::DEFAULT_CHUNK_SIZE;
     use assert_matches::assert_matches;
 
     #[test]
     fn world_new_success() -> Result<(), WorldError> {
-        let world = World::new(256, 256)?;
+        let world = World::new(256, 256, DEFAULT_CHUNK_SIZE)?;
 
         assert_eq!(world.chunks.len(), 256);
 
@@ -92,10 +205,121 @@ mod tests {
 
     #[test]
     fn world_new_fail_error() {
-        let world = World::new(257, 256);
+        let world = World::new(257, 256, DEFAULT_CHUNK_SIZE);
 
-        assert_matches!(world, Err(WorldError::NotMultipleOf16Error));
+        assert_matches!(world, Err(WorldError::NotMultipleOfChunkSize));
     }
+
+    #[test]
+    fn world_new_fail_too_large() {
+        // The area overflows `u32`
+        let world = World::new(1 << 16, 1 << 16, DEFAULT_CHUNK_SIZE);
+        assert_matches!(world, Err(WorldError::TooLarge));
+
+        let world = World::new(8192, 4096, DEFAULT_CHUNK_SIZE);
+        assert_matches!(world, Err(WorldError::TooLarge));
+    }
+
+    #[test]
+    fn world_new_fail_zero_chunk_size() {
+        let world = World::new(256, 256, ChunkSize::new(0, 16));
+
+        assert_matches!(world, Err(WorldError::InvalidChunkSize));
+    }
+
+    #[test]
+    fn world_new_custom_chunk_size() -> Result<(), WorldError> {
+        // 8x8 chunks: 64/8=8 in a row, 32/8=4 rows
+        let world = World::new(64, 32, ChunkSize::new(8, 8))?;
+
+        assert_eq!(world.chunks.len(), 32);
+        assert_eq!(world.chunks_total(), 32);
+        assert_eq!(world.chunk_at(&GridPos { x: 9, y: 0 }), 1);
+        assert_eq!(world.chunk_at(&GridPos { x: 0, y: 8 }), 8);
+        assert_eq!(world.chunk_at(&GridPos { x: 63, y: 31 }), 31);
+
+        // 255 is not a multiple of 32
+        assert_matches!(
+            World::new(255, 64, ChunkSize::new(32, 32)),
+            Err(WorldError::NotMultipleOfChunkSize)
+        );
+
+        Ok(())
+    }
+    #[test]
+    fn world_get_set_tile() -> Result<(), WorldError> {
+        let mut world = World::new(32, 32, DEFAULT_CHUNK_SIZE)?;
+        let pos = GridPos { x: 17, y: 20 };
+
+        assert_eq!(world.get_local_pos(&pos), Some((3, GridPos { x: 1, y: 4 })));
+        assert_eq!(world.set_tile(&pos, Tile::Wall), Some(()));
+        assert_eq!(world.get_tile(&pos), Some(&Tile::Wall));
+        assert_eq!(
+            world.chunks[&3].get_tile(GridPos { x: 1, y: 4 }),
+            Some(&Tile::Wall)
+        );
+
+        assert_eq!(world.get_tile(&GridPos { x: 32, y: 0 }), None);
+        assert_eq!(world.set_tile(&GridPos { x: 0, y: 32 }, Tile::Wall), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn world_roundtrip() -> Result<(), ProtocolError> {
+        let mut world = World::new(32, 16, ChunkSize::new(8, 8)).unwrap();
+        world.set_tile(&GridPos { x: 0, y: 0 }, Tile::Wall);
+        world.set_tile(&GridPos { x: 31, y: 15 }, Tile::Apple);
+        world.spawn_points.push(GridPos { x: 4, y: 4 });
+
+        let mut writer: Vec<u8> = Vec::new();
+        world.encode(&mut writer)?;
+        let mut buf = &writer[..];
+        let world_trip = World::decode(&mut buf)?;
+
+        assert_eq!((world_trip.width, world_trip.height), (32, 16));
+        assert_eq!(world_trip.chunk_size, ChunkSize::new(8, 8));
+        for (chunk_id, chunk) in world.chunks.iter() {
+            assert_eq!(world_trip.chunks[chunk_id].grid, chunk.grid);
+        }
+        assert_eq!(world_trip.spawn_points, world.spawn_points);
+        Ok(())
+    }
+
+    #[test]
+    fn world_decode_fail_invalid_dimensions() -> Result<(), ProtocolError> {
+        let mut writer: Vec<u8> = Vec::new();
+        UVarInt(30).encode(&mut writer)?;
+        UVarInt(16).encode(&mut writer)?;
+        ChunkSize::new(8, 8).encode(&mut writer)?;
+        let mut buf = &writer[..];
+
+        assert_matches!(
+            World::decode(&mut buf),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::InvalidValue
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn world_decode_fail_too_large() -> Result<(), ProtocolError> {
+        let mut writer: Vec<u8> = Vec::new();
+        UVarInt(u32::MAX).encode(&mut writer)?;
+        UVarInt(u32::MAX).encode(&mut writer)?;
+        ChunkSize::new(1, 1).encode(&mut writer)?;
+        let mut buf = &writer[..];
+
+        assert_matches!(
+            World::decode(&mut buf),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::InvalidValue
+            ))
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_chunk_at_logic() {
         // 1. Make the world 64x32 tiles.
@@ -105,7 +329,7 @@ mod tests {
         // It's look like:
         // [0][1][2][3]
         // [4][5][6][7]
-        let world = World::new(64, 32).unwrap();
+        let world = World::new(64, 32, DEFAULT_CHUNK_SIZE).unwrap();
 
         // 2. Checking boundary and internal points for different chunks.
 
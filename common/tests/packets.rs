@@ -0,0 +1,248 @@
+//! Property tests of every packet that has a payload codec:
+//! payloads survive a roundtrip through `Packet::encode`,
+//! and arbitrary input never makes the decoder panic.
+//!
+//! The same properties are checked by the `packets` fuzz target in `/fuzz`.
+
+use common::net::packets::{
+    AppleSpawnButch, AppleSpawnButchData, ChatBroadcast, ChatBroadcastData, ChatMessage,
+    ChatMessageData, ConfigureAcknowledged, ConfigureAcknowledgedData, DisconnectConfigure,
+    DisconnectData, DisconnectLogin, DisconnectPlay, Leaderboard, LeaderboardData,
+    LeaderboardEntryData, Login, LoginData, LoginSuccess, LoginSuccessData, PACKET_COUNT, Packet,
+    ReconnectToken, RemoveEntities, RemoveEntitiesData, SetCompression, SetCompressionData,
+    SetDrawDistanceConfigure, SetDrawDistanceConfigureData, SetDrawDistancePlay,
+    SetDrawDistancePlayData, SpawnEntity, SpawnEntityData, SynchonizePositionAndDirection,
+    SynchonizePositionAndDirectionData, TurnSnake, TurnSnakeData, UpdateEntityPositionAndDirection,
+    UpdateEntityPositionAndDirectionData, WorldInfo, WorldInfoData, packet_docs, visit_packets,
+};
+use common::net::spec::PacketVisitor;
+use common::net::version::{Feature, Features};
+use proptest::prelude::*;
+use protocol::{
+    codec::Codec,
+    error::ProtocolError,
+    primitives::{
+        byte::Byte, prefixed_array::PrefixedArray, string::StringProto, uvarint::UVarInt,
+        varint::VarInt, varlong::VarLong,
+    },
+};
+
+fn fail(err: ProtocolError) -> TestCaseError {
+    TestCaseError::fail(format!("{err:?}"))
+}
+
+/// Payloads have no `PartialEq`, but the encodings are canonical,
+/// so equal bytes after the roundtrip mean equal payloads.
+fn check_roundtrip<P: Packet>(data: &P::Data) -> Result<(), TestCaseError> {
+    let packet = P::encode(data).map_err(fail)?;
+
+    let mut reader = &packet[..];
+    prop_assert_eq!(VarInt::decode(&mut reader).map_err(fail)?.0, P::ID);
+    let decoded = P::Data::decode(&mut reader).map_err(fail)?;
+    prop_assert!(reader.is_empty(), "{} bytes are left", reader.len());
+
+    prop_assert_eq!(P::encode(&decoded).map_err(fail)?, packet);
+    Ok(())
+}
+
+/// Whatever the decoder accepts must be encoded back into the same bytes
+fn check_decoded<P: Packet>(data: &[u8]) -> Result<(), TestCaseError> {
+    let mut reader = data;
+    let Ok(payload) = P::Data::decode(&mut reader) else {
+        return Ok(());
+    };
+    let consumed = &data[..data.len() - reader.len()];
+
+    let mut buf = Vec::new();
+    payload.encode(&mut buf).map_err(fail)?;
+    prop_assert_eq!(buf, consumed);
+    Ok(())
+}
+
+/// `check_decoded` of every packet in the spec, the first failure is kept
+struct CheckDecoded<'a> {
+    data: &'a [u8],
+    checked: usize,
+    result: Result<(), TestCaseError>,
+}
+
+impl PacketVisitor for CheckDecoded<'_> {
+    fn visit<P: Packet>(&mut self) {
+        if self.result.is_ok() {
+            self.result = check_decoded::<P>(self.data);
+        }
+        self.checked += 1;
+    }
+}
+
+fn features(resume: bool) -> Features {
+    match resume {
+        true => Features::from_iter([Feature::SessionResume]),
+        false => Features::none(),
+    }
+}
+
+proptest! {
+    #[test]
+    fn login_roundtrip(
+        version: u32,
+        name in ".{0,32}",
+        username in ".{0,32}",
+        auth_token in ".{0,32}",
+        resume: bool,
+        token: i64,
+    ) {
+        check_roundtrip::<Login>(&LoginData {
+            protocol_version: UVarInt(version),
+            client_name: StringProto(name),
+            username: StringProto(username),
+            auth_token: StringProto(auth_token),
+            features: features(resume),
+            reconnect_token: ReconnectToken::from(token),
+        })?;
+    }
+
+    #[test]
+    fn login_success_roundtrip(token: i64, resume: bool) {
+        check_roundtrip::<LoginSuccess>(&LoginSuccessData {
+            reconnect_token: ReconnectToken::from(token),
+            features: features(resume),
+        })?;
+    }
+
+    #[test]
+    fn world_info_roundtrip(width: u32, height: u32, chunk_width: u32, chunk_height: u32) {
+        check_roundtrip::<WorldInfo>(&WorldInfoData {
+            width: UVarInt(width),
+            height: UVarInt(height),
+            chunk_width: UVarInt(chunk_width),
+            chunk_height: UVarInt(chunk_height),
+        })?;
+    }
+
+    #[test]
+    fn set_compression_roundtrip(threshold: u32) {
+        check_roundtrip::<SetCompression>(&SetCompressionData {
+            threshold: UVarInt(threshold),
+        })?;
+    }
+
+    #[test]
+    fn turn_snake_roundtrip(direction: u8, x: u32, y: u32) {
+        check_roundtrip::<TurnSnake>(&TurnSnakeData {
+            direction: Byte(direction),
+            x: UVarInt(x),
+            y: UVarInt(y),
+        })?;
+    }
+
+    #[test]
+    fn spawn_entity_roundtrip(id: i64, x: u32, y: u32, direction: u8, username in ".{0,32}") {
+        check_roundtrip::<SpawnEntity>(&SpawnEntityData {
+            id: id.into(),
+            x: UVarInt(x),
+            y: UVarInt(y),
+            direction: Byte(direction),
+            username: StringProto(username),
+        })?;
+    }
+
+    #[test]
+    fn positions_roundtrip(id: i64, x: u32, y: u32, direction: u8) {
+        check_roundtrip::<SynchonizePositionAndDirection>(&SynchonizePositionAndDirectionData {
+            x: UVarInt(x),
+            y: UVarInt(y),
+            direction: Byte(direction),
+        })?;
+        check_roundtrip::<UpdateEntityPositionAndDirection>(
+            &UpdateEntityPositionAndDirectionData {
+                id: id.into(),
+                x: UVarInt(x),
+                y: UVarInt(y),
+                direction: Byte(direction),
+            },
+        )?;
+    }
+
+    #[test]
+    fn remove_entities_roundtrip(ids in prop::collection::vec(any::<i64>(), 0..16)) {
+        let ids = ids.into_iter().map(VarLong).collect::<Vec<_>>();
+        check_roundtrip::<RemoveEntities>(&RemoveEntitiesData {
+            entities: PrefixedArray::from(ids),
+        })?;
+    }
+
+    #[test]
+    fn chat_roundtrip(sender in ".{0,32}", message in ".{0,64}") {
+        check_roundtrip::<ChatMessage>(&ChatMessageData {
+            message: StringProto(message.clone()),
+        })?;
+        check_roundtrip::<ChatBroadcast>(&ChatBroadcastData {
+            sender: StringProto(sender),
+            message: StringProto(message),
+        })?;
+    }
+
+    #[test]
+    fn leaderboard_roundtrip(
+        entries in prop::collection::vec((any::<i64>(), "[a-z]{3,16}", any::<[u32; 4]>()), 0..10)
+    ) {
+        let entries = entries
+            .into_iter()
+            .map(|(id, username, [length, apples, kills, survived])| LeaderboardEntryData {
+                id: VarLong(id),
+                username: StringProto(username),
+                length: UVarInt(length),
+                apples: UVarInt(apples),
+                kills: UVarInt(kills),
+                survived: UVarInt(survived),
+            })
+            .collect::<Vec<_>>();
+        check_roundtrip::<Leaderboard>(&LeaderboardData {
+            entries: PrefixedArray::from(entries),
+        })?;
+    }
+
+    #[test]
+    fn disconnect_roundtrip(reason in ".{0,64}") {
+        let data = DisconnectData {
+            reason: StringProto(reason),
+        };
+        check_roundtrip::<DisconnectLogin>(&data)?;
+        check_roundtrip::<DisconnectConfigure>(&data)?;
+        check_roundtrip::<DisconnectPlay>(&data)?;
+    }
+
+    #[test]
+    fn decode_arbitrary_bytes(data in prop::collection::vec(any::<u8>(), 0..64)) {
+        let mut check = CheckDecoded {
+            data: &data,
+            checked: 0,
+            result: Ok(()),
+        };
+        visit_packets(&mut check);
+        check.result?;
+    }
+}
+
+#[test]
+fn every_packet_visited() {
+    let mut check = CheckDecoded {
+        data: &[],
+        checked: 0,
+        result: Ok(()),
+    };
+    visit_packets(&mut check);
+
+    let documented: usize = packet_docs().iter().map(|group| group.packets.len()).sum();
+    assert_eq!(check.checked, PACKET_COUNT);
+    assert_eq!(check.checked, documented);
+}
+
+#[test]
+fn empty_payloads_roundtrip() -> Result<(), TestCaseError> {
+    check_roundtrip::<SetDrawDistanceConfigure>(&SetDrawDistanceConfigureData {})?;
+    check_roundtrip::<ConfigureAcknowledged>(&ConfigureAcknowledgedData {})?;
+    check_roundtrip::<AppleSpawnButch>(&AppleSpawnButchData {})?;
+    check_roundtrip::<SetDrawDistancePlay>(&SetDrawDistancePlayData {})
+}
@@ -0,0 +1,26 @@
+//! Keeps `docs/protocol.md` in sync with the spec in `net::packets`.
+//!
+//! Regenerate after changing the spec:
+//! `UPDATE_PROTOCOL_DOCS=1 cargo test -p common --test protocol_docs`
+
+use std::{fs, path::Path};
+
+use common::net::packets::protocol_markdown;
+
+#[test]
+fn protocol_docs_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../docs/protocol.md");
+    let generated = protocol_markdown();
+
+    if std::env::var_os("UPDATE_PROTOCOL_DOCS").is_some() {
+        fs::write(&path, &generated).unwrap();
+        return;
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        committed == generated,
+        "docs/protocol.md is outdated, regenerate it with \
+         `UPDATE_PROTOCOL_DOCS=1 cargo test -p common --test protocol_docs`"
+    );
+}
@@ -0,0 +1,314 @@
+//! A growable byte buffer with separate read and write cursors.
+//!
+//! Bytes are appended at the writer index and consumed from the reader
+//! index, so a frame that arrives in several pieces can be accumulated
+//! in place and parsed once it is complete:
+//!
+//! ```text
+//! +-------------------+------------------+------------------+
+//! | discardable bytes |  readable bytes  |  writable bytes  |
+//! +-------------------+------------------+------------------+
+//! 0          reader_index        writer_index         capacity
+//! ```
+//!
+//! `ByteBuf` implements `Read` and `Write`, so all readers and writers of
+//! `Buffer`/`BufferMut` (and every `Codec` on top of them) work with it.
+//! `mark`/`reset` undo a read that turned out to be premature.
+
+use std::io::{self, Read, Write};
+
+use crate::error::BufferError;
+
+/// Generates a reader of a number that doesn't move the reader index
+macro_rules! peek_fn {
+    ($name:ident, $ty:ty, $from:ident) => {
+        pub fn $name(&self) -> Result<$ty, BufferError> {
+            let bytes = self.peek_slice(size_of::<$ty>())?;
+            // Can't fail, the length was checked by `peek_slice`
+            Ok(<$ty>::$from(bytes.try_into().unwrap()))
+        }
+    };
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ByteBuf {
+    /// Everything up to `data.len()` is written, so `data.len()`
+    /// is the writer index
+    data: Vec<u8>,
+    reader_index: usize,
+    marked_index: usize,
+}
+
+impl ByteBuf {
+    pub fn new() -> ByteBuf {
+        ByteBuf::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> ByteBuf {
+        ByteBuf {
+            data: Vec::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    pub fn reader_index(&self) -> usize {
+        self.reader_index
+    }
+
+    pub fn writer_index(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Amount of written, but not yet read bytes
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.reader_index
+    }
+
+    pub fn has_remaining(&self) -> bool {
+        self.remaining() > 0
+    }
+
+    /// The readable bytes, without moving the reader index
+    pub fn readable(&self) -> &[u8] {
+        &self.data[self.reader_index..]
+    }
+
+    /// Remembers the reader index for a later `reset`
+    pub fn mark(&mut self) {
+        self.marked_index = self.reader_index;
+    }
+
+    /// Moves the reader index back to the last `mark`
+    /// (or to the start, if there was no mark)
+    pub fn reset(&mut self) {
+        self.reader_index = self.marked_index;
+    }
+
+    /// Skips `n` readable bytes
+    pub fn advance(&mut self, n: usize) -> Result<(), BufferError> {
+        self.check_remaining(n)?;
+        self.reader_index += n;
+        Ok(())
+    }
+
+    /// Borrows the next `n` bytes without copying and moves past them
+    pub fn read_slice(&mut self, n: usize) -> Result<&[u8], BufferError> {
+        self.check_remaining(n)?;
+        let start = self.reader_index;
+        self.reader_index += n;
+        Ok(&self.data[start..self.reader_index])
+    }
+
+    /// Borrows the next `n` bytes without moving the reader index
+    pub fn peek_slice(&self, n: usize) -> Result<&[u8], BufferError> {
+        self.check_remaining(n)?;
+        Ok(&self.readable()[..n])
+    }
+
+    pub fn peek_u8(&self) -> Result<u8, BufferError> {
+        Ok(self.peek_slice(1)?[0])
+    }
+
+    pub fn peek_i8(&self) -> Result<i8, BufferError> {
+        Ok(self.peek_u8()? as i8)
+    }
+
+    peek_fn!(peek_u16_be, u16, from_be_bytes);
+    peek_fn!(peek_u16_le, u16, from_le_bytes);
+    peek_fn!(peek_i16_be, i16, from_be_bytes);
+    peek_fn!(peek_i16_le, i16, from_le_bytes);
+    peek_fn!(peek_u32_be, u32, from_be_bytes);
+    peek_fn!(peek_u32_le, u32, from_le_bytes);
+    peek_fn!(peek_i32_be, i32, from_be_bytes);
+    peek_fn!(peek_i32_le, i32, from_le_bytes);
+    peek_fn!(peek_u64_be, u64, from_be_bytes);
+    peek_fn!(peek_u64_le, u64, from_le_bytes);
+    peek_fn!(peek_i64_be, i64, from_be_bytes);
+    peek_fn!(peek_i64_le, i64, from_le_bytes);
+    peek_fn!(peek_f32_be, f32, from_be_bytes);
+    peek_fn!(peek_f32_le, f32, from_le_bytes);
+    peek_fn!(peek_f64_be, f64, from_be_bytes);
+    peek_fn!(peek_f64_le, f64, from_le_bytes);
+
+    /// Appends bytes at the writer index
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Drops the already read bytes, so a long-living buffer
+    /// doesn't grow with every frame. The mark moves along,
+    /// a mark inside the dropped bytes moves to the start.
+    pub fn compact(&mut self) {
+        if self.reader_index == 0 {
+            return;
+        }
+        self.data.drain(..self.reader_index);
+        self.marked_index = self.marked_index.saturating_sub(self.reader_index);
+        self.reader_index = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.reader_index = 0;
+        self.marked_index = 0;
+    }
+
+    /// Gives away the readable bytes
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.compact();
+        self.data
+    }
+
+    fn check_remaining(&self, needed: usize) -> Result<(), BufferError> {
+        let remaining = self.remaining();
+        if needed > remaining {
+            return Err(BufferError::Underflow { needed, remaining });
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<u8>> for ByteBuf {
+    fn from(data: Vec<u8>) -> Self {
+        ByteBuf {
+            data,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&[u8]> for ByteBuf {
+    fn from(data: &[u8]) -> Self {
+        ByteBuf::from(data.to_vec())
+    }
+}
+
+/// Reads as much as is readable, like `&[u8]` does
+impl Read for ByteBuf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.remaining());
+        buf[..n].copy_from_slice(&self.readable()[..n]);
+        self.reader_index += n;
+        Ok(n)
+    }
+}
+
+impl Write for ByteBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Buffer, BufferMut};
+
+    #[test]
+    fn byte_buf_roundtrip_all_widths() -> io::Result<()> {
+        let mut buf = ByteBuf::new();
+        buf.write_i8(-2)?;
+        buf.write_u16_be(0x0102)?;
+        buf.write_u16_le(0x0102)?;
+        buf.write_i32_le(-322)?;
+        buf.write_u64_be(u64::MAX - 1)?;
+        buf.write_i64_le(i64::MIN)?;
+        buf.write_f32_be(1.5)?;
+        buf.write_f64_le(-0.25)?;
+
+        assert_eq!(buf.read_i8()?, -2);
+        assert_eq!(buf.readable()[..2], [0x01, 0x02]);
+        assert_eq!(buf.read_u16_be()?, 0x0102);
+        assert_eq!(buf.readable()[..2], [0x02, 0x01]);
+        assert_eq!(buf.read_u16_le()?, 0x0102);
+        assert_eq!(buf.read_i32_le()?, -322);
+        assert_eq!(buf.read_u64_be()?, u64::MAX - 1);
+        assert_eq!(buf.read_i64_le()?, i64::MIN);
+        assert_eq!(buf.read_f32_be()?, 1.5);
+        assert_eq!(buf.read_f64_le()?, -0.25);
+        assert!(!buf.has_remaining());
+        Ok(())
+    }
+
+    #[test]
+    fn byte_buf_peek_doesnt_move() -> io::Result<()> {
+        let mut buf = ByteBuf::new();
+        buf.write_u32_be(0xDEADBEEF)?;
+
+        assert_eq!(buf.peek_u32_be(), Ok(0xDEADBEEF));
+        assert_eq!(buf.peek_u32_le(), Ok(0xEFBEADDE));
+        assert_eq!(buf.peek_u8(), Ok(0xDE));
+        assert_eq!(buf.remaining(), 4);
+        assert_eq!(
+            buf.peek_u64_be(),
+            Err(BufferError::Underflow {
+                needed: 8,
+                remaining: 4
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn byte_buf_mark_reset() -> io::Result<()> {
+        let mut buf = ByteBuf::from(vec![1, 2, 3, 4]);
+        buf.advance(1).unwrap();
+        buf.mark();
+
+        assert_eq!(buf.read_u16_be()?, 0x0203);
+        buf.reset();
+        assert_eq!(buf.reader_index(), 1);
+        assert_eq!(buf.read_slice(3), Ok(&[2, 3, 4][..]));
+        Ok(())
+    }
+
+    #[test]
+    fn byte_buf_accumulates_partial_frame() -> io::Result<()> {
+        let mut buf = ByteBuf::new();
+        buf.extend_from_slice(&[0xAA, 0x00, 0x00]);
+        buf.advance(1).unwrap();
+
+        // The second half of the number hasn't arrived yet
+        buf.mark();
+        assert!(buf.read_u32_be().is_err());
+        buf.reset();
+
+        buf.extend_from_slice(&[0x01, 0x02]);
+        buf.compact();
+        assert_eq!(buf.reader_index(), 0);
+        assert_eq!(buf.writer_index(), 4);
+        assert_eq!(buf.read_u32_be()?, 0x0102);
+        Ok(())
+    }
+
+    #[test]
+    fn byte_buf_compact_moves_mark() {
+        let mut buf = ByteBuf::from(vec![1, 2, 3, 4]);
+        buf.advance(1).unwrap();
+        buf.mark();
+        buf.advance(2).unwrap();
+
+        buf.compact();
+        buf.reset();
+        assert_eq!(buf.readable(), [4]);
+    }
+
+    #[test]
+    fn byte_buf_advance_fail() {
+        let mut buf = ByteBuf::from(&[1u8, 2][..]);
+
+        assert_eq!(
+            buf.advance(3),
+            Err(BufferError::Underflow {
+                needed: 3,
+                remaining: 2
+            })
+        );
+        assert_eq!(buf.remaining(), 2);
+    }
+}
@@ -1 +1,21 @@
+use std::io;
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum BufferError {
+    /// Fewer bytes are readable than requested,
+    /// e.g. the rest of a frame hasn't arrived yet
+    Underflow { needed: usize, remaining: usize },
+}
+
+/// Lets `ByteBuf` errors pass through `std::io::Read` based code,
+/// an underflow is the same as a stream ending too early
+impl From<BufferError> for io::Error {
+    fn from(value: BufferError) -> Self {
+        match value {
+            BufferError::Underflow { needed, remaining } => io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("needed {needed} bytes, {remaining} remaining"),
+            ),
+        }
+    }
+}
@@ -1,6 +1,30 @@
+pub mod byte_buf;
 pub mod error;
 
+pub use byte_buf::ByteBuf;
+
 use std::io::{Read, Write};
+
+/// Generates a reader of a fixed-width number in the given byte order
+macro_rules! read_fn {
+    ($name:ident, $ty:ty, $from:ident) => {
+        fn $name(&mut self) -> Result<$ty, std::io::Error> {
+            let mut buf = [0; size_of::<$ty>()];
+            self.read_exact(&mut buf)?;
+            Ok(<$ty>::$from(buf))
+        }
+    };
+}
+
+/// Generates a writer of a fixed-width number in the given byte order
+macro_rules! write_fn {
+    ($name:ident, $ty:ty, $to:ident) => {
+        fn $name(&mut self, val: $ty) -> Result<(), std::io::Error> {
+            self.write_all(&val.$to())
+        }
+    };
+}
+
 pub trait Buffer: Read {
     fn read_u8(&mut self) -> Result<u8, std::io::Error> {
         let mut buf = [0; 1];
@@ -8,17 +32,26 @@ pub trait Buffer: Read {
         Ok(buf[0])
     }
 
-    fn read_u32_be(&mut self) -> Result<u32, std::io::Error> {
-        let mut buf = [0; 4];
-        self.read_exact(&mut buf)?;
-        Ok(u32::from_be_bytes(buf))
+    fn read_i8(&mut self) -> Result<i8, std::io::Error> {
+        Ok(self.read_u8()? as i8)
     }
 
-    fn read_i32_be(&mut self) -> Result<i32, std::io::Error> {
-        let mut buf = [0; 4];
-        self.read_exact(&mut buf)?;
-        Ok(i32::from_be_bytes(buf))
-    }
+    read_fn!(read_u16_be, u16, from_be_bytes);
+    read_fn!(read_u16_le, u16, from_le_bytes);
+    read_fn!(read_i16_be, i16, from_be_bytes);
+    read_fn!(read_i16_le, i16, from_le_bytes);
+    read_fn!(read_u32_be, u32, from_be_bytes);
+    read_fn!(read_u32_le, u32, from_le_bytes);
+    read_fn!(read_i32_be, i32, from_be_bytes);
+    read_fn!(read_i32_le, i32, from_le_bytes);
+    read_fn!(read_u64_be, u64, from_be_bytes);
+    read_fn!(read_u64_le, u64, from_le_bytes);
+    read_fn!(read_i64_be, i64, from_be_bytes);
+    read_fn!(read_i64_le, i64, from_le_bytes);
+    read_fn!(read_f32_be, f32, from_be_bytes);
+    read_fn!(read_f32_le, f32, from_le_bytes);
+    read_fn!(read_f64_be, f64, from_be_bytes);
+    read_fn!(read_f64_le, f64, from_le_bytes);
 }
 
 pub trait BufferMut: Write {
@@ -26,9 +59,26 @@ pub trait BufferMut: Write {
         self.write_all(&[val])
     }
 
-    fn write_i32_be(&mut self, val: i32) -> Result<(), std::io::Error> {
-        self.write_all(&val.to_be_bytes())
+    fn write_i8(&mut self, val: i8) -> Result<(), std::io::Error> {
+        self.write_u8(val as u8)
     }
+
+    write_fn!(write_u16_be, u16, to_be_bytes);
+    write_fn!(write_u16_le, u16, to_le_bytes);
+    write_fn!(write_i16_be, i16, to_be_bytes);
+    write_fn!(write_i16_le, i16, to_le_bytes);
+    write_fn!(write_u32_be, u32, to_be_bytes);
+    write_fn!(write_u32_le, u32, to_le_bytes);
+    write_fn!(write_i32_be, i32, to_be_bytes);
+    write_fn!(write_i32_le, i32, to_le_bytes);
+    write_fn!(write_u64_be, u64, to_be_bytes);
+    write_fn!(write_u64_le, u64, to_le_bytes);
+    write_fn!(write_i64_be, i64, to_be_bytes);
+    write_fn!(write_i64_le, i64, to_le_bytes);
+    write_fn!(write_f32_be, f32, to_be_bytes);
+    write_fn!(write_f32_le, f32, to_le_bytes);
+    write_fn!(write_f64_be, f64, to_be_bytes);
+    write_fn!(write_f64_le, f64, to_le_bytes);
 }
 
 impl<R: Read + ?Sized> Buffer for R {}
@@ -0,0 +1,65 @@
+//! Owned (`StringProto`) vs borrowed (`&str`) decoding of the same packet.
+//!
+//! `cargo bench -p protocol`
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use protocol::{
+    borrowed::BorrowedCodec,
+    codec::Codec,
+    primitives::{string::StringProto, varint::VarInt},
+};
+
+/// A chat-like packet: the sender, the message and a trailing number
+fn packet(message_length: usize) -> Vec<u8> {
+    let mut buf = Vec::new();
+    StringProto("venomous_player".to_string())
+        .encode(&mut buf)
+        .unwrap();
+    StringProto("s".repeat(message_length))
+        .encode(&mut buf)
+        .unwrap();
+    VarInt(1234).encode(&mut buf).unwrap();
+    buf
+}
+
+fn decode_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_strings");
+
+    for message_length in [16, 256, 4096] {
+        let packet = packet(message_length);
+        group.throughput(Throughput::Bytes(packet.len() as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("owned", message_length),
+            &packet,
+            |b, packet| {
+                b.iter(|| {
+                    let mut reader = &packet[..];
+                    let sender = StringProto::decode(&mut reader).unwrap();
+                    let message = StringProto::decode(&mut reader).unwrap();
+                    let number = VarInt::decode(&mut reader).unwrap();
+                    black_box((sender, message, number))
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("borrowed", message_length),
+            &packet,
+            |b, packet| {
+                b.iter(|| {
+                    let mut reader = &packet[..];
+                    let sender = <&str>::decode_borrowed(&mut reader).unwrap();
+                    let message = <&str>::decode_borrowed(&mut reader).unwrap();
+                    let number = VarInt::decode(&mut reader).unwrap();
+                    black_box((sender, message, number))
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_strings);
+criterion_main!(benches);
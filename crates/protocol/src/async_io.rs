@@ -0,0 +1,264 @@
+//! Reading and writing frames on tokio streams (the `tokio` feature).
+//!
+//! `Codec` decodes through the blocking `std::io::Read`, so it must never
+//! wait on a socket. `FrameReader` reads from the `AsyncRead` into its
+//! own buffer until a whole frame is there, and only then the frame
+//! is decoded, from memory.
+//!
+//! Compression is switched on in the middle of the stream, by a packet
+//! of the game. The reader and the writer each get it at that point.
+
+use std::borrow::Cow;
+
+use buffer::ByteBuf;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{
+    codec::Codec,
+    compression::Compression,
+    context::DecodeContext,
+    error::{ProtocolError, ProtocolViolation},
+    frame::{DEFAULT_MAX_FRAME_LENGTH, decode_frame, encode_frame, peek_frame},
+};
+
+/// Size of a single read from the stream
+const READ_CHUNK: usize = 4096;
+
+/// Read bytes kept in the buffer before it's compacted. Compacting after
+/// every frame would move the rest of a burst of N frames N times.
+const COMPACT_THRESHOLD: usize = 16 * READ_CHUNK;
+
+pub struct FrameReader<R> {
+    reader: R,
+
+    /// Bytes read from the stream, but not taken as a frame yet.
+    /// Kept between the calls, that's what makes them cancellation safe.
+    buf: ByteBuf,
+
+    max_frame_length: usize,
+
+    /// Applied to the frames decoded from now on
+    compression: Option<Compression>,
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    pub fn new(reader: R) -> FrameReader<R> {
+        FrameReader {
+            reader,
+            buf: ByteBuf::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            compression: None,
+        }
+    }
+
+    pub fn with_max_frame_length(mut self, max_frame_length: usize) -> FrameReader<R> {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Frames still buffered but not returned yet are decoded with it too
+    pub fn set_compression(&mut self, compression: Option<Compression>) {
+        self.compression = compression;
+    }
+
+    /// Waits for the next frame and returns its body, borrowed from
+    /// the reader unless it was decompressed.
+    /// `None` means the stream ended cleanly between two frames.
+    ///
+    /// Cancellation safe: if the future is dropped (e.g. in `select!`),
+    /// the bytes read so far stay in the reader and the next call
+    /// continues the same frame.
+    pub async fn read_frame(&mut self) -> Result<Option<Cow<'_, [u8]>>, ProtocolError> {
+        // The body returned last time is not borrowed anymore
+        if self.buf.reader_index() >= COMPACT_THRESHOLD || !self.buf.has_remaining() {
+            self.buf.compact();
+        }
+
+        let mut chunk = [0u8; READ_CHUNK];
+        while peek_frame(&self.buf, self.max_frame_length)?.is_none() {
+            // `read` is cancellation safe, nothing is lost
+            // if the future is dropped while waiting here
+            let n = self.reader.read(&mut chunk).await?;
+            if n == 0 {
+                if self.buf.has_remaining() {
+                    // The stream ended in the middle of a frame
+                    return Err(ProtocolError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+                }
+                return Ok(None);
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+        decode_frame(
+            &mut self.buf,
+            self.max_frame_length,
+            self.compression.as_ref(),
+        )
+    }
+
+    /// Reads the next frame and decodes its body as `T`.
+    /// The body must be consumed completely.
+    pub async fn read_value<T: Codec>(
+        &mut self,
+        ctx: &DecodeContext,
+    ) -> Result<Option<T>, ProtocolError> {
+        let Some(body) = self.read_frame().await? else {
+            return Ok(None);
+        };
+        let mut reader = &body[..];
+        let result = T::decode_with(&mut reader, ctx).and_then(|value| {
+            if !reader.is_empty() {
+                return Err(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::TrailingBytes,
+                ));
+            }
+            Ok(value)
+        });
+        result
+            .map(Some)
+            .map_err(|err| err.at_offset(body.len() - reader.len()))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Writes the body with its frame header in one go.
+///
+/// Not cancellation safe: a dropped future may leave half of the frame
+/// on the stream. Writers should own their stream and not be raced.
+pub async fn write_frame(
+    writer: &mut (impl AsyncWrite + Unpin),
+    body: &[u8],
+    compression: Option<&Compression>,
+) -> Result<(), ProtocolError> {
+    writer.write_all(&encode_frame(body, compression)?).await?;
+    Ok(())
+}
+
+/// Encodes the value and writes it as a frame
+pub async fn write_value(
+    writer: &mut (impl AsyncWrite + Unpin),
+    value: &impl Codec,
+    compression: Option<&Compression>,
+) -> Result<(), ProtocolError> {
+    let mut body = Vec::new();
+    value.encode(&mut body)?;
+    write_frame(writer, &body, compression).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{string::StringProto, varint::VarInt};
+    use assert_matches::assert_matches;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn frame_reader_roundtrip() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = FrameReader::new(server);
+
+        write_value(&mut client, &StringProto("hiss".to_string()), None).await?;
+        write_value(&mut client, &VarInt(-7), None).await?;
+        drop(client);
+
+        let ctx = DecodeContext::DEFAULT;
+        let string: Option<StringProto> = reader.read_value(&ctx).await?;
+        assert_eq!(string.unwrap().0, "hiss");
+        let number: Option<VarInt> = reader.read_value(&ctx).await?;
+        assert_eq!(number.unwrap().0, -7);
+        assert_matches!(reader.read_frame().await, Ok(None));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_reader_cancellation_safe() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = FrameReader::new(server);
+        let frame = encode_frame(&[1, 2, 3, 4], None)?;
+
+        // Half of the frame arrives, the read is cancelled by the timeout
+        client.write_all(&frame[..3]).await?;
+        let res = tokio::time::timeout(Duration::from_millis(10), reader.read_frame()).await;
+        assert!(res.is_err());
+
+        client.write_all(&frame[3..]).await?;
+        assert_eq!(
+            reader.read_frame().await?.as_deref(),
+            Some(&[1, 2, 3, 4][..])
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_reader_compacts_lazily() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(READ_CHUNK);
+        let mut reader = FrameReader::new(server);
+        let frame = encode_frame(&[7; 100], None)?;
+        for _ in 0..8 {
+            client.write_all(&frame).await?;
+        }
+
+        // The rest of the burst stays where it is between the frames
+        for i in 1..8 {
+            assert_eq!(reader.read_frame().await?.as_deref(), Some(&[7; 100][..]));
+            assert_eq!(reader.buf.reader_index(), i * frame.len());
+        }
+        assert_eq!(reader.read_frame().await?.as_deref(), Some(&[7; 100][..]));
+
+        // Everything is read, the next call starts over
+        client.write_all(&frame).await?;
+        assert!(reader.read_frame().await?.is_some());
+        assert_eq!(reader.buf.reader_index(), frame.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_reader_compression_switched() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(4096);
+        let mut reader = FrameReader::new(server);
+        let compression = Compression::new(16);
+
+        write_frame(&mut client, &[1], None).await?;
+        write_frame(&mut client, &[2; 100], Some(&compression)).await?;
+
+        assert_eq!(reader.read_frame().await?.as_deref(), Some(&[1][..]));
+        reader.set_compression(Some(compression));
+        assert_eq!(reader.read_frame().await?.as_deref(), Some(&[2; 100][..]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_reader_fail_eof_mid_frame() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = FrameReader::new(server);
+
+        client
+            .write_all(&encode_frame(&[1, 2, 3], None)?[..2])
+            .await?;
+        drop(client);
+
+        assert_matches!(
+            reader.read_frame().await,
+            Err(ProtocolError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn frame_reader_fail_trailing_bytes() -> Result<(), ProtocolError> {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = FrameReader::new(server);
+
+        write_frame(&mut client, &[1, 2], None).await?;
+
+        let err = reader
+            .read_value::<VarInt>(&DecodeContext::DEFAULT)
+            .await
+            .unwrap_err();
+        assert_matches!(err.violation(), Some(ProtocolViolation::TrailingBytes));
+        assert_eq!(err.offset(), Some(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+//! Zero-copy decoding straight from the packet buffer.
+//!
+//! `Codec` reads through `std::io::Read`, so every string becomes a new
+//! `String`. When the whole packet is already in memory, strings and byte
+//! arrays can point into it instead. The wire format is the same:
+//! a `VarInt` length followed by the bytes, so `&str` and `StringProto`
+//! are interchangeable on the wire.
+//!
+//! ```
+//! use protocol::{borrowed::BorrowedCodec, codec::Codec, primitives::varint::VarInt};
+//!
+//! let packet = [5, b'h', b'e', b'l', b'l', b'o', 42];
+//! let mut reader = &packet[..];
+//!
+//! let name = <&str>::decode_borrowed(&mut reader).unwrap();
+//! // Owned primitives are decoded from the same reader
+//! let number = VarInt::decode(&mut reader).unwrap();
+//! assert_eq!((name, number.0), ("hello", 42));
+//! ```
+
+use std::io;
+
+use buffer::BufferMut;
+
+use crate::{
+    codec::Codec,
+    context::{DecodeContext, checked_length, encoded_length},
+    error::ProtocolError,
+    primitives::varint::VarInt,
+};
+
+/// Like `Codec`, but the decoded value may borrow from the input for `'a`
+pub trait BorrowedCodec<'a>: Sized {
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError>;
+
+    /// Decodes with `DecodeContext::DEFAULT` limits
+    /// and advances `reader` past the decoded value
+    fn decode_borrowed(reader: &mut &'a [u8]) -> Result<Self, ProtocolError> {
+        Self::decode_borrowed_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_borrowed_with(
+        reader: &mut &'a [u8],
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError>;
+}
+
+/// Splits off `length` bytes, failing the same way as `read_exact` does
+fn take<'a>(reader: &mut &'a [u8], length: usize) -> Result<&'a [u8], ProtocolError> {
+    if reader.len() < length {
+        return Err(ProtocolError::Io(io::Error::from(
+            io::ErrorKind::UnexpectedEof,
+        )));
+    }
+    let (bytes, rest) = reader.split_at(length);
+    *reader = rest;
+    Ok(bytes)
+}
+
+/// Length-prefixed bytes, limited by `max_array_length`
+impl<'a> BorrowedCodec<'a> for &'a [u8] {
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        encoded_length(self.len())?.encode(writer)?;
+        writer.write_all(self)?;
+        Ok(())
+    }
+
+    fn decode_borrowed_with(
+        reader: &mut &'a [u8],
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError> {
+        let length = checked_length(VarInt::decode(reader)?.0, ctx.max_array_length)?;
+        take(reader, length)
+    }
+}
+
+/// Same layout as `StringProto`, limited by `max_string_length`
+impl<'a> BorrowedCodec<'a> for &'a str {
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        self.as_bytes().encode(writer)
+    }
+
+    fn decode_borrowed_with(
+        reader: &mut &'a [u8],
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError> {
+        let length = checked_length(VarInt::decode(reader)?.0, ctx.max_string_length)?;
+        Ok(std::str::from_utf8(take(reader, length)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::ProtocolViolation, primitives::string::StringProto};
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn str_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        "Hello world".encode(&mut buf)?;
+        VarInt(7).encode(&mut buf)?;
+
+        let mut reader = &buf[..];
+        let str = <&str>::decode_borrowed(&mut reader)?;
+        assert_eq!(str, "Hello world");
+        // Points into the buffer, nothing was copied
+        assert_eq!(str.as_ptr(), buf[1..].as_ptr());
+        assert_eq!(VarInt::decode(&mut reader)?.0, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn str_same_layout_as_stringproto() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        StringProto("snake".to_string()).encode(&mut buf)?;
+
+        let mut reader = &buf[..];
+        assert_eq!(<&str>::decode_borrowed(&mut reader)?, "snake");
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn bytes_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        (&[0u8, 255, 7][..]).encode(&mut buf)?;
+
+        let mut reader = &buf[..];
+        assert_eq!(<&[u8]>::decode_borrowed(&mut reader)?, [0, 255, 7]);
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn str_decode_fail_short_buffer() {
+        let buf = [5, b'a', b'b'];
+
+        assert_matches!(
+            <&str>::decode_borrowed(&mut &buf[..]),
+            Err(ProtocolError::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn str_decode_fail_utf8() {
+        let buf = [2, 0xC3, 0x28];
+
+        assert_matches!(
+            <&str>::decode_borrowed(&mut &buf[..]),
+            Err(ProtocolError::Utf8(_))
+        );
+    }
+
+    #[test]
+    fn str_decode_fail_limit() {
+        let buf = [5, b'h', b'e', b'l', b'l', b'o'];
+
+        assert_matches!(
+            <&str>::decode_borrowed_with(&mut &buf[..], &DecodeContext::new(4, 0)),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+    }
+}
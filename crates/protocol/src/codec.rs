@@ -1,9 +1,17 @@
 use buffer::{Buffer, BufferMut};
 
-use crate::error::ProtocolError;
+use crate::{context::DecodeContext, error::ProtocolError};
 
 pub trait Codec: Sized {
     fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError>;
 
+    /// Decodes with `DecodeContext::DEFAULT` limits
     fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError>;
+
+    /// Decodes with the given limits.
+    /// Types with length prefixes, and types containing them, override it
+    /// and pass the context further down to their fields.
+    fn decode_with(reader: &mut impl Buffer, _ctx: &DecodeContext) -> Result<Self, ProtocolError> {
+        Self::decode(reader)
+    }
 }
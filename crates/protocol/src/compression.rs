@@ -0,0 +1,204 @@
+//! Optional compression of frames.
+//!
+//! Once compression is enabled on a connection, the frame header gets
+//! a flag after the length prefix, and bodies of at least `threshold`
+//! bytes are deflated (see `frame` for the whole layout):
+//!
+//! ```text
+//! flag                 u8      0 - as is, 1 - zlib
+//! uncompressed length  VarInt  only with flag 1
+//! body                 [u8]    packet id + payload, compressed with flag 1
+//! ```
+//!
+//! `encode_frame` and `decode_frame` apply it, the length prefix
+//! of the frame stays uncompressed. Small packets are not worth
+//! the CPU time and usually grow when deflated.
+//!
+//! The uncompressed length is checked before inflating, and inflating
+//! stops right after it, so a small frame can't inflate into gigabytes.
+
+use std::io::{Read, Write};
+
+use flate2::{read::ZlibDecoder, write::ZlibEncoder};
+
+use crate::{
+    codec::Codec,
+    context::{checked_length, encoded_length},
+    error::{ProtocolError, ProtocolViolation},
+    primitives::varint::VarInt,
+};
+
+/// Bodies shorter than this are sent as is
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 256;
+
+/// Limit of an inflated body, unless the connection is given another one
+pub const DEFAULT_MAX_DECOMPRESSED_LENGTH: usize = 8 * 1024 * 1024;
+
+/// Inflating starts with at most this much memory, the declared
+/// length only bounds how far the buffer may grow
+const INITIAL_CAPACITY: usize = 64 * 1024;
+
+const FLAG_NONE: u8 = 0;
+const FLAG_ZLIB: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    /// Bodies of at least this many bytes are compressed.
+    /// Chosen by the sender, the receiver accepts both forms anyway.
+    pub threshold: usize,
+
+    /// Chosen by the receiver, see the module docs
+    pub max_decompressed_length: usize,
+}
+
+impl Compression {
+    pub const fn new(threshold: usize) -> Compression {
+        Compression {
+            threshold,
+            max_decompressed_length: DEFAULT_MAX_DECOMPRESSED_LENGTH,
+        }
+    }
+
+    pub const fn with_max_decompressed_length(
+        mut self,
+        max_decompressed_length: usize,
+    ) -> Compression {
+        self.max_decompressed_length = max_decompressed_length;
+        self
+    }
+
+    /// The rest of the frame after the length prefix:
+    /// the flag, then the body, deflated if it's long enough
+    pub fn compress(&self, body: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        if body.len() < self.threshold {
+            let mut out = Vec::with_capacity(body.len() + 1);
+            out.push(FLAG_NONE);
+            out.extend_from_slice(body);
+            return Ok(out);
+        }
+
+        let mut out = vec![FLAG_ZLIB];
+        encoded_length(body.len())?.encode(&mut out)?;
+        let mut encoder = ZlibEncoder::new(out, flate2::Compression::default());
+        encoder.write_all(body)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Reverses `compress`, refusing to inflate more than
+    /// `max_decompressed_length` bytes
+    pub fn decompress(&self, frame_body: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let Some((&flag, mut reader)) = frame_body.split_first() else {
+            return Err(ProtocolError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        };
+
+        match flag {
+            FLAG_NONE => Ok(reader.to_vec()),
+            FLAG_ZLIB => {
+                let length = VarInt::decode(&mut reader)?;
+                let length = checked_length(length.0, self.max_decompressed_length).map_err(
+                    |err| match err {
+                        ProtocolError::ProtocolViolation(ProtocolViolation::LengthExceedsLimit) => {
+                            ProtocolError::ProtocolViolation(ProtocolViolation::DecompressedTooLong)
+                        }
+                        other => other,
+                    },
+                )?;
+
+                // One byte more than announced, to notice a lie
+                let mut body = Vec::with_capacity(length.min(INITIAL_CAPACITY));
+                ZlibDecoder::new(reader)
+                    .take(length as u64 + 1)
+                    .read_to_end(&mut body)?;
+                if body.len() != length {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::DecompressedLengthMismatch,
+                    ));
+                }
+                Ok(body)
+            }
+            _ => Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownVariant,
+            )),
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::new(DEFAULT_COMPRESSION_THRESHOLD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn compression_small_body_as_is() -> Result<(), ProtocolError> {
+        let compression = Compression::new(4);
+        let frame_body = compression.compress(&[1, 2, 3])?;
+
+        assert_eq!(frame_body, [FLAG_NONE, 1, 2, 3]);
+        assert_eq!(compression.decompress(&frame_body)?, [1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn compression_large_body_roundtrip() -> Result<(), ProtocolError> {
+        let compression = Compression::new(4);
+        let body = vec![42u8; 10_000];
+        let frame_body = compression.compress(&body)?;
+
+        assert_eq!(frame_body[0], FLAG_ZLIB);
+        assert!(frame_body.len() < 100);
+        assert_eq!(compression.decompress(&frame_body)?, body);
+        Ok(())
+    }
+
+    #[test]
+    fn decompress_fail_too_long() -> Result<(), ProtocolError> {
+        let frame_body = Compression::new(0).compress(&[0; 1025])?;
+
+        let compression = Compression::new(0).with_max_decompressed_length(1024);
+        assert_matches!(
+            compression.decompress(&frame_body),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::DecompressedTooLong
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decompress_fail_bomb() -> Result<(), ProtocolError> {
+        // A megabyte of zeroes deflates into about a kilobyte,
+        // but claims to be only 16 bytes long
+        let mut frame_body = Compression::new(0).compress(&vec![0; 1024 * 1024])?;
+        let header = 1 + 3;
+        let mut fake = vec![FLAG_ZLIB, 16];
+        fake.extend_from_slice(&frame_body.split_off(header));
+
+        assert_matches!(
+            Compression::default().decompress(&fake),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::DecompressedLengthMismatch
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn decompress_fail_unknown_flag() {
+        assert_matches!(
+            Compression::default().decompress(&[7, 1, 2]),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownVariant
+            ))
+        );
+        assert_matches!(
+            Compression::default().decompress(&[]),
+            Err(ProtocolError::Io(_))
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    error::{ProtocolError, ProtocolViolation},
+    primitives::varint::VarInt,
+};
+
+/// Limits applied while decoding.
+///
+/// Every length prefix is checked against them before anything is allocated,
+/// so a single malicious packet can't make the decoder reserve gigabytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeContext {
+    /// Maximum length of a `StringProto` in bytes
+    pub max_string_length: usize,
+
+    /// Maximum amount of elements in a `PrefixedArray`
+    pub max_array_length: usize,
+}
+
+impl DecodeContext {
+    pub const DEFAULT: DecodeContext = DecodeContext {
+        max_string_length: 32_767,
+        max_array_length: 65_536,
+    };
+
+    pub const fn new(max_string_length: usize, max_array_length: usize) -> DecodeContext {
+        DecodeContext {
+            max_string_length,
+            max_array_length,
+        }
+    }
+}
+
+impl Default for DecodeContext {
+    fn default() -> Self {
+        DecodeContext::DEFAULT
+    }
+}
+
+/// Turns a decoded length prefix into `usize`, rejecting negative
+/// lengths and lengths above the limit
+pub(crate) fn checked_length(length: i32, limit: usize) -> Result<usize, ProtocolError> {
+    let length = usize::try_from(length)
+        .map_err(|_| ProtocolError::ProtocolViolation(ProtocolViolation::NegativeLength))?;
+    if length > limit {
+        return Err(ProtocolError::ProtocolViolation(
+            ProtocolViolation::LengthExceedsLimit,
+        ));
+    }
+    Ok(length)
+}
+
+/// The length prefix for `length` bytes or elements,
+/// which must fit into a `VarInt`
+pub(crate) fn encoded_length(length: usize) -> Result<VarInt, ProtocolError> {
+    i32::try_from(length)
+        .map(VarInt)
+        .map_err(|_| ProtocolError::ProtocolViolation(ProtocolViolation::LengthExceedsLimit))
+}
@@ -1,17 +1,200 @@
-use std::string::FromUtf8Error;
+use std::{fmt, str::Utf8Error, string::FromUtf8Error};
 
 #[derive(Debug)]
 pub enum ProtocolViolation {
     VarIntTooLong,
     VarLongTooLong,
-    NegativeUnsigned,
+    /// The last byte of a `VarInt` carries bits above 32
+    VarIntOverflow,
+    /// The last byte of a `VarLong` carries bits above 64
+    VarLongOverflow,
+    /// The number has redundant trailing bytes, e.g. `[0x80, 0x00]` for 0
+    OverlongEncoding,
+    /// A byte doesn't correspond to any variant of an enumeration
+    UnknownVariant,
+    /// The value is well-formed, but makes no sense for the decoded type
+    InvalidValue,
+    /// The packet id is unknown or not expected in the current state
+    UnknownPacket,
+    /// A length prefix of a string or an array is negative
+    NegativeLength,
+    /// A length prefix is above the limit of the `DecodeContext`
+    LengthExceedsLimit,
+    /// A frame is longer than the reader accepts
+    FrameTooLong,
+    /// The frame has bytes left after its content was decoded
+    TrailingBytes,
+    /// The announced uncompressed length of a frame is above the limit
+    DecompressedTooLong,
+    /// The inflated body is not as long as announced
+    DecompressedLengthMismatch,
+}
+
+impl fmt::Display for ProtocolViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ProtocolViolation::VarIntTooLong => "VarInt is longer than 5 bytes",
+            ProtocolViolation::VarLongTooLong => "VarLong is longer than 10 bytes",
+            ProtocolViolation::VarIntOverflow => "VarInt doesn't fit into 32 bits",
+            ProtocolViolation::VarLongOverflow => "VarLong doesn't fit into 64 bits",
+            ProtocolViolation::OverlongEncoding => "value has redundant trailing bytes",
+            ProtocolViolation::UnknownVariant => "unknown enumeration variant",
+            ProtocolViolation::InvalidValue => "invalid value",
+            ProtocolViolation::UnknownPacket => "unknown or unexpected packet",
+            ProtocolViolation::NegativeLength => "negative length",
+            ProtocolViolation::LengthExceedsLimit => "length exceeds the limit",
+            ProtocolViolation::FrameTooLong => "frame exceeds the limit",
+            ProtocolViolation::TrailingBytes => "trailing bytes after the content",
+            ProtocolViolation::DecompressedTooLong => "decompressed frame exceeds the limit",
+            ProtocolViolation::DecompressedLengthMismatch => {
+                "decompressed frame length doesn't match the announced one"
+            }
+        };
+        f.write_str(message)
+    }
 }
 
 #[derive(Debug)]
 pub enum ProtocolError {
     Io(std::io::Error),
     ProtocolViolation(ProtocolViolation),
-    Utf8(FromUtf8Error),
+    Utf8(Utf8Error),
+
+    /// Where the wrapped error happened.
+    /// Added by `Codec`s of packets as the error bubbles up,
+    /// never wraps another `Context`.
+    Context {
+        /// Packet and fields, outermost first: `PlayClientbound::SpawnEntity.x`
+        path: String,
+        /// Position of the reader in the packet when it failed
+        offset: Option<usize>,
+        source: Box<ProtocolError>,
+    },
+}
+
+impl ProtocolError {
+    /// Prepends a segment to the path, `.` separated
+    pub fn context(self, segment: &str) -> ProtocolError {
+        match self {
+            ProtocolError::Context {
+                path,
+                offset,
+                source,
+            } => ProtocolError::Context {
+                // The path is empty after `at_offset` on a bare error
+                path: match path.is_empty() {
+                    true => segment.to_string(),
+                    false => format!("{segment}.{path}"),
+                },
+                offset,
+                source,
+            },
+            other => ProtocolError::Context {
+                path: segment.to_string(),
+                offset: None,
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Sets the byte offset, unless a more precise one is already there
+    pub fn at_offset(self, offset: usize) -> ProtocolError {
+        match self {
+            ProtocolError::Context {
+                path,
+                offset: None,
+                source,
+            } => ProtocolError::Context {
+                path,
+                offset: Some(offset),
+                source,
+            },
+            context @ ProtocolError::Context { .. } => context,
+            other => ProtocolError::Context {
+                path: String::new(),
+                offset: Some(offset),
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// The error without its context
+    pub fn root(&self) -> &ProtocolError {
+        match self {
+            ProtocolError::Context { source, .. } => source,
+            other => other,
+        }
+    }
+
+    pub fn violation(&self) -> Option<&ProtocolViolation> {
+        match self.root() {
+            ProtocolError::ProtocolViolation(violation) => Some(violation),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ProtocolError::Context { path, .. } if !path.is_empty() => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ProtocolError::Context { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Io(err) => write!(f, "io error: {err}"),
+            ProtocolError::ProtocolViolation(violation) => write!(f, "{violation}"),
+            ProtocolError::Utf8(err) => write!(f, "invalid utf-8: {err}"),
+            ProtocolError::Context {
+                path,
+                offset,
+                source,
+            } => {
+                if !path.is_empty() {
+                    write!(f, "{path}")?;
+                }
+                if let Some(offset) = offset {
+                    if !path.is_empty() {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "at byte {offset}")?;
+                }
+                write!(f, ": {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProtocolError::Io(err) => Some(err),
+            ProtocolError::Utf8(err) => Some(err),
+            ProtocolError::ProtocolViolation(_) => None,
+            // The message of the wrapped error is already part of `Display`
+            ProtocolError::Context { source, .. } => source.source(),
+        }
+    }
+}
+
+/// `.context(..)` right on the result of a nested `decode`
+pub trait ResultExt<T> {
+    fn context(self, segment: &str) -> Result<T, ProtocolError>;
+}
+
+impl<T> ResultExt<T> for Result<T, ProtocolError> {
+    fn context(self, segment: &str) -> Result<T, ProtocolError> {
+        self.map_err(|err| err.context(segment))
+    }
 }
 
 impl From<std::io::Error> for ProtocolError {
@@ -20,8 +203,84 @@ impl From<std::io::Error> for ProtocolError {
     }
 }
 
+impl From<Utf8Error> for ProtocolError {
+    fn from(value: Utf8Error) -> Self {
+        ProtocolError::Utf8(value)
+    }
+}
+
 impl From<FromUtf8Error> for ProtocolError {
     fn from(value: FromUtf8Error) -> Self {
-        ProtocolError::Utf8(value)
+        ProtocolError::Utf8(value.utf8_error())
+    }
+}
+
+impl From<buffer::error::BufferError> for ProtocolError {
+    fn from(value: buffer::error::BufferError) -> Self {
+        ProtocolError::Io(value.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_builds_path() {
+        let err: Result<(), _> = Err(ProtocolError::ProtocolViolation(
+            ProtocolViolation::VarIntTooLong,
+        ));
+        let err = err
+            .context("x")
+            .context("PlayClientbound::SpawnEntity")
+            .unwrap_err()
+            .at_offset(3)
+            .at_offset(0);
+
+        assert_eq!(err.path(), Some("PlayClientbound::SpawnEntity.x"));
+        assert_eq!(err.offset(), Some(3));
+        assert!(matches!(
+            err.violation(),
+            Some(ProtocolViolation::VarIntTooLong)
+        ));
+        assert_eq!(
+            err.to_string(),
+            "PlayClientbound::SpawnEntity.x at byte 3: VarInt is longer than 5 bytes"
+        );
+    }
+
+    #[test]
+    fn offset_without_path() {
+        let err = ProtocolError::ProtocolViolation(ProtocolViolation::TrailingBytes).at_offset(7);
+
+        assert_eq!(err.path(), None);
+        assert_eq!(
+            err.to_string(),
+            "at byte 7: trailing bytes after the content"
+        );
+    }
+
+    #[test]
+    fn context_after_offset() {
+        let err = ProtocolError::ProtocolViolation(ProtocolViolation::TrailingBytes)
+            .at_offset(7)
+            .context("Login");
+
+        assert_eq!(err.path(), Some("Login"));
+        assert_eq!(
+            err.to_string(),
+            "Login at byte 7: trailing bytes after the content"
+        );
+    }
+
+    #[test]
+    fn error_source() {
+        use std::error::Error;
+
+        let err = ProtocolError::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof))
+            .context("reason");
+
+        assert!(err.source().is_some());
+        assert!(err.to_string().starts_with("reason: io error: "));
     }
 }
@@ -0,0 +1,201 @@
+//! Length-prefixed frames.
+//!
+//! On a stream, packets are separated by frames:
+//!
+//! ```text
+//! length               VarInt   length of the rest of the frame in bytes
+//! flag                 u8       only with compression: 0 - as is, 1 - zlib
+//! uncompressed length  VarInt   only with flag 1
+//! body                 [u8]     packet id + payload, see `Packet::encode`
+//! ```
+//!
+//! Both ends switch compression on at the same point of the stream,
+//! see `compression`. Without it the header is the length prefix only.
+//!
+//! `decode_frame` works on an accumulating `ByteBuf`, so it can be fed
+//! with whatever pieces of the stream have arrived so far. Without
+//! compression the body is borrowed from the buffer, not copied.
+
+use std::borrow::Cow;
+
+use buffer::ByteBuf;
+
+use crate::{
+    codec::Codec,
+    compression::Compression,
+    context::{checked_length, encoded_length},
+    error::{ProtocolError, ProtocolViolation},
+    primitives::varint::VarInt,
+};
+
+/// Limit of a frame body, unless the reader is given another one
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 2 * 1024 * 1024;
+
+/// Prepends the frame header to the body, compressing it
+/// if `compression` is enabled
+pub fn encode_frame(
+    body: &[u8],
+    compression: Option<&Compression>,
+) -> Result<Vec<u8>, ProtocolError> {
+    let compressed;
+    let body = match compression {
+        Some(compression) => {
+            compressed = compression.compress(body)?;
+            &compressed[..]
+        }
+        None => body,
+    };
+    let mut frame = Vec::with_capacity(body.len() + 5);
+    encoded_length(body.len())?.encode(&mut frame)?;
+    frame.extend_from_slice(body);
+    Ok(frame)
+}
+
+/// Sizes of the header and of the body of the next frame in `buf`,
+/// `None` while the frame is incomplete. `max_frame_length`
+/// limits the frame as it is on the stream.
+pub fn peek_frame(
+    buf: &ByteBuf,
+    max_frame_length: usize,
+) -> Result<Option<(usize, usize)>, ProtocolError> {
+    let mut reader = buf.readable();
+    let length = match VarInt::decode(&mut reader) {
+        Ok(length) => length,
+        // The length prefix itself is split between the pieces
+        Err(ProtocolError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => {
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    };
+    let length = checked_length(length.0, max_frame_length).map_err(|err| match err {
+        // The limit of a frame is not the limit of an array
+        ProtocolError::ProtocolViolation(ProtocolViolation::LengthExceedsLimit) => {
+            ProtocolError::ProtocolViolation(ProtocolViolation::FrameTooLong)
+        }
+        other => other,
+    })?;
+
+    if reader.len() < length {
+        return Ok(None);
+    }
+    Ok(Some((buf.remaining() - reader.len(), length)))
+}
+
+/// Takes the next complete frame out of `buf` and returns its body,
+/// decompressed if `compression` is enabled, borrowed from `buf` otherwise.
+/// Returns `None` and leaves `buf` untouched while the frame is incomplete,
+/// see `peek_frame`.
+pub fn decode_frame<'a>(
+    buf: &'a mut ByteBuf,
+    max_frame_length: usize,
+    compression: Option<&Compression>,
+) -> Result<Option<Cow<'a, [u8]>>, ProtocolError> {
+    let Some((header, length)) = peek_frame(buf, max_frame_length)? else {
+        return Ok(None);
+    };
+    if let Some(compression) = compression {
+        let body = compression.decompress(&buf.peek_slice(header + length)?[header..])?;
+        buf.advance(header + length)?;
+        return Ok(Some(Cow::Owned(body)));
+    }
+    buf.advance(header)?;
+    Ok(Some(Cow::Borrowed(buf.read_slice(length)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn frame_roundtrip_in_pieces() -> Result<(), ProtocolError> {
+        let body = vec![7u8; 300];
+        let frame = encode_frame(&body, None)?;
+        // 300 needs a two-byte length prefix
+        assert_eq!(frame.len(), 302);
+
+        let mut buf = ByteBuf::new();
+        for byte in &frame[..301] {
+            buf.extend_from_slice(&[*byte]);
+            assert_matches!(
+                decode_frame(&mut buf, DEFAULT_MAX_FRAME_LENGTH, None),
+                Ok(None)
+            );
+        }
+        buf.extend_from_slice(&frame[301..]);
+
+        assert_eq!(
+            decode_frame(&mut buf, DEFAULT_MAX_FRAME_LENGTH, None)?.as_deref(),
+            Some(&body[..])
+        );
+        assert!(!buf.has_remaining());
+        Ok(())
+    }
+
+    #[test]
+    fn frame_decode_several() -> Result<(), ProtocolError> {
+        let mut buf = ByteBuf::new();
+        buf.extend_from_slice(&encode_frame(&[1], None)?);
+        buf.extend_from_slice(&encode_frame(&[], None)?);
+        buf.extend_from_slice(&encode_frame(&[2, 3], None)?);
+
+        assert_eq!(
+            decode_frame(&mut buf, 16, None)?,
+            Some(Cow::Borrowed(&[1][..]))
+        );
+        assert_eq!(decode_frame(&mut buf, 16, None)?.as_deref(), Some(&[][..]));
+        assert_eq!(
+            decode_frame(&mut buf, 16, None)?.as_deref(),
+            Some(&[2, 3][..])
+        );
+        assert_eq!(decode_frame(&mut buf, 16, None)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn frame_compressed_header() -> Result<(), ProtocolError> {
+        let compression = Compression::new(4);
+        let mut buf = ByteBuf::new();
+        buf.extend_from_slice(&encode_frame(&[1, 2], Some(&compression))?);
+        buf.extend_from_slice(&encode_frame(&[7; 1000], Some(&compression))?);
+
+        // The flag follows the length, short bodies stay as they are
+        assert_eq!(buf.readable()[..4], [3, 0, 1, 2]);
+        assert_eq!(
+            decode_frame(&mut buf, 16, Some(&compression))?.as_deref(),
+            Some(&[1, 2][..])
+        );
+        // The limit is of the frame on the stream, not of the inflated body
+        assert_eq!(
+            decode_frame(&mut buf, 64, Some(&compression))?.as_deref(),
+            Some(&[7; 1000][..])
+        );
+        assert!(!buf.has_remaining());
+        Ok(())
+    }
+
+    #[test]
+    fn frame_length_fits_varint() {
+        assert_eq!(encoded_length(300).unwrap().0, 300);
+        // Encoding such a body would take too much memory for a test
+        assert_matches!(
+            encoded_length(i32::MAX as usize + 1),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+    }
+
+    #[test]
+    fn frame_decode_fail_too_long() -> Result<(), ProtocolError> {
+        let mut buf = ByteBuf::from(encode_frame(&[0; 17], None)?);
+
+        assert_matches!(
+            decode_frame(&mut buf, 16, None),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::FrameTooLong
+            ))
+        );
+        Ok(())
+    }
+}
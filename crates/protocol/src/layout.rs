@@ -0,0 +1,82 @@
+//! Human-readable wire layouts of the primitives,
+//! used to generate the documentation of packets.
+
+use crate::primitives::{
+    array::Array,
+    bitset::BitSet,
+    bool::Bool,
+    byte::Byte,
+    fixed::{U16, U32, U64},
+    float::{F32, F64},
+    prefixed_array::PrefixedArray,
+    string::StringProto,
+    svarint::SVarInt,
+    svarlong::SVarLong,
+    uvarint::UVarInt,
+    uvarlong::UVarLong,
+    varint::VarInt,
+    varlong::VarLong,
+};
+
+pub trait Layout {
+    /// How the type looks on the wire, e.g. `VarInt (1-5 bytes)`
+    fn layout() -> String;
+}
+
+macro_rules! layout {
+    ($ty:ty, $layout:literal) => {
+        impl Layout for $ty {
+            fn layout() -> String {
+                $layout.to_string()
+            }
+        }
+    };
+}
+
+layout!(Byte, "u8 (1 byte)");
+layout!(Bool, "bool (1 byte, 0 or 1)");
+layout!(U16, "u16 BE (2 bytes)");
+layout!(U32, "u32 BE (4 bytes)");
+layout!(U64, "u64 BE (8 bytes)");
+layout!(F32, "f32 BE (4 bytes)");
+layout!(F64, "f64 BE (8 bytes)");
+layout!(VarInt, "VarInt (1-5 bytes)");
+layout!(VarLong, "VarLong (1-10 bytes)");
+layout!(UVarInt, "UVarInt (1-5 bytes)");
+layout!(UVarLong, "UVarLong (1-10 bytes)");
+layout!(SVarInt, "ZigZag VarInt (1-5 bytes)");
+layout!(SVarLong, "ZigZag VarLong (1-10 bytes)");
+layout!(StringProto, "VarInt length + UTF-8 bytes");
+layout!(BitSet, "VarInt count + u64 BE words");
+
+impl<T: Layout> Layout for PrefixedArray<T> {
+    fn layout() -> String {
+        format!("VarInt count + count × {}", T::layout())
+    }
+}
+
+impl<T: Layout> Layout for Option<T> {
+    fn layout() -> String {
+        format!("Bool + {} if true", T::layout())
+    }
+}
+
+impl<T: Layout, const N: usize> Layout for Array<T, N> {
+    fn layout() -> String {
+        format!("{N} × {}", T::layout())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_nested() {
+        assert_eq!(
+            PrefixedArray::<Option<VarInt>>::layout(),
+            "VarInt count + count × Bool + VarInt (1-5 bytes) if true"
+        );
+        assert_eq!(Array::<U16, 3>::layout(), "3 × u16 BE (2 bytes)");
+    }
+}
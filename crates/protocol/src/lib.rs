@@ -1,3 +1,10 @@
+#[cfg(feature = "tokio")]
+pub mod async_io;
+pub mod borrowed;
 pub mod codec;
+pub mod compression;
+pub mod context;
 pub mod error;
+pub mod frame;
+pub mod layout;
 pub mod primitives;
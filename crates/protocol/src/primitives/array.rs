@@ -0,0 +1,79 @@
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, context::DecodeContext, error::ProtocolError};
+
+impl<T, const N: usize> From<[T; N]> for Array<T, N> {
+    fn from(data: [T; N]) -> Self {
+        Array(data)
+    }
+}
+
+/// Exactly `N` elements without a length prefix,
+/// the length is known to both sides from the packet layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array<T, const N: usize>(pub [T; N]);
+
+impl<T: Codec, const N: usize> Codec for Array<T, N> {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(reader: &mut impl Buffer, ctx: &DecodeContext) -> Result<Self, ProtocolError> {
+        let mut data = Vec::with_capacity(N);
+        for _ in 0..N {
+            data.push(T::decode_with(reader, ctx)?);
+        }
+        // Can't fail, exactly `N` elements were pushed
+        let data: [T; N] = data.try_into().unwrap_or_else(|_| unreachable!());
+        Ok(Array(data))
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        for item in &self.0 {
+            item.encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{fixed::U16, varint::VarInt};
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn array_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        Array([U16(1), U16(2), U16(3)]).encode(&mut buf)?;
+        // No length prefix
+        assert_eq!(buf, [0, 1, 0, 2, 0, 3]);
+
+        let mut reader = &buf[..];
+        assert_eq!(
+            Array::<U16, 3>::decode(&mut reader)?,
+            Array([U16(1), U16(2), U16(3)])
+        );
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn array_empty() -> Result<(), ProtocolError> {
+        let mut reader: &[u8] = &[];
+        assert_eq!(Array::<U16, 0>::decode(&mut reader)?, Array([]));
+        Ok(())
+    }
+
+    #[test]
+    fn array_decode_fail_short() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        VarInt(1).encode(&mut buf)?;
+
+        assert_matches!(
+            Array::<VarInt, 2>::decode(&mut &buf[..]),
+            Err(ProtocolError::Io(_))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use buffer::{Buffer, BufferMut};
+
+use crate::{
+    codec::Codec,
+    context::{DecodeContext, checked_length, encoded_length},
+    error::{ProtocolError, ProtocolViolation},
+    primitives::{fixed::U64, varint::VarInt},
+};
+
+/// A growable set of bit flags, packed into 64-bit words.
+///
+/// Encoded as a `VarInt` amount of words followed by the words as `U64`.
+/// Bit `i` is bit `i % 64` of word `i / 64`. Trailing zero words are never
+/// stored, so every set has exactly one encoding, and the empty set is
+/// a single zero byte.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new() -> BitSet {
+        BitSet::default()
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        let word = index / 64;
+        if value {
+            if word >= self.words.len() {
+                self.words.resize(word + 1, 0);
+            }
+            self.words[word] |= 1 << (index % 64);
+        } else if word < self.words.len() {
+            self.words[word] &= !(1 << (index % 64));
+            self.trim();
+        }
+    }
+
+    /// Amount of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Indices of the set bits in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.words.len() * 64).filter(|index| self.get(*index))
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    fn trim(&mut self) {
+        while self.words.last() == Some(&0) {
+            self.words.pop();
+        }
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut set = BitSet::new();
+        for index in iter {
+            set.set(index, true);
+        }
+        set
+    }
+}
+
+impl Codec for BitSet {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(reader: &mut impl Buffer, ctx: &DecodeContext) -> Result<Self, ProtocolError> {
+        let length = checked_length(VarInt::decode(reader)?.0, ctx.max_array_length)?;
+
+        let mut words = Vec::with_capacity(length.min(1024));
+        for _ in 0..length {
+            words.push(U64::decode(reader)?.0);
+        }
+        if words.last() == Some(&0) {
+            return Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::OverlongEncoding,
+            ));
+        }
+
+        Ok(BitSet { words })
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        encoded_length(self.words.len())?.encode(writer)?;
+        for word in &self.words {
+            U64(*word).encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn bitset_set_get() {
+        let mut set = BitSet::new();
+        set.set(3, true);
+        set.set(130, true);
+
+        assert!(set.get(3));
+        assert!(set.get(130));
+        assert!(!set.get(4));
+        assert!(!set.get(10_000));
+        assert_eq!(set.words().len(), 3);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3, 130]);
+
+        // Clearing the highest bit drops the empty words
+        set.set(130, false);
+        assert_eq!(set.words().len(), 1);
+        assert_eq!(set, BitSet::from_iter([3]));
+    }
+
+    #[test]
+    fn bitset_roundtrip() -> Result<(), ProtocolError> {
+        let set = BitSet::from_iter([0, 63, 64, 200]);
+        let mut buf: Vec<u8> = Vec::new();
+        set.encode(&mut buf)?;
+        assert_eq!(buf.len(), 1 + 4 * 8);
+
+        let mut reader = &buf[..];
+        assert_eq!(BitSet::decode(&mut reader)?, set);
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn bitset_empty_is_one_byte() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        BitSet::new().encode(&mut buf)?;
+
+        assert_eq!(buf, [0]);
+        assert!(BitSet::decode(&mut &buf[..])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn bitset_decode_fail_trailing_zero_word() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        VarInt(2).encode(&mut buf)?;
+        U64(1).encode(&mut buf)?;
+        U64(0).encode(&mut buf)?;
+
+        assert_matches!(
+            BitSet::decode(&mut &buf[..]),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::OverlongEncoding
+            ))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{
+    codec::Codec,
+    error::{ProtocolError, ProtocolViolation},
+};
+
+impl From<bool> for Bool {
+    fn from(val: bool) -> Self {
+        Bool(val)
+    }
+}
+
+/// A single byte: `0x00` is false, `0x01` is true.
+/// Any other byte is rejected, so every value has exactly one encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bool(pub bool);
+
+impl Deref for Bool {
+    type Target = bool;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Codec for Bool {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        match reader.read_u8()? {
+            0 => Ok(Bool(false)),
+            1 => Ok(Bool(true)),
+            _ => Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::InvalidValue,
+            )),
+        }
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        writer.write_u8(self.0 as u8)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn bool_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        Bool(true).encode(&mut buf)?;
+        Bool(false).encode(&mut buf)?;
+        assert_eq!(buf, [1, 0]);
+
+        let mut reader = &buf[..];
+        assert!(*Bool::decode(&mut reader)?);
+        assert!(!*Bool::decode(&mut reader)?);
+        Ok(())
+    }
+
+    #[test]
+    fn bool_decode_fail_invalid() {
+        let buf = [2u8];
+
+        assert_matches!(
+            Bool::decode(&mut &buf[..]),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::InvalidValue
+            ))
+        );
+    }
+}
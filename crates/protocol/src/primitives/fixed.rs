@@ -0,0 +1,85 @@
+//! Fixed-width unsigned integers in big-endian (network) byte order.
+//! Unlike varints, they always take the same space, which suits values
+//! that are usually large: hashes, timestamps, bit masks.
+
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, error::ProtocolError};
+
+macro_rules! fixed_width {
+    ($(#[$doc:meta])* $name:ident, $ty:ty, $read:ident, $write:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name(pub $ty);
+
+        impl From<$ty> for $name {
+            fn from(val: $ty) -> Self {
+                $name(val)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = $ty;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl Codec for $name {
+            fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+                Ok($name(reader.$read()?))
+            }
+
+            fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+                writer.$write(self.0)?;
+                Ok(())
+            }
+        }
+    };
+}
+
+fixed_width!(
+    /// 2 bytes, big-endian
+    U16, u16, read_u16_be, write_u16_be
+);
+fixed_width!(
+    /// 4 bytes, big-endian
+    U32, u32, read_u32_be, write_u32_be
+);
+fixed_width!(
+    /// 8 bytes, big-endian
+    U64, u64, read_u64_be, write_u64_be
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn fixed_width_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        U16(0x0102).encode(&mut buf)?;
+        U32(u32::MAX).encode(&mut buf)?;
+        U64(0x0102030405060708).encode(&mut buf)?;
+        assert_eq!(buf.len(), 2 + 4 + 8);
+        assert_eq!(buf[..2], [0x01, 0x02]);
+
+        let mut reader = &buf[..];
+        assert_eq!(U16::decode(&mut reader)?, U16(0x0102));
+        assert_eq!(U32::decode(&mut reader)?, U32(u32::MAX));
+        assert_eq!(U64::decode(&mut reader)?, U64(0x0102030405060708));
+        assert!(reader.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_width_decode_fail_short() {
+        let buf = [0u8; 7];
+
+        assert_matches!(U64::decode(&mut &buf[..]), Err(ProtocolError::Io(_)));
+    }
+}
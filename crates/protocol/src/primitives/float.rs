@@ -0,0 +1,72 @@
+//! IEEE 754 floats in big-endian (network) byte order.
+//! Every bit pattern is accepted, including NaNs and infinities,
+//! so the values have to be validated where they are used.
+
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, error::ProtocolError};
+
+macro_rules! float {
+    ($(#[$doc:meta])* $name:ident, $ty:ty, $read:ident, $write:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct $name(pub $ty);
+
+        impl From<$ty> for $name {
+            fn from(val: $ty) -> Self {
+                $name(val)
+            }
+        }
+
+        impl Deref for $name {
+            type Target = $ty;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl Codec for $name {
+            fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+                Ok($name(reader.$read()?))
+            }
+
+            fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+                writer.$write(self.0)?;
+                Ok(())
+            }
+        }
+    };
+}
+
+float!(
+    /// Single precision, 4 bytes
+    F32, f32, read_f32_be, write_f32_be
+);
+float!(
+    /// Double precision, 8 bytes
+    F64, f64, read_f64_be, write_f64_be
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        F32(-1.5).encode(&mut buf)?;
+        F64(f64::INFINITY).encode(&mut buf)?;
+        F32(f32::NAN).encode(&mut buf)?;
+        assert_eq!(buf.len(), 4 + 8 + 4);
+
+        let mut reader = &buf[..];
+        assert_eq!(F32::decode(&mut reader)?, F32(-1.5));
+        assert_eq!(F64::decode(&mut reader)?, F64(f64::INFINITY));
+        // NaN keeps its bits, even though it's not equal to itself
+        assert_eq!(F32::decode(&mut reader)?.to_bits(), f32::NAN.to_bits());
+        Ok(())
+    }
+}
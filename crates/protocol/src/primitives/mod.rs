@@ -1,6 +1,15 @@
+pub mod array;
+pub mod bitset;
+pub mod bool;
 pub mod byte;
+pub mod fixed;
+pub mod float;
+pub mod option;
 pub mod prefixed_array;
 pub mod string;
+pub mod svarint;
+pub mod svarlong;
 pub mod uvarint;
+pub mod uvarlong;
 pub mod varint;
 pub mod varlong;
@@ -0,0 +1,63 @@
+//! `Option<T>` is a `Bool` presence flag, followed by the value if present
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, context::DecodeContext, error::ProtocolError, primitives::bool::Bool};
+
+impl<T: Codec> Codec for Option<T> {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(reader: &mut impl Buffer, ctx: &DecodeContext) -> Result<Self, ProtocolError> {
+        match *Bool::decode(reader)? {
+            true => Ok(Some(T::decode_with(reader, ctx)?)),
+            false => Ok(None),
+        }
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        Bool(self.is_some()).encode(writer)?;
+        if let Some(value) = self {
+            value.encode(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::ProtocolViolation,
+        primitives::{string::StringProto, varint::VarInt},
+    };
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn option_roundtrip() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        Some(VarInt(300)).encode(&mut buf)?;
+        None::<VarInt>.encode(&mut buf)?;
+        assert_eq!(buf, [1, 0xAC, 0x02, 0]);
+
+        let mut reader = &buf[..];
+        assert_matches!(Option::<VarInt>::decode(&mut reader)?, Some(VarInt(300)));
+        assert_matches!(Option::<VarInt>::decode(&mut reader)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn option_decode_with_passes_context() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        Some(StringProto("venom".to_string())).encode(&mut buf)?;
+
+        assert_matches!(
+            Option::<StringProto>::decode_with(&mut &buf[..], &DecodeContext::new(4, 0)),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+        Ok(())
+    }
+}
@@ -1,19 +1,45 @@
+use crate::context::{DecodeContext, checked_length, encoded_length};
 use crate::error::ProtocolError;
 use crate::{codec::Codec, primitives::varint::VarInt};
+
+/// Upper bound of the capacity reserved up front. The length prefix is only
+/// a promise, the elements are not read yet, so a huge length with a short
+/// packet must not reserve memory for all of them.
+const PREALLOCATION_LIMIT: usize = 1024;
+
+#[derive(Debug)]
 pub struct PrefixedArray<T> {
     pub length: VarInt,
     pub data: Vec<T>,
 }
 
+/// An array too long for a `VarInt` keeps `i32::MAX` as its length,
+/// `encode` refuses it.
+impl<T> From<Vec<T>> for PrefixedArray<T> {
+    fn from(data: Vec<T>) -> Self {
+        PrefixedArray {
+            length: encoded_length(data.len()).unwrap_or(VarInt(i32::MAX)),
+            data,
+        }
+    }
+}
+
 impl<T: Codec> Codec for PrefixedArray<T> {
     fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(
+        reader: &mut impl buffer::Buffer,
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError> {
         let length = VarInt::decode(reader)?;
-        let len_usize = length.0 as usize;
+        let len_usize = checked_length(length.0, ctx.max_array_length)?;
 
-        let mut data: Vec<T> = Vec::with_capacity(len_usize);
+        let mut data: Vec<T> = Vec::with_capacity(len_usize.min(PREALLOCATION_LIMIT));
 
         for _ in 0..len_usize {
-            let item = T::decode(reader)?;
+            let item = T::decode_with(reader, ctx)?;
             data.push(item);
         }
 
@@ -21,7 +47,7 @@ impl<T: Codec> Codec for PrefixedArray<T> {
     }
 
     fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
-        VarInt(self.data.len() as i32).encode(writer)?;
+        encoded_length(self.data.len())?.encode(writer)?;
 
         for item in &self.data {
             item.encode(writer)?;
@@ -34,6 +60,9 @@ impl<T: Codec> Codec for PrefixedArray<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ProtocolViolation;
+    use crate::primitives::string::StringProto;
+    use assert_matches::assert_matches;
 
     #[test]
     fn prefixed_array_roundtrip() -> Result<(), ProtocolError> {
@@ -53,4 +82,69 @@ mod tests {
         assert_eq!(pr_ar.data[2].0, 123);
         Ok(())
     }
+
+    #[test]
+    fn prefixed_array_decode_fail_negative_length() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        VarInt(-1).encode(&mut buf)?;
+        let mut reader = &buf[..];
+
+        let res: Result<PrefixedArray<VarInt>, _> = PrefixedArray::decode(&mut reader);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::NegativeLength
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prefixed_array_decode_fail_length_exceeds_limit() -> Result<(), ProtocolError> {
+        let mut buf: Vec<u8> = Vec::new();
+        VarInt(3).encode(&mut buf)?;
+        let mut reader = &buf[..];
+
+        let ctx = DecodeContext::new(16, 2);
+        let res: Result<PrefixedArray<VarInt>, _> = PrefixedArray::decode_with(&mut reader, &ctx);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn prefixed_array_decode_huge_length_short_packet() -> Result<(), ProtocolError> {
+        // Within the limit, but the elements never arrive
+        let mut buf: Vec<u8> = Vec::new();
+        VarInt(i32::MAX).encode(&mut buf)?;
+        let mut reader = &buf[..];
+
+        let ctx = DecodeContext::new(16, usize::MAX);
+        let res: Result<PrefixedArray<VarInt>, _> = PrefixedArray::decode_with(&mut reader, &ctx);
+        assert_matches!(res, Err(ProtocolError::Io(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn prefixed_array_nested_context() -> Result<(), ProtocolError> {
+        // The limit of the outer array is fine, the inner string is too long
+        let mut buf: Vec<u8> = Vec::new();
+        PrefixedArray::from(vec![StringProto("long".to_string())]).encode(&mut buf)?;
+        let mut reader = &buf[..];
+
+        let ctx = DecodeContext::new(3, 1);
+        let res: Result<PrefixedArray<StringProto>, _> =
+            PrefixedArray::decode_with(&mut reader, &ctx);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+        Ok(())
+    }
 }
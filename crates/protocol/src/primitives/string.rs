@@ -1,12 +1,24 @@
-use crate::{codec::Codec, error::ProtocolError, primitives::varint::VarInt};
+use crate::{
+    codec::Codec,
+    context::{DecodeContext, checked_length, encoded_length},
+    error::ProtocolError,
+    primitives::varint::VarInt,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StringProto(pub String);
 
 impl Codec for StringProto {
     fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
+        Self::decode_with(reader, &DecodeContext::DEFAULT)
+    }
+
+    fn decode_with(
+        reader: &mut impl buffer::Buffer,
+        ctx: &DecodeContext,
+    ) -> Result<Self, ProtocolError> {
         let length = VarInt::decode(reader)?;
-        let len_usize = length.0 as usize;
+        let len_usize = checked_length(length.0, ctx.max_string_length)?;
         let mut buf = vec![0u8; len_usize];
         reader.read_exact(&mut buf)?;
         let data = String::from_utf8(buf)?;
@@ -18,9 +30,8 @@ impl Codec for StringProto {
         writer: &mut impl buffer::BufferMut,
     ) -> Result<(), crate::error::ProtocolError> {
         let string_bytes = self.0.as_bytes();
-        let length = string_bytes.len();
 
-        VarInt::from(length as i32).encode(writer)?;
+        encoded_length(string_bytes.len())?.encode(writer)?;
         writer.write_all(string_bytes)?;
 
         Ok(())
@@ -31,6 +42,8 @@ impl Codec for StringProto {
 mod tests {
 
     use super::*;
+    use crate::error::ProtocolViolation;
+    use assert_matches::assert_matches;
 
     #[test]
     fn stringproto_roundtrip() -> Result<(), ProtocolError> {
@@ -52,4 +65,56 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn stringproto_decode_fail_negative_length() -> Result<(), ProtocolError> {
+        let mut stream: Vec<u8> = Vec::new();
+        VarInt(-5).encode(&mut stream)?;
+        let mut buf = &stream[..];
+
+        assert_matches!(
+            StringProto::decode(&mut buf),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::NegativeLength
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stringproto_decode_fail_length_exceeds_limit() -> Result<(), ProtocolError> {
+        let mut stream: Vec<u8> = Vec::new();
+        // Would be a 2 GiB allocation without the limit
+        VarInt(i32::MAX).encode(&mut stream)?;
+        let mut buf = &stream[..];
+
+        assert_matches!(
+            StringProto::decode(&mut buf),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn stringproto_decode_with_custom_limit() -> Result<(), ProtocolError> {
+        let mut stream: Vec<u8> = Vec::new();
+        StringProto("Hello".to_string()).encode(&mut stream)?;
+
+        let mut buf = &stream[..];
+        assert_eq!(
+            StringProto::decode_with(&mut buf, &DecodeContext::new(5, 0))?.0,
+            "Hello"
+        );
+
+        let mut buf = &stream[..];
+        assert_matches!(
+            StringProto::decode_with(&mut buf, &DecodeContext::new(4, 0)),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::LengthExceedsLimit
+            ))
+        );
+        Ok(())
+    }
 }
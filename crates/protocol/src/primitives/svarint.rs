@@ -0,0 +1,126 @@
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, error::ProtocolError, primitives::uvarint::UVarInt};
+
+impl From<i32> for SVarInt {
+    fn from(val: i32) -> Self {
+        SVarInt(val)
+    }
+}
+
+/// signed variable integer = 32-bits integer with ZigZag encoding
+/// Unlike `VarInt`, small negative numbers are also short: `-1` is 1 byte, not 5.
+/// Use it for values that are often negative, like relative head movement.
+/// See more -> https://protobuf.dev/programming-guides/encoding/#signed-ints
+#[derive(Debug)]
+pub struct SVarInt(pub i32);
+
+impl Deref for SVarInt {
+    type Target = i32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl SVarInt {
+    /// Maps signed integers to unsigned ones: 0 -> 0, -1 -> 1, 1 -> 2, -2 -> 3...
+    pub fn zigzag(val: i32) -> u32 {
+        ((val << 1) ^ (val >> 31)) as u32
+    }
+
+    pub fn unzigzag(val: u32) -> i32 {
+        ((val >> 1) as i32) ^ -((val & 1) as i32)
+    }
+}
+
+impl Codec for SVarInt {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        let zigzagged = UVarInt::decode(reader)?.0;
+        Ok(SVarInt(Self::unzigzag(zigzagged)))
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        UVarInt(Self::zigzag(self.0)).encode(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProtocolViolation;
+    use assert_matches::assert_matches;
+
+    fn encoded(val: i32) -> Result<Vec<u8>, ProtocolError> {
+        let mut writer = Vec::new();
+        SVarInt(val).encode(&mut writer)?;
+        Ok(writer)
+    }
+
+    #[test]
+    fn svarint_zigzag_mapping() {
+        // Reference values from the protobuf documentation
+        assert_eq!(SVarInt::zigzag(0), 0);
+        assert_eq!(SVarInt::zigzag(-1), 1);
+        assert_eq!(SVarInt::zigzag(1), 2);
+        assert_eq!(SVarInt::zigzag(-2), 3);
+        assert_eq!(SVarInt::zigzag(i32::MAX), 0xFFFF_FFFE);
+        assert_eq!(SVarInt::zigzag(i32::MIN), 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn svarint_encode_length() -> Result<(), ProtocolError> {
+        assert_eq!(encoded(0)?, [0x00]);
+        assert_eq!(encoded(-1)?, [0x01]);
+        assert_eq!(encoded(1)?, [0x02]);
+        assert_eq!(encoded(-64)?.len(), 1);
+        assert_eq!(encoded(64)?.len(), 2);
+        assert_eq!(encoded(i32::MIN)?, [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+        Ok(())
+    }
+
+    #[test]
+    fn svarint_roundtrip_boundaries() -> Result<(), ProtocolError> {
+        for val in [
+            0,
+            1,
+            -1,
+            63,
+            -64,
+            64,
+            -65,
+            i16::MAX as i32,
+            i32::MAX,
+            i32::MIN,
+        ] {
+            let writer = encoded(val)?;
+            let mut reader = &writer[..];
+            assert_eq!(SVarInt::decode(&mut reader)?.0, val);
+            assert!(reader.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn svarint_decode_fail_protocol_violation() {
+        let buf: [u8; 6] = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80];
+        let mut reader: &[u8] = &buf;
+        let res = SVarInt::decode(&mut reader);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarIntTooLong
+            ))
+        );
+    }
+
+    #[test]
+    fn svarint_decode_fail_io() {
+        let buf: [u8; 0] = [];
+        let mut reader: &[u8] = &buf;
+        let res = SVarInt::decode(&mut reader);
+        assert_matches!(res, Err(ProtocolError::Io(_)))
+    }
+}
@@ -0,0 +1,131 @@
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{codec::Codec, error::ProtocolError, primitives::uvarlong::UVarLong};
+
+impl From<i64> for SVarLong {
+    fn from(val: i64) -> Self {
+        SVarLong(val)
+    }
+}
+
+/// signed variable integer = 64-bits integer with ZigZag encoding
+/// Unlike `VarLong`, small negative numbers are also short: `-1` is 1 byte, not 10.
+/// Use it for values that are often negative, like relative head movement.
+/// See more -> https://protobuf.dev/programming-guides/encoding/#signed-ints
+#[derive(Debug)]
+pub struct SVarLong(pub i64);
+
+impl Deref for SVarLong {
+    type Target = i64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl SVarLong {
+    /// Maps signed integers to unsigned ones: 0 -> 0, -1 -> 1, 1 -> 2, -2 -> 3...
+    pub fn zigzag(val: i64) -> u64 {
+        ((val << 1) ^ (val >> 63)) as u64
+    }
+
+    pub fn unzigzag(val: u64) -> i64 {
+        ((val >> 1) as i64) ^ -((val & 1) as i64)
+    }
+}
+
+impl Codec for SVarLong {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        let zigzagged = UVarLong::decode(reader)?.0;
+        Ok(SVarLong(Self::unzigzag(zigzagged)))
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        UVarLong(Self::zigzag(self.0)).encode(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProtocolViolation;
+    use assert_matches::assert_matches;
+
+    fn encoded(val: i64) -> Result<Vec<u8>, ProtocolError> {
+        let mut writer = Vec::new();
+        SVarLong(val).encode(&mut writer)?;
+        Ok(writer)
+    }
+
+    #[test]
+    fn svarlong_zigzag_mapping() {
+        // Reference values from the protobuf documentation
+        assert_eq!(SVarLong::zigzag(0), 0);
+        assert_eq!(SVarLong::zigzag(-1), 1);
+        assert_eq!(SVarLong::zigzag(1), 2);
+        assert_eq!(SVarLong::zigzag(-2), 3);
+        assert_eq!(SVarLong::zigzag(i64::MAX), 0xFFFF_FFFF_FFFF_FFFE);
+        assert_eq!(SVarLong::zigzag(i64::MIN), 0xFFFF_FFFF_FFFF_FFFF);
+    }
+
+    #[test]
+    fn svarlong_encode_length() -> Result<(), ProtocolError> {
+        assert_eq!(encoded(0)?, [0x00]);
+        assert_eq!(encoded(-1)?, [0x01]);
+        assert_eq!(encoded(1)?, [0x02]);
+        assert_eq!(encoded(-64)?.len(), 1);
+        assert_eq!(encoded(64)?.len(), 2);
+        assert_eq!(
+            encoded(i64::MIN)?,
+            [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn svarlong_roundtrip_boundaries() -> Result<(), ProtocolError> {
+        for val in [
+            0,
+            1,
+            -1,
+            63,
+            -64,
+            64,
+            -65,
+            i32::MIN as i64,
+            i64::MAX,
+            i64::MIN,
+        ] {
+            let writer = encoded(val)?;
+            let mut reader = &writer[..];
+            assert_eq!(SVarLong::decode(&mut reader)?.0, val);
+            assert!(reader.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn svarlong_decode_fail_protocol_violation() {
+        let buf: [u8; 11] = [
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+        ];
+        let mut reader: &[u8] = &buf;
+        let res = SVarLong::decode(&mut reader);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarLongTooLong
+            ))
+        );
+    }
+
+    #[test]
+    fn svarlong_decode_fail_io() {
+        let buf: [u8; 0] = [];
+        let mut reader: &[u8] = &buf;
+        let res = SVarLong::decode(&mut reader);
+        assert_matches!(res, Err(ProtocolError::Io(_)))
+    }
+}
@@ -1,33 +1,86 @@
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
 use crate::{
     codec::Codec,
     error::{ProtocolError, ProtocolViolation},
-    primitives::varint::VarInt,
 };
 
+impl From<u32> for UVarInt {
+    fn from(val: u32) -> Self {
+        UVarInt(val)
+    }
+}
+
+const UVARINT_LENGTH: u8 = 5;
+
 /// unsigned variable integer = 32-bits unsigned integer
 /// Defines the implementation of variable integer from protocol buffer
 /// See more -> https://protobuf.dev/programming-guides/encoding/#varints
+///
+/// The decoder accepts only the canonical (shortest) encoding of a value,
+/// so every value has exactly one representation on the wire.
 #[derive(Debug)]
 pub struct UVarInt(pub u32);
 
+impl Deref for UVarInt {
+    type Target = u32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl Codec for UVarInt {
-    fn decode(reader: &mut impl buffer::Buffer) -> Result<Self, ProtocolError> {
-        let num = {
-            let val = VarInt::decode(reader)?;
-            if val.0 < 0 {
-                return Err(ProtocolError::ProtocolViolation(
-                    ProtocolViolation::NegativeUnsigned,
-                ));
-            } else {
-                val
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        let mut result: u32 = 0;
+        for num_read in 0..UVARINT_LENGTH {
+            let byte = reader.read_u8()?;
+            let value = (byte & 0b0111_1111) as u32;
+
+            // 4 * 7 = 28 bits are already read, only 4 are left for the last byte
+            if num_read == UVARINT_LENGTH - 1 {
+                if (byte & 0b1000_0000) != 0 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::VarIntTooLong,
+                    ));
+                }
+                if value > 0b0000_1111 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::VarIntOverflow,
+                    ));
+                }
             }
-        };
-        // absolutely logic-safety after this check
-        Ok(UVarInt(num.0 as u32))
+
+            result |= value << (7 * num_read);
+
+            if (byte & 0b1000_0000) == 0 {
+                // A zero last byte means it could have been shorter, e.g. [0x80, 0x00]
+                if byte == 0 && num_read > 0 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::OverlongEncoding,
+                    ));
+                }
+                return Ok(UVarInt(result));
+            }
+        }
+        unreachable!("the last byte either ends the number or is rejected")
     }
 
-    fn encode(&self, writer: &mut impl buffer::BufferMut) -> Result<(), ProtocolError> {
-        VarInt::encode(&VarInt(self.0 as i32), writer)?;
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        let mut value = self.0;
+        loop {
+            let mut byte = (value & 0b0111_1111) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0b1000_0000;
+            }
+            writer.write_u8(byte)?;
+            if value == 0 {
+                break;
+            }
+        }
         Ok(())
     }
 }
@@ -36,6 +89,7 @@ impl Codec for UVarInt {
 mod tests {
     use super::*;
     use assert_matches::assert_matches;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
 
     #[test]
     fn uvarint_roundtrip() -> Result<(), ProtocolError> {
@@ -47,18 +101,92 @@ mod tests {
         assert_eq!(num.0, uvarint.0);
         Ok(())
     }
+
+    #[test]
+    fn uvarint_roundtrip_above_i32_max() -> Result<(), ProtocolError> {
+        for val in [i32::MAX as u32 + 1, u32::MAX] {
+            let mut writer: Vec<u8> = Vec::new();
+            UVarInt(val).encode(&mut writer)?;
+            assert_eq!(writer.len(), 5);
+            let mut buf = &writer[..];
+            assert_eq!(UVarInt::decode(&mut buf)?.0, val);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn uvarint_decode_fail_protocol_violation() {
+        let buf: [u8; 6] = [0x80, 0x80, 0x80, 0x80, 0x80, 0x80];
+        let mut reader: &[u8] = &buf;
+        assert_matches!(
+            UVarInt::decode(&mut reader),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarIntTooLong
+            ))
+        );
+    }
+
     #[test]
-    fn uvarint_decode_fail_protocol_violation() -> Result<(), ProtocolError> {
-        let mut writer: Vec<u8> = Vec::new();
-        VarInt(-58).encode(&mut writer)?;
-        let mut buf = &writer[..];
-        let res = UVarInt::decode(&mut buf);
+    fn uvarint_decode_fail_overflow() {
+        // u32::MAX is [0xFF, 0xFF, 0xFF, 0xFF, 0x0F], anything above in the last byte overflows
+        let buf: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x1F];
+        let mut reader: &[u8] = &buf;
         assert_matches!(
-            res,
+            UVarInt::decode(&mut reader),
             Err(ProtocolError::ProtocolViolation(
-                ProtocolViolation::NegativeUnsigned
+                ProtocolViolation::VarIntOverflow
             ))
         );
+    }
+
+    #[test]
+    fn uvarint_decode_fail_overlong() {
+        // 0 and 1 with a redundant continuation byte
+        for buf in [&[0x80, 0x00][..], &[0x81, 0x80, 0x00][..]] {
+            let mut reader = buf;
+            assert_matches!(
+                UVarInt::decode(&mut reader),
+                Err(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::OverlongEncoding
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn uvarint_decode_fail_io() {
+        let buf: [u8; 2] = [0x80, 0x80];
+        let mut reader: &[u8] = &buf;
+        assert_matches!(UVarInt::decode(&mut reader), Err(ProtocolError::Io(_)));
+    }
+
+    /// Every value survives the roundtrip, and every byte string the decoder
+    /// accepts is exactly what the encoder would produce for that value
+    #[test]
+    fn uvarint_fuzz_against_encoder() -> Result<(), ProtocolError> {
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+
+        for _ in 0..10_000 {
+            // Spread values over all lengths, not only the 5-byte ones
+            let val = rng.r#gen::<u32>() >> rng.gen_range(0..32);
+            let mut writer: Vec<u8> = Vec::new();
+            UVarInt(val).encode(&mut writer)?;
+            let mut buf = &writer[..];
+            assert_eq!(UVarInt::decode(&mut buf)?.0, val);
+            assert!(buf.is_empty());
+        }
+
+        for _ in 0..10_000 {
+            let len = rng.gen_range(1..=6);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.r#gen()).collect();
+            let mut buf = &bytes[..];
+            if let Ok(val) = UVarInt::decode(&mut buf) {
+                let consumed = bytes.len() - buf.len();
+                let mut writer: Vec<u8> = Vec::new();
+                val.encode(&mut writer)?;
+                assert_eq!(writer, bytes[..consumed]);
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,194 @@
+use std::ops::Deref;
+
+use buffer::{Buffer, BufferMut};
+
+use crate::{
+    codec::Codec,
+    error::{ProtocolError, ProtocolViolation},
+};
+
+impl From<u64> for UVarLong {
+    fn from(val: u64) -> Self {
+        UVarLong(val)
+    }
+}
+
+const UVARLONG_LENGTH: u8 = 10;
+
+/// unsigned variable long = 64-bits unsigned integer
+/// Defines the implementation of variable integer from protocol buffer
+/// See more -> https://protobuf.dev/programming-guides/encoding/#varints
+///
+/// The decoder accepts only the canonical (shortest) encoding of a value,
+/// so every value has exactly one representation on the wire.
+#[derive(Debug)]
+pub struct UVarLong(pub u64);
+
+impl Deref for UVarLong {
+    type Target = u64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Codec for UVarLong {
+    fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
+        let mut result: u64 = 0;
+        for num_read in 0..UVARLONG_LENGTH {
+            let byte = reader.read_u8()?;
+            let value = (byte & 0b0111_1111) as u64;
+
+            // 9 * 7 = 63 bits are already read, only 1 is left for the last byte
+            if num_read == UVARLONG_LENGTH - 1 {
+                if (byte & 0b1000_0000) != 0 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::VarLongTooLong,
+                    ));
+                }
+                if value > 0b0000_0001 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::VarLongOverflow,
+                    ));
+                }
+            }
+
+            result |= value << (7 * num_read);
+
+            if (byte & 0b1000_0000) == 0 {
+                // A zero last byte means it could have been shorter, e.g. [0x80, 0x00]
+                if byte == 0 && num_read > 0 {
+                    return Err(ProtocolError::ProtocolViolation(
+                        ProtocolViolation::OverlongEncoding,
+                    ));
+                }
+                return Ok(UVarLong(result));
+            }
+        }
+        unreachable!("the last byte either ends the number or is rejected")
+    }
+
+    fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
+        let mut value = self.0;
+        loop {
+            let mut byte = (value & 0b0111_1111) as u8;
+            value >>= 7;
+            if value != 0 {
+                byte |= 0b1000_0000;
+            }
+            writer.write_u8(byte)?;
+            if value == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    #[test]
+    fn uvarlong_roundtrip() -> Result<(), ProtocolError> {
+        let uvarlong = UVarLong(42);
+        let mut reader: Vec<u8> = Vec::new();
+        uvarlong.encode(&mut reader)?;
+        let mut buf = &reader[..];
+        let num = UVarLong::decode(&mut buf)?;
+        assert_eq!(num.0, uvarlong.0);
+        Ok(())
+    }
+
+    #[test]
+    fn uvarlong_roundtrip_above_i64_max() -> Result<(), ProtocolError> {
+        for val in [i64::MAX as u64 + 1, u64::MAX] {
+            let mut writer: Vec<u8> = Vec::new();
+            UVarLong(val).encode(&mut writer)?;
+            assert_eq!(writer.len(), 10);
+            let mut buf = &writer[..];
+            assert_eq!(UVarLong::decode(&mut buf)?.0, val);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn uvarlong_decode_fail_protocol_violation() {
+        let buf: [u8; 11] = [
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+        ];
+        let mut reader: &[u8] = &buf;
+        assert_matches!(
+            UVarLong::decode(&mut reader),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarLongTooLong
+            ))
+        );
+    }
+
+    #[test]
+    fn uvarlong_decode_fail_overflow() {
+        // u64::MAX ends with 0x01, anything above in the last byte overflows
+        let buf: [u8; 10] = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02];
+        let mut reader: &[u8] = &buf;
+        assert_matches!(
+            UVarLong::decode(&mut reader),
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarLongOverflow
+            ))
+        );
+    }
+
+    #[test]
+    fn uvarlong_decode_fail_overlong() {
+        // 0 and 1 with a redundant continuation byte
+        for buf in [&[0x80, 0x00][..], &[0x81, 0x80, 0x00][..]] {
+            let mut reader = buf;
+            assert_matches!(
+                UVarLong::decode(&mut reader),
+                Err(ProtocolError::ProtocolViolation(
+                    ProtocolViolation::OverlongEncoding
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn uvarlong_decode_fail_io() {
+        let buf: [u8; 2] = [0x80, 0x80];
+        let mut reader: &[u8] = &buf;
+        assert_matches!(UVarLong::decode(&mut reader), Err(ProtocolError::Io(_)));
+    }
+
+    /// Every value survives the roundtrip, and every byte string the decoder
+    /// accepts is exactly what the encoder would produce for that value
+    #[test]
+    fn uvarlong_fuzz_against_encoder() -> Result<(), ProtocolError> {
+        let mut rng = StdRng::seed_from_u64(0x5EED);
+
+        for _ in 0..10_000 {
+            // Spread values over all lengths, not only the 5-byte ones
+            let val = rng.r#gen::<u64>() >> rng.gen_range(0..64);
+            let mut writer: Vec<u8> = Vec::new();
+            UVarLong(val).encode(&mut writer)?;
+            let mut buf = &writer[..];
+            assert_eq!(UVarLong::decode(&mut buf)?.0, val);
+            assert!(buf.is_empty());
+        }
+
+        for _ in 0..10_000 {
+            let len = rng.gen_range(1..=11);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.r#gen()).collect();
+            let mut buf = &bytes[..];
+            if let Ok(val) = UVarLong::decode(&mut buf) {
+                let consumed = bytes.len() - buf.len();
+                let mut writer: Vec<u8> = Vec::new();
+                val.encode(&mut writer)?;
+                assert_eq!(writer, bytes[..consumed]);
+            }
+        }
+        Ok(())
+    }
+}
@@ -2,10 +2,7 @@ use std::ops::Deref;
 
 use buffer::{Buffer, BufferMut};
 
-use crate::{
-    codec::Codec,
-    error::{ProtocolError, ProtocolViolation},
-};
+use crate::{codec::Codec, error::ProtocolError, primitives::uvarint::UVarInt};
 
 impl From<i32> for VarInt {
     fn from(val: i32) -> Self {
@@ -13,8 +10,6 @@ impl From<i32> for VarInt {
     }
 }
 
-const VARINT_LENGTH: i8 = 5;
-
 /// variable integer = 32-bits integer
 /// Defines the implementation of variable integer from protocol buffer
 /// See more -> https://protobuf.dev/programming-guides/encoding/#varints
@@ -29,49 +24,22 @@ impl Deref for VarInt {
     }
 }
 
+/// Negative numbers are encoded as their two's complement `u32`,
+/// so they always take the maximum length. Use `SVarInt` for values that are often negative.
 impl Codec for VarInt {
     fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
-        let mut num_read = 0;
-        let mut result: i32 = 0;
-        loop {
-            let byte = reader.read_u8()?;
-            let value = (byte & 0b0111_1111) as i32;
-            result |= value << (7 * num_read);
-
-            if (byte & 0b1000_0000) == 0 {
-                break;
-            }
-
-            num_read += 1;
-            if num_read >= VARINT_LENGTH {
-                return Err(ProtocolError::ProtocolViolation(
-                    ProtocolViolation::VarIntTooLong,
-                ));
-            }
-        }
-        Ok(VarInt(result))
+        Ok(VarInt(UVarInt::decode(reader)?.0 as i32))
     }
 
     fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
-        let mut value: u32 = self.0 as u32;
-        loop {
-            let mut byte = (value & 0b0111_1111) as u8;
-            value >>= 7;
-            if value != 0 {
-                byte |= 0b1000_0000;
-            }
-            writer.write_u8(byte)?;
-            if value == 0 {
-                break;
-            }
-        }
-        Ok(())
+        UVarInt(self.0 as u32).encode(writer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ProtocolViolation;
     use assert_matches::assert_matches;
 
     #[test]
@@ -115,4 +83,29 @@ mod tests {
         assert_eq!(comp, num_comp.0);
         Ok(())
     }
+
+    #[test]
+    fn varint_decode_fail_garbage_high_bits() {
+        // The last byte may carry only the bits that still fit into i32
+        let buf: [u8; 5] = [0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        let mut reader: &[u8] = &buf;
+        let res = VarInt::decode(&mut reader);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarIntOverflow
+            ))
+        );
+    }
+
+    #[test]
+    fn varint_roundtrip_negative() -> Result<(), ProtocolError> {
+        for comp in [-1, i32::MIN] {
+            let mut writer = Vec::new();
+            VarInt(comp).encode(&mut writer)?;
+            let mut writer_comp = &writer[..];
+            assert_eq!(comp, VarInt::decode(&mut writer_comp)?.0);
+        }
+        Ok(())
+    }
 }
@@ -2,12 +2,13 @@ use std::ops::Deref;
 
 use buffer::{Buffer, BufferMut};
 
-use crate::{
-    codec::Codec,
-    error::{ProtocolError, ProtocolViolation},
-};
+use crate::{codec::Codec, error::ProtocolError, primitives::uvarlong::UVarLong};
 
-const VARLONG_LENGTH: i8 = 10;
+impl From<i64> for VarLong {
+    fn from(val: i64) -> Self {
+        VarLong(val)
+    }
+}
 
 /// variable long = 64-bits integer
 /// Defines the implementation of variable integer from protocol buffer
@@ -23,54 +24,22 @@ impl Deref for VarLong {
     }
 }
 
+/// Negative numbers are encoded as their two's complement `u64`,
+/// so they always take the maximum length. Use `SVarLong` for values that are often negative.
 impl Codec for VarLong {
     fn decode(reader: &mut impl Buffer) -> Result<Self, ProtocolError> {
-        let mut num_read = 0;
-        let mut result: i64 = 0;
-        loop {
-            let byte = reader.read_u8()?;
-            let value = (byte & 0b0111_1111) as i64;
-
-            result |= value << (7 * num_read);
-
-            if (byte & 0b1000_0000) == 0 {
-                break;
-            }
-
-            num_read += 1;
-
-            if num_read >= VARLONG_LENGTH {
-                return Err(ProtocolError::ProtocolViolation(
-                    ProtocolViolation::VarLongTooLong,
-                ));
-            }
-            if (byte & 0b1000_0000) == 0 {
-                break;
-            }
-        }
-        Ok(VarLong(result))
+        Ok(VarLong(UVarLong::decode(reader)?.0 as i64))
     }
 
     fn encode(&self, writer: &mut impl BufferMut) -> Result<(), ProtocolError> {
-        let mut value: u64 = self.0 as u64;
-        loop {
-            let mut byte = (value & 0b0111_1111) as u8;
-            value >>= 7;
-            if value != 0 {
-                byte |= 0b1000_0000;
-            }
-            writer.write_u8(byte)?;
-            if value == 0 {
-                break;
-            }
-        }
-        Ok(())
+        UVarLong(self.0 as u64).encode(writer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ProtocolViolation;
     use assert_matches::assert_matches;
 
     #[test]
@@ -116,4 +85,29 @@ mod tests {
         assert_eq!(comp, num_comp.0);
         Ok(())
     }
+
+    #[test]
+    fn varlong_decode_fail_garbage_high_bits() {
+        // The last byte may carry only the bits that still fit into i64
+        let buf: [u8; 10] = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F];
+        let mut reader: &[u8] = &buf;
+        let res = VarLong::decode(&mut reader);
+        assert_matches!(
+            res,
+            Err(ProtocolError::ProtocolViolation(
+                ProtocolViolation::VarLongOverflow
+            ))
+        );
+    }
+
+    #[test]
+    fn varlong_roundtrip_negative() -> Result<(), ProtocolError> {
+        for comp in [-1, i64::MIN] {
+            let mut writer = Vec::new();
+            VarLong(comp).encode(&mut writer)?;
+            let mut writer_comp = &writer[..];
+            assert_eq!(comp, VarLong::decode(&mut writer_comp)?.0);
+        }
+        Ok(())
+    }
 }
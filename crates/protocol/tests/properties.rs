@@ -0,0 +1,152 @@
+//! Property tests of the primitives: values survive a roundtrip,
+//! and arbitrary input never makes the decoder panic.
+//!
+//! The same properties are checked by the fuzz targets in `/fuzz`,
+//! these ones run in a normal `cargo test`.
+
+use proptest::prelude::*;
+use protocol::{
+    codec::Codec,
+    error::ProtocolError,
+    primitives::{
+        array::Array,
+        bitset::BitSet,
+        bool::Bool,
+        fixed::{U16, U32, U64},
+        float::{F32, F64},
+        prefixed_array::PrefixedArray,
+        string::StringProto,
+        svarint::SVarInt,
+        svarlong::SVarLong,
+        uvarint::UVarInt,
+        uvarlong::UVarLong,
+        varint::VarInt,
+        varlong::VarLong,
+    },
+};
+
+fn encode(value: &impl Codec) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value
+        .encode(&mut buf)
+        .expect("encoding into a Vec can't fail");
+    buf
+}
+
+/// Whatever the decoder accepts must be encoded back into the same bytes,
+/// the encodings are canonical.
+fn check_decoded<T: Codec>(data: &[u8]) -> Result<(), TestCaseError> {
+    let mut reader = data;
+    let Ok(value) = T::decode(&mut reader) else {
+        return Ok(());
+    };
+    let consumed = &data[..data.len() - reader.len()];
+    prop_assert_eq!(encode(&value), consumed);
+    Ok(())
+}
+
+/// Decodes the encoded value back and checks that nothing is left
+fn roundtrip<T: Codec>(value: &T) -> Result<T, TestCaseError> {
+    let buf = encode(value);
+    let mut reader = &buf[..];
+    let decoded = T::decode(&mut reader)
+        .map_err(|err: ProtocolError| TestCaseError::fail(format!("{err:?}")))?;
+    prop_assert!(reader.is_empty(), "{} bytes are left", reader.len());
+    Ok(decoded)
+}
+
+proptest! {
+    #[test]
+    fn varint_roundtrip(value: i32) {
+        prop_assert_eq!(roundtrip(&VarInt(value))?.0, value);
+    }
+
+    #[test]
+    fn varlong_roundtrip(value: i64) {
+        prop_assert_eq!(roundtrip(&VarLong(value))?.0, value);
+    }
+
+    #[test]
+    fn uvarint_roundtrip(value: u32) {
+        prop_assert_eq!(roundtrip(&UVarInt(value))?.0, value);
+    }
+
+    #[test]
+    fn uvarlong_roundtrip(value: u64) {
+        prop_assert_eq!(roundtrip(&UVarLong(value))?.0, value);
+    }
+
+    #[test]
+    fn svarint_roundtrip(value: i32) {
+        prop_assert_eq!(roundtrip(&SVarInt(value))?.0, value);
+    }
+
+    #[test]
+    fn svarlong_roundtrip(value: i64) {
+        prop_assert_eq!(roundtrip(&SVarLong(value))?.0, value);
+    }
+
+    #[test]
+    fn stringproto_roundtrip(value in ".{0,64}") {
+        prop_assert_eq!(roundtrip(&StringProto(value.clone()))?.0, value);
+    }
+
+    #[test]
+    fn prefixed_array_roundtrip(values in prop::collection::vec(any::<i32>(), 0..64)) {
+        let array = PrefixedArray::from(values.iter().copied().map(VarInt).collect::<Vec<_>>());
+        let decoded = roundtrip(&array)?;
+        prop_assert_eq!(decoded.length.0 as usize, values.len());
+        prop_assert_eq!(decoded.data.iter().map(|v| v.0).collect::<Vec<_>>(), values);
+    }
+
+    #[test]
+    fn fixed_width_roundtrip(a: u16, b: u32, c: u64, flag: bool) {
+        prop_assert_eq!(roundtrip(&U16(a))?, U16(a));
+        prop_assert_eq!(roundtrip(&U32(b))?, U32(b));
+        prop_assert_eq!(roundtrip(&U64(c))?, U64(c));
+        prop_assert_eq!(roundtrip(&Bool(flag))?, Bool(flag));
+    }
+
+    #[test]
+    fn float_roundtrip(a: f32, b: f64) {
+        // Compared by bits, so NaNs are fine too
+        prop_assert_eq!(roundtrip(&F32(a))?.to_bits(), a.to_bits());
+        prop_assert_eq!(roundtrip(&F64(b))?.to_bits(), b.to_bits());
+    }
+
+    #[test]
+    fn option_roundtrip(value: Option<i32>) {
+        let decoded = roundtrip(&value.map(VarInt))?;
+        prop_assert_eq!(decoded.map(|v| v.0), value);
+    }
+
+    #[test]
+    fn array_roundtrip(values: [u16; 4]) {
+        prop_assert_eq!(roundtrip(&Array(values.map(U16)))?, Array(values.map(U16)));
+    }
+
+    #[test]
+    fn bitset_roundtrip(indices in prop::collection::vec(0usize..1024, 0..32)) {
+        let set = BitSet::from_iter(indices);
+        prop_assert_eq!(roundtrip(&set)?, set);
+    }
+
+    #[test]
+    fn decode_arbitrary_bytes(data in prop::collection::vec(any::<u8>(), 0..64)) {
+        check_decoded::<VarInt>(&data)?;
+        check_decoded::<VarLong>(&data)?;
+        check_decoded::<UVarInt>(&data)?;
+        check_decoded::<UVarLong>(&data)?;
+        check_decoded::<SVarInt>(&data)?;
+        check_decoded::<SVarLong>(&data)?;
+        check_decoded::<StringProto>(&data)?;
+        check_decoded::<PrefixedArray<VarInt>>(&data)?;
+        check_decoded::<PrefixedArray<StringProto>>(&data)?;
+        check_decoded::<Bool>(&data)?;
+        check_decoded::<U64>(&data)?;
+        check_decoded::<F64>(&data)?;
+        check_decoded::<Option<StringProto>>(&data)?;
+        check_decoded::<Array<VarInt, 3>>(&data)?;
+        check_decoded::<BitSet>(&data)?;
+    }
+}
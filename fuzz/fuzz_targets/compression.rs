@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::compression::Compression;
+
+/// Small limit, so the fuzzer finds the edges of it quickly
+const MAX_DECOMPRESSED_LENGTH: usize = 64 * 1024;
+
+fuzz_target!(|data: &[u8]| {
+    let compression = Compression::new(16).with_max_decompressed_length(MAX_DECOMPRESSED_LENGTH);
+    let Ok(body) = compression.decompress(data) else {
+        return;
+    };
+    assert!(body.len() <= MAX_DECOMPRESSED_LENGTH);
+
+    let frame_body = compression.compress(&body).unwrap();
+    assert_eq!(compression.decompress(&frame_body).unwrap(), body);
+});
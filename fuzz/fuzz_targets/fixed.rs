@@ -0,0 +1,24 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::primitives::{
+    array::Array,
+    bitset::BitSet,
+    bool::Bool,
+    fixed::{U16, U32, U64},
+    float::{F32, F64},
+    varint::VarInt,
+};
+use venomized_fuzz::check_codec;
+
+fuzz_target!(|data: &[u8]| {
+    check_codec::<Bool>(data);
+    check_codec::<U16>(data);
+    check_codec::<U32>(data);
+    check_codec::<U64>(data);
+    check_codec::<F32>(data);
+    check_codec::<F64>(data);
+    check_codec::<Option<VarInt>>(data);
+    check_codec::<Array<VarInt, 4>>(data);
+    check_codec::<BitSet>(data);
+});
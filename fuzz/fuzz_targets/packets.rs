@@ -0,0 +1,37 @@
+#![no_main]
+
+use common::net::{
+    packets::{PACKET_COUNT, Packet, visit_packets},
+    spec::PacketVisitor,
+};
+use libfuzzer_sys::fuzz_target;
+use venomized_fuzz::check_codec;
+
+/// Checks the packet at `selector` in the order of the spec
+struct CheckPacket<'a> {
+    selector: usize,
+    index: usize,
+    data: &'a [u8],
+}
+
+impl PacketVisitor for CheckPacket<'_> {
+    fn visit<P: Packet>(&mut self) {
+        if self.index == self.selector {
+            check_codec::<P::Data>(self.data);
+        }
+        self.index += 1;
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // The first byte picks the packet, so the fuzzer can learn
+    // the layout of every payload separately
+    let Some((&selector, data)) = data.split_first() else {
+        return;
+    };
+    visit_packets(&mut CheckPacket {
+        selector: selector as usize % PACKET_COUNT,
+        index: 0,
+        data,
+    });
+});
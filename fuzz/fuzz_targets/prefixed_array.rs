@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::primitives::{
+    prefixed_array::PrefixedArray, string::StringProto, varint::VarInt, varlong::VarLong,
+};
+use venomized_fuzz::check_codec;
+
+fuzz_target!(|data: &[u8]| {
+    // Fixed and variable sized elements take different paths in the decoder
+    check_codec::<PrefixedArray<VarInt>>(data);
+    check_codec::<PrefixedArray<VarLong>>(data);
+    check_codec::<PrefixedArray<StringProto>>(data);
+});
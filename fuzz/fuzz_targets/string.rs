@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::primitives::string::StringProto;
+use venomized_fuzz::check_codec;
+
+fuzz_target!(|data: &[u8]| {
+    check_codec::<StringProto>(data);
+});
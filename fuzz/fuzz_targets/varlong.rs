@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::primitives::varlong::VarLong;
+use venomized_fuzz::check_codec;
+
+fuzz_target!(|data: &[u8]| {
+    check_codec::<VarLong>(data);
+});
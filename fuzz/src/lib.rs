@@ -0,0 +1,33 @@
+//! Checks shared by the fuzz targets.
+//!
+//! Every target feeds raw bytes into a decoder. The decoder may reject them,
+//! but it must never panic, and whatever it accepts must be encoded back
+//! into exactly the bytes it consumed. The encodings are canonical, so this
+//! is the same as `decode(encode(x)) == x` for every value the fuzzer reaches.
+
+use protocol::codec::Codec;
+
+/// Decodes `T` from the start of `data` and checks the roundtrip
+pub fn check_codec<T: Codec>(data: &[u8]) {
+    let mut reader = data;
+    let Ok(value) = T::decode(&mut reader) else {
+        return;
+    };
+    let consumed = &data[..data.len() - reader.len()];
+
+    let mut encoded = Vec::new();
+    value
+        .encode(&mut encoded)
+        .expect("encoding into a Vec can't fail");
+    assert_eq!(encoded, consumed, "the encoding is not canonical");
+
+    let mut reader = &encoded[..];
+    let decoded = T::decode(&mut reader).expect("the encoded value must decode");
+    assert!(reader.is_empty(), "{} bytes are left", reader.len());
+
+    let mut reencoded = Vec::new();
+    decoded
+        .encode(&mut reencoded)
+        .expect("encoding into a Vec can't fail");
+    assert_eq!(reencoded, encoded);
+}
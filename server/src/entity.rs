@@ -8,10 +8,17 @@ pub type EntityId = u64;
 
 type EntityMap = HashMap<EntityId, Snake>;
 
+#[derive(Debug)]
 pub struct EntityManager {
     pub entities: EntityMap,
 }
 
+impl Default for EntityManager {
+    fn default() -> Self {
+        EntityManager::new()
+    }
+}
+
 impl EntityManager {
     pub fn new() -> EntityManager {
         EntityManager {
@@ -20,8 +27,15 @@ impl EntityManager {
     }
 
     // -- Wrappers --
-    pub fn add(&mut self) {
-        self.entities.insert(U64Id::new().inner(), Snake::new());
+    pub fn add(&mut self, snake: Snake) -> EntityId {
+        let id = U64Id::new().inner();
+        self.entities.insert(id, snake);
+        id
+    }
+
+    /// Puts an entity with an already known id, e.g. restored from a snapshot
+    pub fn insert(&mut self, id: EntityId, snake: Snake) {
+        self.entities.insert(id, snake);
     }
 
     pub fn remove(&mut self, id: EntityId) {
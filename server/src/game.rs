@@ -1,31 +1,705 @@
-use crate::{systems::{movement::{MovementEvent, MovementSystem}, physics::{PhysicsEvent, PhysicsSystem}, presence::{PresenceEvent, PresenceSystem}}, world::World};
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use common::{
+    net::{
+        datagram::Tick,
+        packets::{
+            Id, LEADERBOARD_SIZE, LeaderboardData, LeaderboardEntryData,
+            UpdateEntityPositionAndDirection, UpdateEntityPositionAndDirectionData,
+        },
+    },
+    world::chunk::Tile,
+};
+use protocol::primitives::{
+    byte::Byte, prefixed_array::PrefixedArray, string::StringProto, uvarint::UVarInt,
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    entity::EntityId,
+    net::{
+        ConnectionManager, NetEvent, NetEventReceiver, NetEventSender, NetMetrics,
+        auth::Auth,
+        connection::{Connection, ConnectionId, ConnectionState},
+        handler::{HandlerError, PacketHandler},
+        session::SessionManager,
+    },
+    snapshot::Snapshot,
+    systems::{
+        movement::{MovementEvent, MovementSystem},
+        physics::{PhysicsEvent, PhysicsSystem},
+        presence::{PresenceEvent, PresenceSystem},
+        score::ScoreSystem,
+    },
+    world::World,
+};
+
+/// How often the game state is written to disk
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Duration of one game tick, 10 ticks per second
+const TICK_DURATION: Duration = Duration::from_millis(100);
+
+/// The leaderboard is sent every this many ticks, once a second
+const LEADERBOARD_INTERVAL: Tick = 10;
+
+/// Sent to every client when the server stops
+const SHUTDOWN_REASON: &str = "Server is shutting down";
+
+/// Sent to a client whose outbound queue is full
+const SLOW_CLIENT_REASON: &str = "Can't keep up with the server";
+
+/// Sent to a client over its inbound rate limit
+const FLOOD_REASON: &str = "Too many packets";
 
 pub struct Game {
     world: World, // contains chunks
+
+    presence_system: PresenceSystem,
+    score_system: ScoreSystem,
+
+    connections: ConnectionManager,
+    sessions: SessionManager,
+
+    /// Events from the transports, `net_events_tx` is cloned for every new transport
+    net_events: NetEventReceiver,
+    net_events_tx: NetEventSender,
+
+    /// Where to write snapshots, `None` disables them
+    snapshot_path: Option<PathBuf>,
+
+    /// Number of the current tick, datagrams are stamped with it.
+    /// Not part of the snapshot, clients start over at `LoginSuccess`.
+    tick: Tick,
 }
 
 impl Game {
-    pub fn new(width: u32, height: u32) -> Game {
+    pub fn new(world: World, sessions: SessionManager) -> Game {
+        let mut presence_system = PresenceSystem::new();
+        presence_system.add_chunks(world.world.chunks_total());
+        for (entity_id, snake) in world.entity_manager.entities.iter() {
+            presence_system.register_new_entity(*entity_id, snake, &world.world);
+        }
+
+        let (net_events_tx, net_events) = mpsc::unbounded_channel();
+
         Game {
-            world: World::new(width, height),
+            world,
+            presence_system,
+            score_system: ScoreSystem::new(),
+            connections: ConnectionManager::new(),
+            sessions,
+            net_events,
+            net_events_tx,
+            snapshot_path: None,
+            tick: 0,
+        }
+    }
+
+    /// Enables periodic snapshots of the game state
+    pub fn with_snapshots(mut self, path: impl Into<PathBuf>) -> Game {
+        self.snapshot_path = Some(path.into());
+        self
+    }
+
+    /// Continues the scores restored from a snapshot, see `Snapshot::load`
+    pub fn with_scores(mut self, scores: ScoreSystem) -> Game {
+        self.score_system = scores;
+        self
+    }
+
+    /// Lets in only the players `auth` agrees to
+    pub fn with_auth(mut self, auth: Auth) -> Game {
+        self.connections.auth = Arc::new(auth);
+        self
+    }
+
+    /// The sender for transports to deliver their events to the game
+    pub fn net_events(&self) -> NetEventSender {
+        self.net_events_tx.clone()
+    }
+
+    /// Queues and kicks of the connections, for monitoring
+    pub fn metrics(&self) -> NetMetrics {
+        self.connections.metrics()
+    }
+
+    /// Writes the snapshot if they are enabled.
+    /// A failed snapshot must not stop the game, so the error is only logged.
+    pub fn save_snapshot(&self) {
+        if let Some(path) = &self.snapshot_path
+            && let Err(err) = Snapshot::save(
+                &self.world,
+                &self.sessions,
+                &self.score_system,
+                self.tick,
+                path,
+            )
+        {
+            eprintln!("Failed to save snapshot to {}: {err:?}", path.display());
+        }
+    }
+
+    /// Runs the tick loop until `shutdown` is set, then shuts the game down
+    pub fn run(&mut self, shutdown: &AtomicBool) {
+        let mut next_tick = Instant::now();
+        let mut last_snapshot = Instant::now();
+
+        while !shutdown.load(Ordering::Relaxed) {
+            self.tick();
+
+            if last_snapshot.elapsed() >= SNAPSHOT_INTERVAL {
+                self.save_snapshot();
+                last_snapshot = Instant::now();
+            }
+
+            next_tick += TICK_DURATION;
+            let now = Instant::now();
+            if next_tick > now {
+                std::thread::sleep(next_tick - now);
+            } else {
+                // The tick took too long, don't try to catch up
+                next_tick = now;
+            }
         }
+
+        self.shutdown();
+    }
+
+    /// Notifies the clients and flushes the state to disk.
+    /// Sessions are suspended, so players can resume after a restart.
+    pub fn shutdown(&mut self) {
+        self.connections.disconnect_all(SHUTDOWN_REASON);
+        self.sessions.suspend_all(Instant::now());
+        self.save_snapshot();
     }
 
     pub fn tick(&mut self) {
-        
-        // just init presence system
-        let mut presence_system = PresenceSystem::new();
+        self.tick = self.tick.wrapping_add(1);
+        self.handle_net_events();
+
+        for entity_id in self.sessions.expire(Instant::now()) {
+            self.remove_entity(entity_id);
+        }
 
         // events
         let mut movement_events: Vec<MovementEvent> = Vec::new();
         let mut presence_events: Vec<PresenceEvent> = Vec::new();
         let mut physics_events: Vec<PhysicsEvent> = Vec::new();
-        
-        loop {
-            MovementSystem::tick(&mut self.world.entity_manager, &mut movement_events);
-            presence_system.tick(&mut self.world.world, &movement_events[..], &mut presence_events);
-            PhysicsSystem::tick(&mut presence_system, &mut self.world.entity_manager, &mut self.world.world, &mut physics_events);
+
+        MovementSystem::tick(
+            &mut self.world.entity_manager,
+            &self.world.world,
+            &mut movement_events,
+        );
+        self.presence_system.tick(
+            &self.world.world,
+            &movement_events[..],
+            &mut presence_events,
+        );
+        PhysicsSystem::tick(
+            &mut self.presence_system,
+            &self.world.entity_manager,
+            &self.world.world,
+            &movement_events,
+            &mut physics_events,
+        );
+
+        // All at first, a killer may die in the same tick
+        for event in &physics_events {
+            let PhysicsEvent::EntityDied { entity_id, killer } = *event;
+            self.announce_death(entity_id, killer);
+        }
+        let mut dead = Vec::with_capacity(physics_events.len());
+        for event in &physics_events {
+            match *event {
+                PhysicsEvent::EntityDied { entity_id, .. } => {
+                    // PhysicsSystem already cleaned the presence map
+                    self.world.entity_manager.remove(entity_id);
+                    self.sessions.remove_entity(entity_id);
+                    dead.push(entity_id);
+                }
+            }
+        }
+        if !dead.is_empty() {
+            self.connections.despawn(&dead);
+        }
+        self.score_system
+            .tick(&self.world.entity_manager, &physics_events, self.tick);
+        self.eat_apples(&movement_events);
+
+        self.broadcast_positions(&movement_events);
+        if self.tick.is_multiple_of(LEADERBOARD_INTERVAL) {
+            self.broadcast_leaderboard();
+        }
+        self.kick_slow_clients();
+    }
+
+    /// A snake that survived the move with its head on an apple eats it,
+    /// the tile is empty from then on
+    fn eat_apples(&mut self, movement_events: &[MovementEvent]) {
+        for event in movement_events {
+            let MovementEvent::EntityMoved {
+                entity_id,
+                new_head,
+                ..
+            } = event
+            else {
+                continue;
+            };
+            if self.world.entity_manager.get(entity_id).is_none()
+                || self.world.world.get_tile(new_head) != Some(&Tile::Apple)
+            {
+                continue;
+            }
+            self.world.world.set_tile(new_head, Tile::Empty);
+            self.score_system.apple_eaten(*entity_id);
+        }
+    }
+
+    /// Tells the chat who died and who killed them, if it was a snake
+    fn announce_death(&self, entity_id: EntityId, killer: Option<EntityId>) {
+        let Some(username) = self.sessions.username(entity_id) else {
+            return;
+        };
+        let message = match killer.and_then(|killer| self.sessions.username(killer)) {
+            Some(killer) => format!("{username} was killed by {killer}"),
+            None => format!("{username} died"),
+        };
+        self.connections.broadcast_chat("", &message);
+    }
+
+    /// The best players, only those with a username are shown
+    fn broadcast_leaderboard(&self) {
+        let entries = self
+            .score_system
+            .top(&self.world.entity_manager, self.tick, LEADERBOARD_SIZE)
+            .into_iter()
+            .filter_map(|standing| {
+                let username = self.sessions.username(standing.entity_id)?;
+                Some(LeaderboardEntryData {
+                    id: Id::from(standing.entity_id as i64),
+                    username: StringProto(username.to_string()),
+                    length: UVarInt(standing.length),
+                    apples: UVarInt(standing.apples),
+                    kills: UVarInt(standing.kills),
+                    survived: UVarInt(standing.survived),
+                })
+            })
+            .collect::<Vec<_>>();
+        self.connections.broadcast_leaderboard(&LeaderboardData {
+            entries: PrefixedArray::from(entries),
+        });
+    }
+
+    /// A client that doesn't read fast enough would make the server
+    /// hold everything sent to it, it's better off reconnecting.
+    /// It did nothing wrong, so its session can still be resumed.
+    fn kick_slow_clients(&mut self) {
+        for connection_id in self.connections.overflowed() {
+            self.connections.kicked += 1;
+            self.close(
+                connection_id,
+                HandlerError::Kick(SLOW_CLIENT_REASON.to_string()),
+            );
+            self.sessions.suspend(connection_id, Instant::now());
         }
+    }
+
+    /// Sends the new position of every entity that moved (and survived)
+    /// to the other players in `Play`
+    fn broadcast_positions(&self, movement_events: &[MovementEvent]) {
+        for event in movement_events {
+            let MovementEvent::EntityMoved {
+                entity_id,
+                new_head,
+                ..
+            } = event
+            else {
+                continue;
+            };
+            let Some(snake) = self.world.entity_manager.get(entity_id) else {
+                continue;
+            };
+            let data = UpdateEntityPositionAndDirectionData {
+                id: Id::from(*entity_id as i64),
+                x: UVarInt(new_head.x),
+                y: UVarInt(new_head.y),
+                direction: Byte(snake.direction as u8),
+            };
+
+            for connection in self.connections.connections.values() {
+                if connection.state != ConnectionState::Play
+                    || connection.entity_id == Some(*entity_id)
+                {
+                    continue;
+                }
+                let result = connection.send_datagram::<UpdateEntityPositionAndDirection>(
+                    self.tick, *entity_id, &data,
+                );
+                if let Err(err) = result {
+                    eprintln!("Failed to send position to {}: {err}", connection.id);
+                }
+            }
+        }
+    }
+
+    fn handle_net_events(&mut self) {
+        while let Ok(event) = self.net_events.try_recv() {
+            match event {
+                NetEvent::Connected {
+                    connection_id,
+                    outbound,
+                } => {
+                    self.connections
+                        .add(Connection::new(connection_id, outbound));
+                }
+                NetEvent::Packet {
+                    connection_id,
+                    packet,
+                } => {
+                    // Checked before anything is decoded, a flood must be cheap.
+                    // Packets of unknown connections are ignored by the handler.
+                    let allowed = match self.connections.get_mut(connection_id) {
+                        Some(connection) => connection.inbound.allow(self.tick, packet.len()),
+                        None => true,
+                    };
+                    if !allowed {
+                        self.connections.kicked += 1;
+                        self.kick(connection_id, HandlerError::Kick(FLOOD_REASON.to_string()));
+                        continue;
+                    }
+
+                    let result = PacketHandler::handle(
+                        &mut self.world,
+                        &mut self.presence_system,
+                        &mut self.connections,
+                        &mut self.sessions,
+                        self.tick,
+                        connection_id,
+                        &packet,
+                    );
+                    if let Err(err) = result {
+                        self.kick(connection_id, err);
+                    }
+                }
+                NetEvent::Disconnected { connection_id } => {
+                    self.connections.remove(connection_id);
+                    self.sessions.suspend(connection_id, Instant::now());
+                }
+            }
+        }
+    }
+
+    /// Closes the connection of a misbehaving client. Its snake is despawned
+    /// and the session ends, so the client can't resume it with its token.
+    fn kick(&mut self, connection_id: ConnectionId, err: HandlerError) {
+        let Some(connection) = self.close(connection_id, err) else {
+            return;
+        };
+        if let Some(entity_id) = connection.entity_id {
+            self.remove_entity(entity_id);
+            self.sessions.remove_entity(entity_id);
+            self.connections.despawn(&[entity_id]);
+        }
+    }
+
+    /// Closes the connection, tells the client why and returns it
+    fn close(&mut self, connection_id: ConnectionId, err: HandlerError) -> Option<Connection> {
+        let reason = match err {
+            HandlerError::Kick(reason) => reason,
+            HandlerError::Protocol(err) => format!("Protocol error: {err}"),
+        };
+        eprintln!("Kicking {connection_id}: {reason}");
+
+        let connection = self.connections.remove(connection_id)?;
+        let _ = connection.disconnect(&reason);
+        Some(connection)
+    }
+
+    fn remove_entity(&mut self, entity_id: EntityId) {
+        if let Some(snake) = self.world.entity_manager.get(&entity_id) {
+            self.presence_system
+                .handle_entity_death(entity_id, snake, &self.world.world);
+        }
+        self.world.entity_manager.remove(entity_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{
+        connection::Outbound,
+        queue::{self, MAX_QUEUED_BYTES, OutboundReceiver},
+        anticheat::STRIKE_LIMIT,
+        rate_limit::BYTE_BURST,
+        transport::memory::{join_packets, login_data},
+    };
+    use assert_matches::assert_matches;
+    use common::{
+        entities::snake::Direction,
+        net::{
+            datagram,
+            packets::{
+                Login, Packet, RemoveEntities, SetDrawDistanceConfigure,
+                SetDrawDistanceConfigureData, TurnSnake, TurnSnakeData,
+            },
+            version::Features,
+        },
+        world::types::{ChunkSize, GridPos},
+    };
+    use protocol::context::DecodeContext;
+
+    /// Connects a client and brings it to `Play`, discarding what it received
+    fn join(game: &mut Game, connection_id: ConnectionId) -> OutboundReceiver {
+        let (outbound, mut rx) = queue::channel();
+        let events = game.net_events();
+        let packets = join_packets(&format!("player{connection_id}"), Features::none()).unwrap();
+
+        events
+            .send(NetEvent::Connected {
+                connection_id,
+                outbound,
+            })
+            .unwrap();
+        for packet in packets {
+            events
+                .send(NetEvent::Packet {
+                    connection_id,
+                    packet,
+                })
+                .unwrap();
+        }
+        game.handle_net_events();
+        while rx.try_recv().is_ok() {}
+        rx
+    }
+
+    #[test]
+    fn positions_sent_as_datagrams() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        // Far from the walls, whatever the direction
+        world.world.spawn_points = vec![GridPos { x: 8, y: 8 }, GridPos { x: 24, y: 24 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let mut first = join(&mut game, 1);
+        let mut second = join(&mut game, 2);
+        let second_entity = game.connections.get(&2).unwrap().entity_id.unwrap();
+        // `SpawnEntity` of the second snake
+        assert_matches!(first.try_recv(), Ok(Outbound::Reliable(_)));
+
+        game.tick();
+
+        // Only the position of the other snake, stamped with the tick
+        let Ok(Outbound::Datagram(bytes)) = first.try_recv() else {
+            panic!("Expected a datagram");
+        };
+        let (tick, packet) = datagram::decode_datagram(&bytes).unwrap();
+        assert_eq!(tick, game.tick);
+        let data =
+            UpdateEntityPositionAndDirection::decode(packet, &DecodeContext::DEFAULT).unwrap();
+        assert_eq!(data.id.0, second_entity as i64);
+        assert!(first.try_recv().is_err());
+
+        assert_matches!(second.try_recv(), Ok(Outbound::Datagram(_)));
+        assert!(second.try_recv().is_err());
+    }
+
+    #[test]
+    fn positions_coalesced_for_slow_reader() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        world.world.spawn_points = vec![GridPos { x: 8, y: 8 }, GridPos { x: 24, y: 24 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let mut first = join(&mut game, 1);
+        let _second = join(&mut game, 2);
+        assert_matches!(first.try_recv(), Ok(Outbound::Reliable(_)));
+
+        for _ in 0..3 {
+            game.tick();
+        }
+
+        // Only the newest position is left
+        let Ok(Outbound::Datagram(bytes)) = first.try_recv() else {
+            panic!("Expected a datagram");
+        };
+        let (tick, _) = datagram::decode_datagram(&bytes).unwrap();
+        assert_eq!(tick, game.tick);
+        assert!(first.try_recv().is_err());
+        // Two in the queue of each client
+        assert_eq!(game.metrics().coalesced, 4);
+    }
+
+    #[test]
+    fn kill_credited() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        // Both in the first chunk
+        world.world.spawn_points = vec![GridPos { x: 4, y: 4 }, GridPos { x: 10, y: 10 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let _first = join(&mut game, 1);
+        let mut second = join(&mut game, 2);
+        let killer = game.connections.get(&1).unwrap().entity_id.unwrap();
+        let victim = game.connections.get(&2).unwrap().entity_id.unwrap();
+
+        // The victim runs into the neck of the killer
+        let entities = &mut game.world.entity_manager;
+        let snake = entities.get_mut(killer).unwrap();
+        snake.direction = Direction::East;
+        snake.body = [(5, 5), (4, 5), (3, 5)]
+            .map(|(x, y)| GridPos { x, y })
+            .into();
+        let snake = entities.get_mut(victim).unwrap();
+        snake.direction = Direction::North;
+        snake.body = [(4, 6), (4, 7), (4, 8)]
+            .map(|(x, y)| GridPos { x, y })
+            .into();
+        game.tick();
+
+        assert!(game.world.entity_manager.get(&victim).is_none());
+        assert_eq!(
+            game.score_system.get(killer).map(|score| score.kills),
+            Some(1)
+        );
+        assert_eq!(game.score_system.get(victim), None);
+
+        // The victim is told and has nothing to steer anymore
+        assert_eq!(game.connections.get(&2).unwrap().entity_id, None);
+        let Ok(Outbound::Reliable(packet)) = second.try_recv() else {
+            panic!("Expected RemoveEntities");
+        };
+        let data = RemoveEntities::decode(&packet, &DecodeContext::DEFAULT).unwrap();
+        let removed: Vec<i64> = data.entities.data.iter().map(|id| id.0).collect();
+        assert_eq!(removed, [victim as i64]);
+    }
+
+    #[test]
+    fn apple_eaten() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        world.world.spawn_points = vec![GridPos { x: 8, y: 8 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let _rx = join(&mut game, 1);
+        let entity_id = game.connections.get(&1).unwrap().entity_id.unwrap();
+
+        let snake = game.world.entity_manager.get_mut(entity_id).unwrap();
+        snake.direction = Direction::East;
+        snake.body = [(8, 8), (7, 8), (6, 8)]
+            .map(|(x, y)| GridPos { x, y })
+            .into();
+        let apple = GridPos { x: 9, y: 8 };
+        game.world.world.set_tile(&apple, Tile::Apple);
+        game.tick();
+
+        assert_eq!(
+            game.score_system.get(entity_id).map(|score| score.apples),
+            Some(1)
+        );
+        assert_eq!(game.world.world.get_tile(&apple), Some(&Tile::Empty));
+    }
+
+    #[test]
+    fn slow_client_kicked() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        world.world.spawn_points = vec![GridPos { x: 8, y: 8 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let _rx = join(&mut game, 1);
+
+        // The client never reads
+        let connection = game.connections.get(&1).unwrap();
+        let reason = "x".repeat(1000);
+        while !connection.overflowed() {
+            connection.disconnect(&reason).unwrap();
+        }
+        assert!(game.metrics().deepest_queue > MAX_QUEUED_BYTES);
+        game.tick();
+
+        assert!(game.connections.get(&1).is_none());
+        assert_eq!(game.metrics().kicked, 1);
+        // Not its fault, the player may resume
+        assert!(game.sessions.username_taken("player1"));
+        assert_eq!(game.world.entity_manager.entities.len(), 1);
+    }
+
+    #[test]
+    fn cheater_kicked_without_session() {
+        let mut world = World::new(32, 32, ChunkSize::new(16, 16));
+        world.world.spawn_points = vec![GridPos { x: 8, y: 8 }];
+        let mut game = Game::new(world, SessionManager::new());
+        let _rx = join(&mut game, 1);
+        let entity_id = game.connections.get(&1).unwrap().entity_id.unwrap();
+        let (token, _, _) = game.sessions.iter().next().unwrap();
+
+        // Claims a head far from the real one
+        let turn = TurnSnake::encode(&TurnSnakeData {
+            direction: Byte(Direction::North as u8),
+            x: UVarInt(30),
+            y: UVarInt(30),
+        })
+        .unwrap();
+        let events = game.net_events();
+        for _ in 0..STRIKE_LIMIT {
+            events
+                .send(NetEvent::Packet {
+                    connection_id: 1,
+                    packet: turn.clone(),
+                })
+                .unwrap();
+        }
+        game.handle_net_events();
+
+        assert!(game.connections.get(&1).is_none());
+        assert!(game.world.entity_manager.get(&entity_id).is_none());
+        assert!(!game.sessions.username_taken("player1"));
+
+        // The token is worth nothing, the player starts over
+        let (outbound, _rx) = queue::channel();
+        events
+            .send(NetEvent::Connected {
+                connection_id: 2,
+                outbound,
+            })
+            .unwrap();
+        let packets = [
+            Login::encode(&login_data("player1", Features::none(), token as i64)).unwrap(),
+            SetDrawDistanceConfigure::encode(&SetDrawDistanceConfigureData {}).unwrap(),
+        ];
+        for packet in packets {
+            events
+                .send(NetEvent::Packet {
+                    connection_id: 2,
+                    packet,
+                })
+                .unwrap();
+        }
+        game.handle_net_events();
+
+        let resumed = game.connections.get(&2).unwrap().entity_id.unwrap();
+        assert_ne!(resumed, entity_id);
+        assert_eq!(game.world.entity_manager.entities.len(), 1);
+    }
+
+    #[test]
+    fn flooding_client_kicked() {
+        let mut game = Game::new(
+            World::new(32, 32, ChunkSize::new(16, 16)),
+            SessionManager::new(),
+        );
+        let _rx = join(&mut game, 1);
+        // Not even decoded, it would be a protocol error otherwise
+        game.net_events()
+            .send(NetEvent::Packet {
+                connection_id: 1,
+                packet: vec![0xFF; BYTE_BURST as usize + 1],
+            })
+            .unwrap();
+        game.handle_net_events();
 
+        assert!(game.connections.get(&1).is_none());
+        assert_eq!(game.metrics().kicked, 1);
     }
 }
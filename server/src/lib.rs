@@ -0,0 +1,9 @@
+//! The game server. `main.rs` wires it to the network and the disk,
+//! integration tests in `tests/` drive it in-process.
+
+pub mod entity;
+pub mod game;
+pub mod net;
+pub mod snapshot;
+pub mod systems;
+pub mod world;
@@ -1,19 +1,146 @@
-use crate::{game::Game};
+use std::{
+    io::ErrorKind,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-mod entity;
-mod game;
-mod world;
+use common::world::{
+    generator::{Generator, GeneratorConfig},
+    map::Map,
+    types::ChunkSize,
+};
 
-mod systems;
+use venomized_server::{
+    game::Game,
+    net::{
+        auth::Auth,
+        session::SessionManager,
+        transport::{ConnectionIds, quic::QuicTransport},
+    },
+    snapshot::{Snapshot, SnapshotError},
+    systems::score::ScoreSystem,
+    world::World,
+};
 
-mod net;
+const SNAPSHOT_PATH: &str = "world.snapshot";
 
-fn main() {
-    // init game
-    let mut game = Game::new(256, 256);
+const LISTEN_ADDR: &str = "0.0.0.0:7777";
 
-    // start server
+/// How long the disconnect reasons may take to reach the clients
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
 
-    // start tick
-    game.tick();
+/// The self-signed certificate of this run, for the clients to trust
+const CERT_PATH: &str = "server.der";
+
+/// The same `auth_token` for every player
+const SECRET_VAR: &str = "VENOMIZED_SECRET";
+
+/// Path to the list of personal tokens, see `net::auth`
+const TOKENS_VAR: &str = "VENOMIZED_TOKENS";
+
+#[tokio::main]
+async fn main() {
+    // init game, restoring the previous state if there is one
+    let (world, sessions, scores) = match Snapshot::load(SNAPSHOT_PATH) {
+        Ok(restored) => {
+            println!("Restored world from {SNAPSHOT_PATH}");
+            restored
+        }
+        Err(SnapshotError::Io(err)) if err.kind() == ErrorKind::NotFound => {
+            (new_world(), SessionManager::new(), ScoreSystem::new())
+        }
+        Err(err) => {
+            eprintln!("Failed to restore {SNAPSHOT_PATH}, starting a new world: {err:?}");
+            (new_world(), SessionManager::new(), ScoreSystem::new())
+        }
+    };
+    let mut game = Game::new(world, sessions)
+        .with_scores(scores)
+        .with_snapshots(SNAPSHOT_PATH)
+        .with_auth(auth());
+
+    // start server, transports deliver their events through this sender
+    let addr = LISTEN_ADDR.parse().expect("LISTEN_ADDR is valid");
+    let (transport, cert) = QuicTransport::bind(addr, game.net_events(), ConnectionIds::new())
+        .unwrap_or_else(|err| panic!("Failed to listen on {LISTEN_ADDR}: {err}"));
+    std::fs::write(CERT_PATH, &cert).expect("Failed to write the certificate");
+    println!("Listening on {LISTEN_ADDR}, certificate in {CERT_PATH}");
+    tokio::spawn(transport.clone().serve());
+
+    // start tick, the loop is blocking so it gets its own thread
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let game_shutdown = shutdown.clone();
+    let mut game_loop = tokio::task::spawn_blocking(move || game.run(&game_shutdown));
+
+    tokio::select! {
+        _ = shutdown_signal() => {
+            println!("Shutting down...");
+            shutdown.store(true, Ordering::Relaxed);
+            game_loop.await.expect("Game loop panicked");
+        }
+        result = &mut game_loop => result.expect("Game loop panicked"),
+    }
+    transport.shutdown(SHUTDOWN_TIMEOUT).await;
+}
+
+/// Resolves on SIGINT (Ctrl+C) or SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Open unless one of the variables is set
+fn auth() -> Auth {
+    if let Ok(path) = std::env::var(TOKENS_VAR) {
+        let auth = Auth::load_tokens(&path)
+            .unwrap_or_else(|err| panic!("Failed to load tokens from {path}: {err}"));
+        println!("Only players listed in {path} may log in");
+        return auth;
+    }
+    match std::env::var(SECRET_VAR) {
+        Ok(secret) if !secret.is_empty() => {
+            println!("Players need the secret of {SECRET_VAR} to log in");
+            Auth::SharedSecret(secret)
+        }
+        _ => Auth::Open,
+    }
+}
+
+/// The first argument is an optional path to the map,
+/// without it the arena is generated
+fn new_world() -> World {
+    let world = match std::env::args().nth(1) {
+        Some(path) => Map::load(&path)
+            .and_then(|map| map.into_world(ChunkSize::default()))
+            .unwrap_or_else(|err| panic!("Failed to load map {path}: {err:?}")),
+        None => {
+            // every match gets a fresh arena
+            let seed = rand::random();
+            println!("Generating world with seed {seed}");
+            let config = GeneratorConfig {
+                seed,
+                ..GeneratorConfig::default()
+            };
+            Generator::generate_map(256, 256, config)
+                .and_then(|map| map.into_world(ChunkSize::default()))
+                .expect("256x256 fits into default chunks")
+        }
+    };
+    World::from_world(world)
 }
@@ -0,0 +1,170 @@
+//! Validation of the input of players in `Play`.
+//!
+//! The server never trusts what a client says: every `TurnSnake` is
+//! checked against the state of the game before it's applied. A packet
+//! that fails a check is dropped and the connection gets a strike with
+//! the reason in the log. A single strike proves nothing (a turn sent
+//! right before the snake died or a burst of packets after a lag spike
+//! look the same), so only `STRIKE_LIMIT` strikes within `STRIKE_WINDOW`
+//! get the connection kicked.
+
+use std::{collections::VecDeque, fmt};
+
+use common::{entities::snake::Snake, net::datagram::Tick, world::types::GridPos};
+
+/// Turns accepted from one connection per tick, a few more than one
+/// so the packets bunched up by the network still get through
+pub const MAX_TURNS_PER_TICK: u32 = 3;
+
+/// How far in tiles (Manhattan distance) the claimed head may be from
+/// the real one. The client sees its head late by the round trip,
+/// the snake moves a tile per tick, so this is about 800 ms of lag.
+pub const MAX_POSITION_DRIFT: u32 = 8;
+
+/// Strikes that get the connection kicked...
+pub const STRIKE_LIMIT: usize = 10;
+
+/// ...within this many ticks
+pub const STRIKE_WINDOW: Tick = 50;
+
+/// Why a packet was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// More than `MAX_TURNS_PER_TICK` in one tick
+    TooManyTurns,
+
+    /// The snake of the connection is dead
+    DeadEntity,
+
+    /// The claimed head is too far from the real one
+    ImpossiblePosition { claimed: GridPos, actual: GridPos },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::TooManyTurns => {
+                write!(f, "more than {MAX_TURNS_PER_TICK} turns in a tick")
+            }
+            Violation::DeadEntity => write!(f, "turn of a dead snake"),
+            Violation::ImpossiblePosition { claimed, actual } => write!(
+                f,
+                "head claimed at {},{} but it is at {},{}",
+                claimed.x, claimed.y, actual.x, actual.y
+            ),
+        }
+    }
+}
+
+/// The checks that need a memory of the connection
+#[derive(Debug, Default)]
+pub struct InputGuard {
+    /// The tick `turns` are counted in
+    tick: Tick,
+    turns: u32,
+
+    /// Ticks of the recent strikes, oldest first
+    strikes: VecDeque<Tick>,
+}
+
+impl InputGuard {
+    pub fn new() -> InputGuard {
+        InputGuard::default()
+    }
+
+    /// Counts a turn in `tick`, rejecting the ones over the limit
+    pub fn count_turn(&mut self, tick: Tick) -> Result<(), Violation> {
+        if self.tick != tick {
+            self.tick = tick;
+            self.turns = 0;
+        }
+        self.turns += 1;
+        match self.turns > MAX_TURNS_PER_TICK {
+            true => Err(Violation::TooManyTurns),
+            false => Ok(()),
+        }
+    }
+
+    /// Records a strike in `tick`, `true` if there were too many of them lately
+    pub fn strike(&mut self, tick: Tick) -> bool {
+        while let Some(&oldest) = self.strikes.front() {
+            if tick.wrapping_sub(oldest) < STRIKE_WINDOW {
+                break;
+            }
+            self.strikes.pop_front();
+        }
+        self.strikes.push_back(tick);
+        self.strikes.len() >= STRIKE_LIMIT
+    }
+}
+
+/// The claimed head must be close to the real one
+pub fn check_position(snake: &Snake, claimed: &GridPos) -> Result<(), Violation> {
+    let Some(actual) = snake.body.front() else {
+        return Err(Violation::DeadEntity);
+    };
+    let drift = actual
+        .x
+        .abs_diff(claimed.x)
+        .saturating_add(actual.y.abs_diff(claimed.y));
+    match drift > MAX_POSITION_DRIFT {
+        true => Err(Violation::ImpossiblePosition {
+            claimed: claimed.clone(),
+            actual: actual.clone(),
+        }),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn guard_turns_per_tick() {
+        let mut guard = InputGuard::new();
+        for _ in 0..MAX_TURNS_PER_TICK {
+            assert_eq!(guard.count_turn(1), Ok(()));
+        }
+        assert_eq!(guard.count_turn(1), Err(Violation::TooManyTurns));
+        assert_eq!(guard.count_turn(2), Ok(()));
+    }
+
+    #[test]
+    fn guard_strikes_expire() {
+        let mut guard = InputGuard::new();
+        for _ in 0..STRIKE_LIMIT - 1 {
+            assert!(!guard.strike(1));
+        }
+        // The old ones are forgiven
+        assert!(!guard.strike(1 + STRIKE_WINDOW));
+
+        for _ in 0..STRIKE_LIMIT - 2 {
+            assert!(!guard.strike(2 + STRIKE_WINDOW));
+        }
+        assert!(guard.strike(2 + STRIKE_WINDOW));
+    }
+
+    #[test]
+    fn position_drift() {
+        let mut snake = Snake::new();
+        snake.body.push_back(GridPos { x: 10, y: 10 });
+
+        assert_eq!(check_position(&snake, &GridPos { x: 14, y: 6 }), Ok(()));
+        assert_matches!(
+            check_position(&snake, &GridPos { x: 15, y: 6 }),
+            Err(Violation::ImpossiblePosition { .. })
+        );
+        assert_matches!(
+            check_position(
+                &snake,
+                &GridPos {
+                    x: u32::MAX,
+                    y: u32::MAX
+                }
+            ),
+            Err(Violation::ImpossiblePosition { .. })
+        );
+    }
+}
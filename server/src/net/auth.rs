@@ -0,0 +1,152 @@
+//! Who may log in.
+//!
+//! The server is open by default: any valid username not taken by another
+//! player gets in. Two stricter modes are configured at startup:
+//!
+//! - a shared secret, the same `auth_token` for everybody, for private games;
+//! - personal tokens, each username has its own and only the listed
+//!   usernames may play. The list is a text file, one `username token`
+//!   per line, `#` starts a comment.
+//!
+//! Tokens travel in `Login` as they are, the transport must encrypt
+//! the connection (QUIC does).
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+
+use common::net::username::{UsernameError, validate_username};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Auth {
+    #[default]
+    Open,
+    SharedSecret(String),
+    /// Token by lowercase username
+    Tokens(HashMap<String, String>),
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+    Io(io::Error),
+    /// Not `username token`, the number starts at 1
+    MalformedLine(usize),
+    InvalidUsername {
+        line: usize,
+        err: UsernameError,
+    },
+    DuplicateUsername(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Io(err) => write!(f, "{err}"),
+            AuthError::MalformedLine(line) => {
+                write!(f, "line {line}: expected `username token`")
+            }
+            AuthError::InvalidUsername { line, err } => {
+                write!(f, "line {line}: invalid username, {err}")
+            }
+            AuthError::DuplicateUsername(username) => {
+                write!(f, "{username} is listed more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<io::Error> for AuthError {
+    fn from(value: io::Error) -> Self {
+        AuthError::Io(value)
+    }
+}
+
+impl Auth {
+    /// Parses the list of personal tokens
+    pub fn from_tokens(text: &str) -> Result<Auth, AuthError> {
+        let mut tokens = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let (Some(username), Some(token), None) = (words.next(), words.next(), words.next())
+            else {
+                return Err(AuthError::MalformedLine(i + 1));
+            };
+            validate_username(username)
+                .map_err(|err| AuthError::InvalidUsername { line: i + 1, err })?;
+            if tokens
+                .insert(username.to_ascii_lowercase(), token.to_string())
+                .is_some()
+            {
+                return Err(AuthError::DuplicateUsername(username.to_string()));
+            }
+        }
+        Ok(Auth::Tokens(tokens))
+    }
+
+    pub fn load_tokens(path: impl AsRef<Path>) -> Result<Auth, AuthError> {
+        Auth::from_tokens(&fs::read_to_string(path)?)
+    }
+
+    /// Whether `auth_token` lets `username` in
+    pub fn check(&self, username: &str, auth_token: &str) -> bool {
+        match self {
+            Auth::Open => true,
+            Auth::SharedSecret(secret) => constant_time_eq(secret, auth_token),
+            Auth::Tokens(tokens) => tokens
+                .get(&username.to_ascii_lowercase())
+                .is_some_and(|token| constant_time_eq(token, auth_token)),
+        }
+    }
+}
+
+/// Doesn't tell by its timing how much of the token was right
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn auth_modes() {
+        assert!(Auth::Open.check("viper", ""));
+
+        let secret = Auth::SharedSecret("hiss".to_string());
+        assert!(secret.check("viper", "hiss"));
+        assert!(!secret.check("viper", "his"));
+        assert!(!secret.check("viper", ""));
+
+        let tokens = Auth::from_tokens("# players\nViper a1\n\ncobra b2 # the boss\n").unwrap();
+        assert!(tokens.check("viper", "a1"));
+        assert!(tokens.check("COBRA", "b2"));
+        assert!(!tokens.check("cobra", "a1"));
+        assert!(!tokens.check("mamba", "a1"));
+    }
+
+    #[test]
+    fn auth_tokens_malformed() {
+        assert_matches!(
+            Auth::from_tokens("viper a1\ncobra"),
+            Err(AuthError::MalformedLine(2))
+        );
+        assert_matches!(
+            Auth::from_tokens("viper a1 b2"),
+            Err(AuthError::MalformedLine(1))
+        );
+        assert_matches!(
+            Auth::from_tokens("v a1"),
+            Err(AuthError::InvalidUsername { line: 1, .. })
+        );
+        assert_matches!(
+            Auth::from_tokens("viper a1\nVIPER b2"),
+            Err(AuthError::DuplicateUsername(_))
+        );
+    }
+}
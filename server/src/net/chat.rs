@@ -0,0 +1,146 @@
+//! The chat of the players and the announcements of the server.
+//!
+//! A message goes through `sanitize` before anybody sees it: control
+//! characters are stripped, long messages are refused and the words of
+//! `BLOCKED_WORDS` are masked. `ChatGuard` drops the messages of a player
+//! who writes faster than `MESSAGES_PER_WINDOW` per `FLOOD_WINDOW`.
+//! The author of a dropped message is told why, nobody else sees it.
+
+use std::{collections::VecDeque, fmt};
+
+use common::net::{datagram::Tick, packets::MAX_CHAT_MESSAGE_LEN};
+
+/// Messages a player may send...
+pub const MESSAGES_PER_WINDOW: usize = 5;
+
+/// ...within this many ticks
+pub const FLOOD_WINDOW: Tick = 50;
+
+/// Masked with `*` wherever they stand as whole words, ignoring the case
+const BLOCKED_WORDS: &[&str] = &[
+    "fuck", "fucking", "shit", "bitch", "cunt", "asshole", "bastard", "dick",
+];
+
+/// Why a message was dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatError {
+    /// Nothing left after stripping, dropped without a word
+    Empty,
+    TooLong,
+    Flooding,
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatError::Empty => write!(f, "the message is empty"),
+            ChatError::TooLong => {
+                write!(f, "longer than {MAX_CHAT_MESSAGE_LEN} characters")
+            }
+            ChatError::Flooding => write!(f, "you are sending messages too fast"),
+        }
+    }
+}
+
+/// The flood protection of a connection
+#[derive(Debug, Default)]
+pub struct ChatGuard {
+    /// Ticks of the recent messages, oldest first
+    sent: VecDeque<Tick>,
+}
+
+impl ChatGuard {
+    pub fn new() -> ChatGuard {
+        ChatGuard::default()
+    }
+
+    /// Counts a message in `tick`, unless there were too many lately
+    pub fn allow(&mut self, tick: Tick) -> Result<(), ChatError> {
+        while let Some(&oldest) = self.sent.front() {
+            if tick.wrapping_sub(oldest) < FLOOD_WINDOW {
+                break;
+            }
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= MESSAGES_PER_WINDOW {
+            return Err(ChatError::Flooding);
+        }
+        self.sent.push_back(tick);
+        Ok(())
+    }
+}
+
+/// The message as the other players will see it
+pub fn sanitize(message: &str) -> Result<String, ChatError> {
+    let message: String = message.chars().filter(|c| !c.is_control()).collect();
+    let message = message.trim();
+    if message.is_empty() {
+        return Err(ChatError::Empty);
+    }
+    if message.chars().count() > MAX_CHAT_MESSAGE_LEN {
+        return Err(ChatError::TooLong);
+    }
+    Ok(mask_blocked_words(message))
+}
+
+fn mask_blocked_words(message: &str) -> String {
+    let mut masked = String::with_capacity(message.len());
+    let mut rest = message;
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+        let (word, tail) = rest.split_at(end);
+        match BLOCKED_WORDS
+            .iter()
+            .any(|blocked| word.eq_ignore_ascii_case(blocked))
+        {
+            true => masked.extend(word.chars().map(|_| '*')),
+            false => masked.push_str(word),
+        }
+
+        // The separator goes as it is
+        let mut tail = tail.chars();
+        masked.extend(tail.next());
+        rest = tail.as_str();
+    }
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_sanitize() {
+        assert_eq!(sanitize("  hi\x07 all\n"), Ok("hi all".to_string()));
+        assert_eq!(sanitize(" \r\n "), Err(ChatError::Empty));
+        assert_eq!(
+            sanitize(&"ы".repeat(MAX_CHAT_MESSAGE_LEN)).map(|m| m.chars().count()),
+            Ok(MAX_CHAT_MESSAGE_LEN)
+        );
+        assert_eq!(
+            sanitize(&"a".repeat(MAX_CHAT_MESSAGE_LEN + 1)),
+            Err(ChatError::TooLong)
+        );
+    }
+
+    #[test]
+    fn chat_masks_whole_words() {
+        assert_eq!(
+            sanitize("Shit, a wall! shitake?").unwrap(),
+            "****, a wall! shitake?"
+        );
+    }
+
+    #[test]
+    fn chat_guard_flood() {
+        let mut guard = ChatGuard::new();
+        for _ in 0..MESSAGES_PER_WINDOW {
+            assert_eq!(guard.allow(1), Ok(()));
+        }
+        assert_eq!(guard.allow(2), Err(ChatError::Flooding));
+        // The old ones expire
+        assert_eq!(guard.allow(1 + FLOOD_WINDOW), Ok(()));
+    }
+}
@@ -1 +1,151 @@
+//! Provides the server-side state of a single client connection.
 
+use common::net::{
+    datagram::{self, Channel, Tick},
+    packets::{DisconnectConfigure, DisconnectData, DisconnectLogin, DisconnectPlay, Packet},
+    version::Features,
+};
+use protocol::{compression::Compression, error::ProtocolError, primitives::string::StringProto};
+
+use crate::{
+    entity::EntityId,
+    net::{
+        anticheat::InputGuard,
+        chat::ChatGuard,
+        queue::{OutboundSender, QueueStats},
+        rate_limit::RateLimit,
+    },
+};
+
+/// For identifying connections, unlike `EntityId` it changes on every reconnect
+pub type ConnectionId = u64;
+
+/// Stage of the dialogue with the client,
+/// the same packet id means different packets in different stages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Login,
+    Configure,
+    Play,
+}
+
+/// What the game hands over to the transport
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outbound {
+    /// A frame body for the reliable stream
+    Reliable(Vec<u8>),
+
+    /// A whole datagram, see `common::net::datagram`
+    Datagram(Vec<u8>),
+
+    /// The frame body of `SetCompression`, sent uncompressed.
+    /// From then on the transport compresses the frames it writes
+    /// and decompresses the ones it reads.
+    Compress {
+        body: Vec<u8>,
+        compression: Compression,
+    },
+}
+
+pub struct Connection {
+    pub id: ConnectionId,
+    pub state: ConnectionState,
+
+    /// The snake controlled by this connection, known after login.
+    /// `None` again once it died.
+    pub entity_id: Option<EntityId>,
+
+    /// Known after login, stays after the snake dies
+    pub username: Option<String>,
+
+    /// Features negotiated at login, packets of other features
+    /// must not be sent to this client
+    pub features: Features,
+
+    /// Rate limits and strikes of the input in `Play`
+    pub guard: InputGuard,
+
+    /// Packets and bytes the client may still send
+    pub inbound: RateLimit,
+
+    /// Flood protection of the chat
+    pub chat: ChatGuard,
+
+    /// Frame bodies and datagrams waiting for the transport
+    outbound: OutboundSender,
+}
+
+impl Connection {
+    pub fn new(id: ConnectionId, outbound: OutboundSender) -> Connection {
+        Connection {
+            id,
+            state: ConnectionState::Login,
+            entity_id: None,
+            username: None,
+            features: Features::none(),
+            guard: InputGuard::new(),
+            inbound: RateLimit::new(),
+            chat: ChatGuard::new(),
+            outbound,
+        }
+    }
+
+    /// Queues the packet for the transport.
+    /// If the transport is already gone, the packet is silently dropped,
+    /// the `Disconnected` event will follow anyway.
+    pub fn send<P: Packet>(&self, data: &P::Data) -> Result<(), ProtocolError> {
+        debug_assert_eq!(P::CHANNEL, Channel::Reliable, "{} is a datagram", P::NAME);
+        self.outbound.send_reliable(P::encode(data)?);
+        Ok(())
+    }
+
+    /// Queues the packet announcing compression, the transport
+    /// compresses the frames after it in both directions
+    pub fn send_enabling_compression<P: Packet>(
+        &self,
+        data: &P::Data,
+        compression: Compression,
+    ) -> Result<(), ProtocolError> {
+        self.outbound.send_compress(P::encode(data)?, compression);
+        Ok(())
+    }
+
+    /// Queues the packet as a datagram describing `entity_id` at `tick`.
+    /// A datagram of the same packet about the same entity still waiting
+    /// in the queue is replaced by this one.
+    pub fn send_datagram<P: Packet>(
+        &self,
+        tick: Tick,
+        entity_id: EntityId,
+        data: &P::Data,
+    ) -> Result<(), ProtocolError> {
+        debug_assert_eq!(P::CHANNEL, Channel::Unreliable, "{} is reliable", P::NAME);
+        let datagram = datagram::encode_datagram(tick, &P::encode(data)?)?;
+        self.outbound
+            .send_datagram(Some((P::ID, entity_id)), datagram);
+        Ok(())
+    }
+
+    /// The client doesn't read its stream fast enough
+    pub fn overflowed(&self) -> bool {
+        self.outbound.overflowed()
+    }
+
+    pub fn queue_stats(&self) -> QueueStats {
+        self.outbound.stats()
+    }
+
+    /// Sends the disconnect packet matching the current state.
+    /// The transport closes the connection once the `Connection`
+    /// is dropped and its queue is drained.
+    pub fn disconnect(&self, reason: &str) -> Result<(), ProtocolError> {
+        let data = DisconnectData {
+            reason: StringProto(reason.to_string()),
+        };
+        match self.state {
+            ConnectionState::Login => self.send::<DisconnectLogin>(&data),
+            ConnectionState::Configure => self.send::<DisconnectConfigure>(&data),
+            ConnectionState::Play => self.send::<DisconnectPlay>(&data),
+        }
+    }
+}
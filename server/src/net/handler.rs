@@ -0,0 +1,704 @@
+//! Provides handling of the packets coming from clients.
+//! The meaning of a packet id depends on the state of the connection.
+//! `Login` goes through `auth`, input in `Play` goes through `anticheat`
+//! before it touches the game.
+
+use common::{
+    entities::snake::{Direction, Snake},
+    net::{
+        datagram::Tick,
+        packets::{
+            ChatBroadcast, ChatBroadcastData, ChatMessage, ChatMessageData, ConfigureAcknowledged,
+            ConfigureAcknowledgedData, Id, Login, LoginData, LoginSuccess, LoginSuccessData,
+            Packet, ReconnectToken, SetCompression, SetCompressionData, SetDrawDistanceConfigure,
+            SpawnEntity, SpawnEntityData, TurnSnake, TurnSnakeData, WorldInfo, WorldInfoData,
+        },
+        username::validate_username,
+        version::{self, Feature, Features, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+    },
+    world::types::GridPos,
+};
+use protocol::{
+    codec::Codec,
+    error::{ProtocolError, ProtocolViolation},
+    primitives::{byte::Byte, string::StringProto, uvarint::UVarInt, varint::VarInt},
+};
+
+use crate::{
+    entity::EntityId,
+    net::{
+        ConnectionManager,
+        anticheat::{self, Violation},
+        chat::{self, ChatError},
+        connection::{Connection, ConnectionId, ConnectionState},
+        session::SessionManager,
+    },
+    systems::{entity_spawn::EntitySpawnSystem, presence::PresenceSystem},
+    world::World,
+};
+
+#[derive(Debug)]
+pub enum HandlerError {
+    /// The packet is malformed or unexpected
+    Protocol(ProtocolError),
+
+    /// The packet is fine, but the connection must be closed with the reason
+    Kick(String),
+}
+
+impl From<ProtocolError> for HandlerError {
+    fn from(value: ProtocolError) -> Self {
+        HandlerError::Protocol(value)
+    }
+}
+
+pub struct PacketHandler;
+
+impl PacketHandler {
+    pub fn handle(
+        world: &mut World,
+        presence_system: &mut PresenceSystem,
+        connections: &mut ConnectionManager,
+        sessions: &mut SessionManager,
+        tick: Tick,
+        connection_id: ConnectionId,
+        packet: &[u8],
+    ) -> Result<(), HandlerError> {
+        let ctx = connections.decode_context;
+        let Some(connection) = connections.get_mut(connection_id) else {
+            // Already disconnected, the rest of its packets doesn't matter
+            return Ok(());
+        };
+
+        let mut reader = packet;
+        let packet_id = VarInt::decode(&mut reader)?.0;
+
+        match (connection.state, packet_id) {
+            (ConnectionState::Login, Login::ID) => {
+                // The version goes first in every layout of `Login`, the rest
+                // may be laid out differently in a version we don't speak
+                let version = UVarInt::decode(&mut reader)?.0;
+                if !version::is_supported(version) {
+                    return Err(HandlerError::Kick(format!(
+                        "Unsupported protocol version {version}, \
+                         the server supports {MIN_PROTOCOL_VERSION}..={PROTOCOL_VERSION}"
+                    )));
+                }
+                let data = Login::decode(packet, &ctx)?;
+                Self::handle_login(
+                    world,
+                    presence_system,
+                    sessions,
+                    connections,
+                    connection_id,
+                    data,
+                )
+            }
+            (ConnectionState::Configure, SetDrawDistanceConfigure::ID) => {
+                SetDrawDistanceConfigure::decode(packet, &ctx)?;
+                connection.send::<ConfigureAcknowledged>(&ConfigureAcknowledgedData {})?;
+                connection.state = ConnectionState::Play;
+                Self::introduce(world, connections, sessions, connection_id)
+            }
+            (ConnectionState::Play, TurnSnake::ID) => {
+                let data = TurnSnake::decode(packet, &ctx)?;
+                Self::handle_turn(world, connection, tick, data)
+            }
+            // Unknown to clients without the feature
+            (ConnectionState::Play, ChatMessage::ID)
+                if connection.features.contains(Feature::Chat) =>
+            {
+                let data = ChatMessage::decode(packet, &ctx)?;
+                Self::handle_chat(connections, tick, connection_id, data)
+            }
+            _ => Err(HandlerError::Protocol(ProtocolError::ProtocolViolation(
+                ProtocolViolation::UnknownPacket,
+            ))),
+        }
+    }
+
+    /// Turns the snake if the packet passes the checks of `anticheat`.
+    /// A rejected packet is dropped and strikes the connection,
+    /// too many strikes kick it.
+    fn handle_turn(
+        world: &mut World,
+        connection: &mut Connection,
+        tick: Tick,
+        data: TurnSnakeData,
+    ) -> Result<(), HandlerError> {
+        let direction = Direction::try_from(data.direction.0)?;
+        let claimed = GridPos {
+            x: data.x.0,
+            y: data.y.0,
+        };
+
+        let checked = connection.guard.count_turn(tick).and_then(|()| {
+            // The snake died, the turns sent before `RemoveEntities` arrived are honest
+            let Some(entity_id) = connection.entity_id else {
+                return Ok(None);
+            };
+            let snake = world
+                .entity_manager
+                .get_mut(entity_id)
+                .ok_or(Violation::DeadEntity)?;
+            anticheat::check_position(snake, &claimed)?;
+            Ok(Some(snake))
+        });
+
+        match checked {
+            Ok(None) => Ok(()),
+            Ok(Some(snake)) => {
+                // Not cheating, just a deadly mistake the server doesn't let happen.
+                // Against the last move, `direction` may be turned already this tick.
+                let moving = snake.heading().unwrap_or(snake.direction);
+                if direction != moving.opposite() {
+                    snake.direction = direction;
+                }
+                Ok(())
+            }
+            Err(violation) => {
+                eprintln!("Flagged {}: {violation}", connection.id);
+                match connection.guard.strike(tick) {
+                    true => Err(HandlerError::Kick(format!(
+                        "Too many invalid packets, the last one: {violation}"
+                    ))),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Passes the message on to everybody, or tells the author why not
+    fn handle_chat(
+        connections: &mut ConnectionManager,
+        tick: Tick,
+        connection_id: ConnectionId,
+        data: ChatMessageData,
+    ) -> Result<(), HandlerError> {
+        let Some(connection) = connections.get_mut(connection_id) else {
+            return Ok(());
+        };
+        let checked = chat::sanitize(&data.message.0)
+            .and_then(|message| connection.chat.allow(tick).map(|()| message));
+        match checked {
+            Ok(message) => {
+                let sender = connection.username.clone().unwrap_or_default();
+                connections.broadcast_chat(&sender, &message);
+            }
+            Err(ChatError::Empty) => {}
+            Err(err) => connection.send::<ChatBroadcast>(&ChatBroadcastData {
+                sender: StringProto(String::new()),
+                message: StringProto(format!("Message dropped: {err}")),
+            })?,
+        }
+        Ok(())
+    }
+
+    /// Sends the snake of a player entering `Play` to everybody in `Play`,
+    /// and everybody's snakes to the player, then announces the player
+    fn introduce(
+        world: &World,
+        connections: &ConnectionManager,
+        sessions: &SessionManager,
+        connection_id: ConnectionId,
+    ) -> Result<(), HandlerError> {
+        let Some(joined) = connections.get(&connection_id) else {
+            return Ok(());
+        };
+        if let Some(username) = &joined.username {
+            connections.broadcast_chat("", &format!("{username} joined the game"));
+        }
+        let entities = &world.entity_manager.entities;
+        for (entity_id, snake) in entities.iter() {
+            if joined.entity_id == Some(*entity_id) {
+                continue;
+            }
+            if let Some(data) = spawn_entity_data(*entity_id, snake, sessions) {
+                joined.send::<SpawnEntity>(&data)?;
+            }
+        }
+
+        let Some(data) = joined.entity_id.and_then(|entity_id| {
+            let snake = entities.get(&entity_id)?;
+            spawn_entity_data(entity_id, snake, sessions)
+        }) else {
+            return Ok(());
+        };
+        for connection in connections.connections.values() {
+            if connection.state != ConnectionState::Play || connection.id == connection_id {
+                continue;
+            }
+            // Not the fault of the player who joined
+            if let Err(err) = connection.send::<SpawnEntity>(&data) {
+                eprintln!("Failed to send spawn to {}: {err}", connection.id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the username and the auth token, then resumes the session
+    /// if the client has a valid token, otherwise spawns a new snake.
+    /// The snake exists from this moment, even though the client
+    /// is still in the `Configure` stage.
+    fn handle_login(
+        world: &mut World,
+        presence_system: &mut PresenceSystem,
+        sessions: &mut SessionManager,
+        connections: &mut ConnectionManager,
+        connection_id: ConnectionId,
+        data: LoginData,
+    ) -> Result<(), HandlerError> {
+        let username = &data.username.0;
+        validate_username(username)
+            .map_err(|err| HandlerError::Kick(format!("Invalid username: {err}")))?;
+        if !connections.auth.check(username, &data.auth_token.0) {
+            return Err(HandlerError::Kick("Authentication failed".to_string()));
+        }
+
+        // The player keeps the name after the snake died, until they leave
+        let online = connections.username_taken(username);
+        let compression = connections.compression;
+        let Some(connection) = connections.get_mut(connection_id) else {
+            return Ok(());
+        };
+
+        connection.features = Features::all().intersection(&data.features);
+        let can_resume = connection.features.contains(Feature::SessionResume);
+
+        let token = data.reconnect_token.0 as u64;
+        let resumed = match token {
+            0 => None,
+            _ if !can_resume => None,
+            token => sessions.resume(token, connection.id, username),
+        };
+
+        let (entity_id, token) = match resumed {
+            Some(entity_id) => (entity_id, token),
+            None => {
+                // Also while the snake of the name waits for its player
+                if online || sessions.username_taken(username) {
+                    return Err(HandlerError::Kick(format!("Username {username} is taken")));
+                }
+                let entity_id = EntitySpawnSystem::spawn(world, presence_system)
+                    .ok_or_else(|| HandlerError::Kick("The arena is full".to_string()))?;
+                (
+                    entity_id,
+                    sessions.create(entity_id, connection.id, username),
+                )
+            }
+        };
+        println!(
+            "{} logged in as {username} with {} (protocol {})",
+            connection.id, data.client_name.0, data.protocol_version.0
+        );
+
+        connection.entity_id = Some(entity_id);
+        connection.username = Some(username.clone());
+        // Without the feature the session still exists, so the snake
+        // lingers for a moment after a disconnect, but can't be resumed
+        let token = if can_resume { token as i64 } else { 0 };
+        connection.send::<LoginSuccess>(&LoginSuccessData {
+            reconnect_token: ReconnectToken::from(token),
+            features: connection.features.clone(),
+        })?;
+
+        connection.state = ConnectionState::Configure;
+        if connection.features.contains(Feature::Compression) {
+            connection.send_enabling_compression::<SetCompression>(
+                &SetCompressionData {
+                    threshold: UVarInt(compression.threshold as u32),
+                },
+                compression,
+            )?;
+        }
+        connection.send::<WorldInfo>(&WorldInfoData::from(&world.world))?;
+        Ok(())
+    }
+}
+
+/// `None` for a snake without a body, there is nothing to show
+fn spawn_entity_data(
+    entity_id: EntityId,
+    snake: &Snake,
+    sessions: &SessionManager,
+) -> Option<SpawnEntityData> {
+    let head = snake.body.front()?;
+    Some(SpawnEntityData {
+        id: Id::from(entity_id as i64),
+        x: UVarInt(head.x),
+        y: UVarInt(head.y),
+        direction: Byte(snake.direction as u8),
+        username: StringProto(sessions.username(entity_id).unwrap_or_default().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{
+        anticheat::{MAX_POSITION_DRIFT, MAX_TURNS_PER_TICK, STRIKE_LIMIT},
+        auth::Auth,
+        chat::MESSAGES_PER_WINDOW,
+        connection::Outbound,
+        queue::{self, OutboundReceiver},
+        transport::memory::{join_packets, login_data},
+    };
+    use assert_matches::assert_matches;
+    use common::{
+        net::packets::{DisconnectLogin, SetDrawDistanceConfigureData},
+        world::types::ChunkSize,
+    };
+    use protocol::{
+        context::DecodeContext,
+        primitives::{byte::Byte, string::StringProto},
+    };
+    use std::sync::Arc;
+
+    struct Server {
+        world: World,
+        presence_system: PresenceSystem,
+        connections: ConnectionManager,
+        sessions: SessionManager,
+        tick: Tick,
+    }
+
+    impl Server {
+        fn new() -> Server {
+            let world = World::new(32, 32, ChunkSize::new(16, 16));
+            let mut presence_system = PresenceSystem::new();
+            presence_system.add_chunks(world.world.chunks_total());
+            Server {
+                world,
+                presence_system,
+                connections: ConnectionManager::new(),
+                sessions: SessionManager::new(),
+                tick: 1,
+            }
+        }
+
+        fn connect(&mut self, id: ConnectionId) -> OutboundReceiver {
+            let (tx, rx) = queue::channel();
+            self.connections.add(Connection::new(id, tx));
+            rx
+        }
+
+        fn handle(&mut self, id: ConnectionId, packet: &[u8]) -> Result<(), HandlerError> {
+            PacketHandler::handle(
+                &mut self.world,
+                &mut self.presence_system,
+                &mut self.connections,
+                &mut self.sessions,
+                self.tick,
+                id,
+                packet,
+            )
+        }
+    }
+
+    fn recv_reliable(outbound: &mut OutboundReceiver) -> Vec<u8> {
+        match outbound.try_recv().unwrap() {
+            Outbound::Reliable(body) => body,
+            other => panic!("Expected a reliable packet, got {other:?}"),
+        }
+    }
+
+    fn login(version: u32, features: Features) -> Vec<u8> {
+        login_as("viper", "", version, features)
+    }
+
+    fn login_as(username: &str, auth_token: &str, version: u32, features: Features) -> Vec<u8> {
+        Login::encode(&LoginData {
+            protocol_version: UVarInt(version),
+            auth_token: StringProto(auth_token.to_string()),
+            ..login_data(username, features, 0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn login_negotiates_features() {
+        let mut server = Server::new();
+        let mut outbound = server.connect(1);
+
+        server
+            .handle(1, &login(PROTOCOL_VERSION, Features::all()))
+            .unwrap();
+
+        let packet = recv_reliable(&mut outbound);
+        let data = LoginSuccess::decode(&packet, &DecodeContext::DEFAULT).unwrap();
+        assert_eq!(data.features, Features::all());
+        assert_ne!(data.reconnect_token.0, 0);
+        let connection = server.connections.get(&1).unwrap();
+        assert_eq!(connection.state, ConnectionState::Configure);
+    }
+
+    #[test]
+    fn login_without_resume_gets_no_token() {
+        let mut server = Server::new();
+        let mut outbound = server.connect(1);
+
+        server
+            .handle(1, &login(PROTOCOL_VERSION, Features::none()))
+            .unwrap();
+
+        let packet = recv_reliable(&mut outbound);
+        let data = LoginSuccess::decode(&packet, &DecodeContext::DEFAULT).unwrap();
+        assert_eq!(data.features, Features::none());
+        assert_eq!(data.reconnect_token.0, 0);
+    }
+
+    #[test]
+    fn login_enables_compression() {
+        let mut server = Server::new();
+        let mut outbound = server.connect(1);
+        let features = Features::from_iter([Feature::Compression]);
+
+        server
+            .handle(1, &login(PROTOCOL_VERSION, features))
+            .unwrap();
+
+        let ctx = DecodeContext::DEFAULT;
+        LoginSuccess::decode(&recv_reliable(&mut outbound), &ctx).unwrap();
+        // The transport compresses every frame after this one
+        let Ok(Outbound::Compress { body, compression }) = outbound.try_recv() else {
+            panic!("Expected SetCompression");
+        };
+        let data = SetCompression::decode(&body, &ctx).unwrap();
+        assert_eq!(data.threshold.0 as usize, compression.threshold);
+        assert_eq!(compression, server.connections.compression);
+
+        // Packets stay uncompressed for the game
+        WorldInfo::decode(&recv_reliable(&mut outbound), &ctx).unwrap();
+        let packet = SetDrawDistanceConfigure::encode(&SetDrawDistanceConfigureData {}).unwrap();
+        server.handle(1, &packet).unwrap();
+        ConfigureAcknowledged::decode(&recv_reliable(&mut outbound), &ctx).unwrap();
+    }
+
+    #[test]
+    fn login_personal_tokens() {
+        let mut server = Server::new();
+        server.connections.auth = Arc::new(Auth::from_tokens("viper a1\ncobra b2").unwrap());
+        server.connect(1);
+        server.connect(2);
+        server.connect(3);
+
+        // The token of another player
+        let res = server.handle(
+            1,
+            &login_as("viper", "b2", PROTOCOL_VERSION, Features::none()),
+        );
+        assert_matches!(res, Err(HandlerError::Kick(reason)) if reason.contains("Authentication"));
+        // Not on the list
+        let res = server.handle(
+            2,
+            &login_as("mamba", "a1", PROTOCOL_VERSION, Features::none()),
+        );
+        assert_matches!(res, Err(HandlerError::Kick(_)));
+
+        server
+            .handle(
+                3,
+                &login_as("Viper", "a1", PROTOCOL_VERSION, Features::none()),
+            )
+            .unwrap();
+        let entity_id = server.connections.get(&3).unwrap().entity_id.unwrap();
+        assert_eq!(server.sessions.username(entity_id), Some("Viper"));
+    }
+
+    fn turn(direction: Direction, head: &GridPos) -> Vec<u8> {
+        TurnSnake::encode(&TurnSnakeData {
+            direction: Byte(direction as u8),
+            x: UVarInt(head.x),
+            y: UVarInt(head.y),
+        })
+        .unwrap()
+    }
+
+    /// Logs in and goes to `Play`, returns the head of the snake
+    fn play(server: &mut Server, id: ConnectionId) -> GridPos {
+        for packet in join_packets(&format!("player{id}"), Features::none()).unwrap() {
+            server.handle(id, &packet).unwrap();
+        }
+
+        let entity_id = server.connections.get(&id).unwrap().entity_id.unwrap();
+        let snake = server.world.entity_manager.get(&entity_id).unwrap();
+        snake.body.front().unwrap().clone()
+    }
+
+    fn direction(server: &Server, id: ConnectionId) -> Direction {
+        let entity_id = server.connections.get(&id).unwrap().entity_id.unwrap();
+        server
+            .world
+            .entity_manager
+            .get(&entity_id)
+            .unwrap()
+            .direction
+    }
+
+    #[test]
+    fn login_fail_name_of_dead_snake() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        play(&mut server, 1);
+
+        // Dies the way `Game::tick` handles it, the player stays online
+        let entity_id = server.connections.get(&1).unwrap().entity_id.unwrap();
+        server.world.entity_manager.remove(entity_id);
+        server.sessions.remove_entity(entity_id);
+        server.connections.despawn(&[entity_id]);
+
+        server.connect(2);
+        let res = server.handle(
+            2,
+            &login_as("Player1", "", PROTOCOL_VERSION, Features::none()),
+        );
+        assert_matches!(res, Err(HandlerError::Kick(reason)) if reason.contains("taken"));
+    }
+
+    #[test]
+    fn turn_applied() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        let head = play(&mut server, 1);
+        let current = direction(&server, 1);
+        let sideways = match current {
+            Direction::North | Direction::South => Direction::West,
+            Direction::West | Direction::East => Direction::North,
+        };
+
+        // Back into the own body is ignored
+        server.handle(1, &turn(current.opposite(), &head)).unwrap();
+        assert_eq!(direction(&server, 1), current);
+
+        server.handle(1, &turn(sideways, &head)).unwrap();
+        assert_eq!(direction(&server, 1), sideways);
+    }
+
+    #[test]
+    fn turn_back_within_tick_ignored() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        let head = play(&mut server, 1);
+        let current = direction(&server, 1);
+        let sideways = match current {
+            Direction::North | Direction::South => Direction::West,
+            Direction::West | Direction::East => Direction::North,
+        };
+
+        // Two turns before the snake moves make a U-turn into the neck
+        server.handle(1, &turn(sideways, &head)).unwrap();
+        server.handle(1, &turn(current.opposite(), &head)).unwrap();
+        assert_eq!(direction(&server, 1), sideways);
+    }
+
+    #[test]
+    fn chat_flood_dropped() {
+        let mut server = Server::new();
+        let mut outbound = server.connect(1);
+        let chat = Features::from_iter([Feature::Chat]);
+        server
+            .handle(1, &login_as("viper", "", PROTOCOL_VERSION, chat))
+            .unwrap();
+        let packet = SetDrawDistanceConfigure::encode(&SetDrawDistanceConfigureData {}).unwrap();
+        server.handle(1, &packet).unwrap();
+        while outbound.try_recv().is_ok() {}
+
+        let message = ChatMessage::encode(&ChatMessageData {
+            message: StringProto("hiss".to_string()),
+        })
+        .unwrap();
+        for _ in 0..MESSAGES_PER_WINDOW {
+            server.handle(1, &message).unwrap();
+            let data =
+                ChatBroadcast::decode(&recv_reliable(&mut outbound), &DecodeContext::DEFAULT)
+                    .unwrap();
+            assert_eq!(data.sender.0, "viper");
+        }
+
+        // Only the author is told
+        server.handle(1, &message).unwrap();
+        let data =
+            ChatBroadcast::decode(&recv_reliable(&mut outbound), &DecodeContext::DEFAULT).unwrap();
+        assert_eq!(data.sender.0, "");
+        assert!(data.message.0.contains("too fast"), "{}", data.message.0);
+    }
+
+    #[test]
+    fn turn_rejected_then_kicked() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        let head = play(&mut server, 1);
+        let current = direction(&server, 1);
+        let far = GridPos {
+            x: head.x + MAX_POSITION_DRIFT + 1,
+            y: head.y,
+        };
+
+        // Dropped without a kick at first
+        server.handle(1, &turn(current.opposite(), &far)).unwrap();
+        for _ in 0..MAX_TURNS_PER_TICK {
+            server.handle(1, &turn(current, &head)).unwrap();
+        }
+        server.handle(1, &turn(current.opposite(), &head)).unwrap();
+        assert_eq!(direction(&server, 1), current);
+
+        let mut result = Ok(());
+        for _ in 0..STRIKE_LIMIT {
+            server.tick += 1;
+            result = server.handle(1, &turn(current, &far));
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_matches!(result, Err(HandlerError::Kick(reason)) if reason.contains("claimed"));
+    }
+
+    #[test]
+    fn turn_of_dead_snake_ignored() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        let head = play(&mut server, 1);
+        let entity_id = server.connections.get(&1).unwrap().entity_id.unwrap();
+        server.world.entity_manager.remove(entity_id);
+        server.connections.despawn(&[entity_id]);
+
+        // The client keeps turning until it learns about the death
+        for tick in 0..STRIKE_LIMIT as Tick * 2 {
+            server.tick = tick;
+            server.handle(1, &turn(Direction::North, &head)).unwrap();
+        }
+    }
+
+    #[test]
+    fn turn_of_missing_snake_rejected() {
+        let mut server = Server::new();
+        let _outbound = server.connect(1);
+        let head = play(&mut server, 1);
+        let entity_id = server.connections.get(&1).unwrap().entity_id.unwrap();
+        server.world.entity_manager.remove(entity_id);
+
+        for tick in 0..STRIKE_LIMIT as Tick - 1 {
+            server.tick = tick;
+            server.handle(1, &turn(Direction::North, &head)).unwrap();
+        }
+        assert_matches!(
+            server.handle(1, &turn(Direction::North, &head)),
+            Err(HandlerError::Kick(reason)) if reason.contains("dead")
+        );
+    }
+
+    #[test]
+    fn login_fail_unsupported_version() {
+        let mut server = Server::new();
+        let mut outbound = server.connect(1);
+
+        let res = server.handle(1, &login(PROTOCOL_VERSION + 1, Features::all()));
+        assert_matches!(res, Err(HandlerError::Kick(reason)) if reason.contains("version"));
+
+        // The reason reaches the client in the packet of the `Login` state
+        let connection = server.connections.remove(1).unwrap();
+        connection.disconnect("bye").unwrap();
+        let packet = recv_reliable(&mut outbound);
+        assert!(DisconnectLogin::decode(&packet, &DecodeContext::DEFAULT).is_ok());
+    }
+}
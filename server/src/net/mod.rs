@@ -1 +1,249 @@
+//! Networking part of the server.
+//!
+//! Transports (the task per client) and the game loop talk through channels:
+//! transports push `NetEvent`s into the game, the game pushes encoded
+//! packets into the outbound queue of every `Connection`. The queues are
+//! bounded (see `queue`) and the input is rate limited (see `rate_limit`),
+//! so a single slow or flooding client can't stall the tick for everyone.
+//!
+//! Transports add and strip the frame headers, and send
+//! `Outbound::Datagram`s outside of the stream (e.g. as QUIC datagrams).
+//! Compression is part of the frame header, so it's up to the transport
+//! too. It is switched on in the middle of the dialogue by a packet of
+//! the game, which comes through the queue as `Outbound::Compress`.
+
+pub mod anticheat;
+pub mod auth;
+pub mod chat;
 pub mod connection;
+pub mod handler;
+pub mod queue;
+pub mod rate_limit;
+pub mod session;
+pub mod transport;
+
+use std::{collections::HashMap, sync::Arc};
+
+use common::net::{
+    packets::{
+        ChatBroadcast, ChatBroadcastData, Id, Leaderboard, LeaderboardData, RemoveEntities,
+        RemoveEntitiesData,
+    },
+    username::same_username,
+    version::Feature,
+};
+use protocol::{compression::Compression, context::DecodeContext, primitives::string::StringProto};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    entity::EntityId,
+    net::{
+        auth::Auth,
+        connection::{Connection, ConnectionId, ConnectionState},
+        queue::OutboundSender,
+    },
+};
+
+#[derive(Debug)]
+pub enum NetEvent {
+    /// A client connected, its packets go to `outbound`
+    Connected {
+        connection_id: ConnectionId,
+        outbound: OutboundSender,
+    },
+
+    /// A frame body arrived from the client: the packet (id + payload),
+    /// already decompressed by the transport
+    Packet {
+        connection_id: ConnectionId,
+        packet: Vec<u8>,
+    },
+
+    /// The client is gone, by its own will or not
+    Disconnected { connection_id: ConnectionId },
+}
+
+pub type NetEventSender = UnboundedSender<NetEvent>;
+pub type NetEventReceiver = UnboundedReceiver<NetEvent>;
+
+/// Outbound queues of all connections, summed up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetMetrics {
+    pub connections: usize,
+
+    /// Frame bytes waiting in all queues
+    pub queued_bytes: usize,
+
+    /// Frame bytes waiting in the fullest queue
+    pub deepest_queue: usize,
+    pub queued_datagrams: usize,
+
+    /// Datagrams replaced by newer ones and dropped, since the start
+    /// of the connections still open
+    pub coalesced: u64,
+    pub dropped: u64,
+
+    /// Kicked for a full queue or flooding, since the start of the server
+    pub kicked: u64,
+}
+
+pub struct ConnectionManager {
+    pub connections: HashMap<ConnectionId, Connection>,
+
+    /// Limits for decoding packets of the clients
+    pub decode_context: DecodeContext,
+
+    /// Offered to clients with `Feature::Compression`
+    pub compression: Compression,
+
+    /// Who may log in, shared with the handler for every login
+    pub auth: Arc<Auth>,
+
+    /// Connections kicked for a full queue or flooding
+    pub kicked: u64,
+}
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        ConnectionManager::new()
+    }
+}
+
+impl ConnectionManager {
+    pub fn new() -> ConnectionManager {
+        ConnectionManager {
+            connections: HashMap::new(),
+            decode_context: DecodeContext::DEFAULT,
+            compression: Compression::default(),
+            auth: Arc::new(Auth::Open),
+            kicked: 0,
+        }
+    }
+
+    // -- Wrappers --
+    pub fn add(&mut self, connection: Connection) {
+        self.connections.insert(connection.id, connection);
+    }
+
+    pub fn remove(&mut self, id: ConnectionId) -> Option<Connection> {
+        self.connections.remove(&id)
+    }
+
+    pub fn get(&self, id: &ConnectionId) -> Option<&Connection> {
+        self.connections.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: ConnectionId) -> Option<&mut Connection> {
+        self.connections.get_mut(&id)
+    }
+    // -- Wrappers end --
+
+    /// Connections that don't read fast enough,
+    /// more than `queue::MAX_QUEUED_BYTES` are waiting for them
+    pub fn overflowed(&self) -> Vec<ConnectionId> {
+        self.connections
+            .values()
+            .filter(|connection| connection.overflowed())
+            .map(|connection| connection.id)
+            .collect()
+    }
+
+    pub fn metrics(&self) -> NetMetrics {
+        let mut metrics = NetMetrics {
+            connections: self.connections.len(),
+            kicked: self.kicked,
+            ..NetMetrics::default()
+        };
+        for connection in self.connections.values() {
+            let stats = connection.queue_stats();
+            metrics.queued_bytes += stats.frame_bytes;
+            metrics.deepest_queue = metrics.deepest_queue.max(stats.frame_bytes);
+            metrics.queued_datagrams += stats.datagrams;
+            metrics.coalesced += stats.coalesced;
+            metrics.dropped += stats.dropped;
+        }
+        metrics
+    }
+
+    /// Sends a chat line to everybody in `Play` with `Feature::Chat`,
+    /// an empty `sender` for announcements of the server
+    pub fn broadcast_chat(&self, sender: &str, message: &str) {
+        let data = ChatBroadcastData {
+            sender: StringProto(sender.to_string()),
+            message: StringProto(message.to_string()),
+        };
+        for connection in self.connections.values() {
+            if connection.state != ConnectionState::Play
+                || !connection.features.contains(Feature::Chat)
+            {
+                continue;
+            }
+            if let Err(err) = connection.send::<ChatBroadcast>(&data) {
+                eprintln!("Failed to send chat to {}: {err}", connection.id);
+            }
+        }
+    }
+
+    /// Whether a logged in connection has this name, with a snake or without
+    pub fn username_taken(&self, username: &str) -> bool {
+        self.connections.values().any(|connection| {
+            connection
+                .username
+                .as_deref()
+                .is_some_and(|taken| same_username(taken, username))
+        })
+    }
+
+    /// Removes dead snakes from the clients in `Play`,
+    /// their players keep watching without one
+    pub fn despawn(&mut self, entity_ids: &[EntityId]) {
+        let data = RemoveEntitiesData {
+            entities: entity_ids
+                .iter()
+                .map(|entity_id| Id::from(*entity_id as i64))
+                .collect::<Vec<_>>()
+                .into(),
+        };
+        for connection in self.connections.values_mut() {
+            if connection
+                .entity_id
+                .is_some_and(|entity_id| entity_ids.contains(&entity_id))
+            {
+                connection.entity_id = None;
+            }
+            if connection.state != ConnectionState::Play {
+                continue;
+            }
+            if let Err(err) = connection.send::<RemoveEntities>(&data) {
+                eprintln!(
+                    "Failed to send removed entities to {}: {err}",
+                    connection.id
+                );
+            }
+        }
+    }
+
+    /// Sends the leaderboard to everybody in `Play` with `Feature::Leaderboard`
+    pub fn broadcast_leaderboard(&self, data: &LeaderboardData) {
+        for connection in self.connections.values() {
+            if connection.state != ConnectionState::Play
+                || !connection.features.contains(Feature::Leaderboard)
+            {
+                continue;
+            }
+            if let Err(err) = connection.send::<Leaderboard>(data) {
+                eprintln!("Failed to send leaderboard to {}: {err}", connection.id);
+            }
+        }
+    }
+
+    /// Sends the reason to every client and drops the connections,
+    /// so the transports close them after flushing their queues.
+    pub fn disconnect_all(&mut self, reason: &str) {
+        for (_, connection) in self.connections.drain() {
+            if let Err(err) = connection.disconnect(reason) {
+                eprintln!("Failed to disconnect {}: {err:?}", connection.id);
+            }
+        }
+    }
+}
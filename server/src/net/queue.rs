@@ -0,0 +1,358 @@
+//! The outbound queue of a connection, from the game to the transport.
+//!
+//! Unlike a plain channel, the queue is bounded, so a client that can't
+//! keep up doesn't make the server buffer without end:
+//!
+//! - frames of the reliable stream can't be dropped, they pile up until
+//!   `MAX_QUEUED_BYTES`, then the queue is `overflowed` and the game kicks
+//!   the client;
+//! - a datagram with the key of one still waiting replaces it in place,
+//!   the newer state of an entity makes the older one useless anyway;
+//! - beyond `MAX_QUEUED_DATAGRAMS` the oldest datagram is dropped.
+//!
+//! The transport takes frames first, datagrams when the stream is idle,
+//! so a stalled stream is what makes datagrams coalesce.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use protocol::compression::Compression;
+use tokio::sync::{Notify, mpsc::error::TryRecvError};
+
+use crate::net::connection::Outbound;
+
+/// Frame bytes a client may fall behind before it's kicked
+pub const MAX_QUEUED_BYTES: usize = 1024 * 1024;
+
+/// Datagrams waiting at most, the older ones are dropped
+pub const MAX_QUEUED_DATAGRAMS: usize = 256;
+
+/// Datagrams with the same key describe the same thing,
+/// e.g. packet id and entity id
+pub type CoalesceKey = (i32, u64);
+
+/// What is waiting and what was thrown away so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub frames: usize,
+    pub frame_bytes: usize,
+    pub datagrams: usize,
+
+    /// Replaced by a newer datagram with the same key
+    pub coalesced: u64,
+
+    /// Dropped over `MAX_QUEUED_DATAGRAMS`
+    pub dropped: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// `Outbound::Reliable` and `Outbound::Compress`, in order
+    frames: VecDeque<Outbound>,
+    frame_bytes: usize,
+
+    /// Keys in the order of arrival, `None` for datagrams that never coalesce
+    order: VecDeque<Option<CoalesceKey>>,
+    keyed: HashMap<CoalesceKey, Vec<u8>>,
+    unkeyed: VecDeque<Vec<u8>>,
+
+    coalesced: u64,
+    dropped: u64,
+
+    /// The game dropped its end
+    closed: bool,
+
+    /// The transport dropped its end, nothing will be read anymore
+    abandoned: bool,
+}
+
+impl State {
+    fn pop_frame(&mut self) -> Option<Outbound> {
+        let frame = self.frames.pop_front()?;
+        if let Outbound::Reliable(body) | Outbound::Compress { body, .. } = &frame {
+            self.frame_bytes -= body.len();
+        }
+        Some(frame)
+    }
+
+    fn pop_datagram(&mut self) -> Option<Vec<u8>> {
+        match self.order.pop_front()? {
+            Some(key) => self.keyed.remove(&key),
+            None => self.unkeyed.pop_front(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // Nothing panics while holding the lock, but a poisoned queue
+        // is still a consistent one
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// A new queue, the sender goes to the `Connection`, the receiver to the transport
+pub fn channel() -> (OutboundSender, OutboundReceiver) {
+    let shared = Arc::new(Shared::default());
+    (
+        OutboundSender {
+            shared: shared.clone(),
+        },
+        OutboundReceiver { shared },
+    )
+}
+
+/// The end of the game. Dropping it ends the queue
+/// once the transport has read everything.
+#[derive(Debug)]
+pub struct OutboundSender {
+    shared: Arc<Shared>,
+}
+
+impl OutboundSender {
+    /// Never refused, check `overflowed` afterwards
+    pub fn send_reliable(&self, body: Vec<u8>) {
+        self.push_frame(body.len(), Outbound::Reliable(body));
+    }
+
+    /// The last frame sent uncompressed, see `Outbound::Compress`
+    pub fn send_compress(&self, body: Vec<u8>, compression: Compression) {
+        self.push_frame(body.len(), Outbound::Compress { body, compression });
+    }
+
+    fn push_frame(&self, len: usize, frame: Outbound) {
+        let mut state = self.shared.lock();
+        if state.abandoned {
+            return;
+        }
+        state.frame_bytes += len;
+        state.frames.push_back(frame);
+        drop(state);
+        self.shared.notify.notify_one();
+    }
+
+    /// Replaces the waiting datagram with the same `key`, if there is one
+    pub fn send_datagram(&self, key: Option<CoalesceKey>, datagram: Vec<u8>) {
+        let mut state = self.shared.lock();
+        if state.abandoned {
+            return;
+        }
+        if let Some(key) = key
+            && let Some(waiting) = state.keyed.get_mut(&key)
+        {
+            *waiting = datagram;
+            state.coalesced += 1;
+            return;
+        }
+
+        if state.order.len() >= MAX_QUEUED_DATAGRAMS {
+            state.pop_datagram();
+            state.dropped += 1;
+        }
+        state.order.push_back(key);
+        match key {
+            Some(key) => {
+                state.keyed.insert(key, datagram);
+            }
+            None => state.unkeyed.push_back(datagram),
+        }
+        drop(state);
+        self.shared.notify.notify_one();
+    }
+
+    /// More than `MAX_QUEUED_BYTES` of frames are waiting
+    pub fn overflowed(&self) -> bool {
+        self.shared.lock().frame_bytes > MAX_QUEUED_BYTES
+    }
+
+    /// The transport is gone
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().abandoned
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.shared.lock();
+        QueueStats {
+            frames: state.frames.len(),
+            frame_bytes: state.frame_bytes,
+            datagrams: state.order.len(),
+            coalesced: state.coalesced,
+            dropped: state.dropped,
+        }
+    }
+}
+
+impl Drop for OutboundSender {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.notify.notify_one();
+    }
+}
+
+/// The end of the transport
+#[derive(Debug)]
+pub struct OutboundReceiver {
+    shared: Arc<Shared>,
+}
+
+impl OutboundReceiver {
+    /// Frames first, then datagrams. `Disconnected` once the game
+    /// closed the queue and everything was read.
+    pub fn try_recv(&mut self) -> Result<Outbound, TryRecvError> {
+        let mut state = self.shared.lock();
+        if let Some(frame) = state.pop_frame() {
+            return Ok(frame);
+        }
+        if let Some(datagram) = state.pop_datagram() {
+            return Ok(Outbound::Datagram(datagram));
+        }
+        match state.closed {
+            true => Err(TryRecvError::Disconnected),
+            false => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Waits for the next packet, `None` once the queue ended
+    pub async fn recv(&mut self) -> Option<Outbound> {
+        loop {
+            match self.try_recv() {
+                Ok(outbound) => return Some(outbound),
+                Err(TryRecvError::Disconnected) => return None,
+                // A send between `try_recv` and here leaves a permit,
+                // so the wakeup isn't lost
+                Err(TryRecvError::Empty) => self.shared.notify.notified().await,
+            }
+        }
+    }
+
+    /// The game closed the queue
+    pub fn is_closed(&self) -> bool {
+        self.shared.lock().closed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let state = self.shared.lock();
+        state.frames.is_empty() && state.order.is_empty()
+    }
+}
+
+impl Drop for OutboundReceiver {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.abandoned = true;
+        state.frames.clear();
+        state.frame_bytes = 0;
+        state.order.clear();
+        state.keyed.clear();
+        state.unkeyed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn queue_frames_before_datagrams() {
+        let (sender, mut receiver) = channel();
+        sender.send_datagram(None, vec![1]);
+        sender.send_reliable(vec![2]);
+        drop(sender);
+
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Reliable(vec![2])));
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Datagram(vec![1])));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[test]
+    fn queue_compress_in_order() {
+        let (sender, mut receiver) = channel();
+        let compression = Compression::new(64);
+        sender.send_reliable(vec![1]);
+        sender.send_compress(vec![2], compression);
+        sender.send_reliable(vec![3]);
+        assert_eq!(sender.stats().frame_bytes, 3);
+
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Reliable(vec![1])));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(Outbound::Compress {
+                body: vec![2],
+                compression
+            })
+        );
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Reliable(vec![3])));
+        assert_eq!(sender.stats().frame_bytes, 0);
+    }
+
+    #[test]
+    fn queue_coalesces_datagrams() {
+        let (sender, mut receiver) = channel();
+        sender.send_datagram(Some((3, 1)), vec![1]);
+        sender.send_datagram(Some((3, 2)), vec![2]);
+        sender.send_datagram(Some((3, 1)), vec![3]);
+
+        let stats = sender.stats();
+        assert_eq!(stats.datagrams, 2);
+        assert_eq!(stats.coalesced, 1);
+        // The newer one takes the place of the older one
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Datagram(vec![3])));
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Datagram(vec![2])));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn queue_bounded() {
+        let (sender, mut receiver) = channel();
+        for i in 0..=MAX_QUEUED_DATAGRAMS {
+            sender.send_datagram(None, vec![i as u8]);
+        }
+        assert_eq!(sender.stats().dropped, 1);
+        assert_eq!(sender.stats().datagrams, MAX_QUEUED_DATAGRAMS);
+        assert_eq!(receiver.try_recv(), Ok(Outbound::Datagram(vec![1])));
+
+        sender.send_reliable(vec![0; MAX_QUEUED_BYTES]);
+        assert!(!sender.overflowed());
+        sender.send_reliable(vec![0]);
+        assert!(sender.overflowed());
+        assert_matches!(receiver.try_recv(), Ok(Outbound::Reliable(_)));
+        assert!(!sender.overflowed());
+    }
+
+    #[test]
+    fn queue_abandoned_by_transport() {
+        let (sender, receiver) = channel();
+        sender.send_reliable(vec![1]);
+        drop(receiver);
+
+        assert!(sender.is_closed());
+        sender.send_reliable(vec![2]);
+        assert_eq!(sender.stats(), QueueStats::default());
+    }
+
+    #[tokio::test]
+    async fn queue_recv_wakes_up() {
+        let (sender, mut receiver) = channel();
+        let reader = tokio::spawn(async move {
+            let first = receiver.recv().await;
+            let end = receiver.recv().await;
+            (first, end)
+        });
+        tokio::task::yield_now().await;
+        sender.send_reliable(vec![1]);
+        drop(sender);
+
+        let (first, end) = reader.await.unwrap();
+        assert_eq!(first, Some(Outbound::Reliable(vec![1])));
+        assert_eq!(end, None);
+    }
+}
@@ -0,0 +1,111 @@
+//! Limits of what a client may send.
+//!
+//! Every frame body costs the game a decode at least, so a client
+//! flooding the server slows the tick down for everyone. Each connection
+//! has a budget of packets and bytes, refilled every tick up to a burst;
+//! a client over it is kicked without its packet being looked at.
+
+use common::net::datagram::Tick;
+
+/// Packets per tick a client may send on average...
+pub const PACKETS_PER_TICK: u32 = 10;
+
+/// ...and at once, e.g. after a lag spike
+pub const PACKET_BURST: u32 = 100;
+
+/// The same for the bytes of frame bodies
+pub const BYTES_PER_TICK: u32 = 4 * 1024;
+pub const BYTE_BURST: u32 = 64 * 1024;
+
+/// Tokens refilled every tick up to `capacity`
+#[derive(Debug, Clone)]
+struct Bucket {
+    tokens: u32,
+    capacity: u32,
+    refill: u32,
+}
+
+impl Bucket {
+    fn new(refill: u32, capacity: u32) -> Bucket {
+        Bucket {
+            tokens: capacity,
+            capacity,
+            refill,
+        }
+    }
+
+    fn refill(&mut self, ticks: Tick) {
+        let refilled = self.refill.saturating_mul(ticks);
+        self.tokens = self.tokens.saturating_add(refilled).min(self.capacity);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    /// The tick the buckets were last refilled in
+    tick: Tick,
+    packets: Bucket,
+    bytes: Bucket,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit::new()
+    }
+}
+
+impl RateLimit {
+    pub fn new() -> RateLimit {
+        RateLimit {
+            tick: 0,
+            packets: Bucket::new(PACKETS_PER_TICK, PACKET_BURST),
+            bytes: Bucket::new(BYTES_PER_TICK, BYTE_BURST),
+        }
+    }
+
+    /// Takes a packet of `len` bytes received in `tick` out of the budget,
+    /// `false` if the client is over it
+    pub fn allow(&mut self, tick: Tick, len: usize) -> bool {
+        let ticks = tick.wrapping_sub(self.tick);
+        if ticks > 0 {
+            self.tick = tick;
+            self.packets.refill(ticks);
+            self.bytes.refill(ticks);
+        }
+        let len = u32::try_from(len).unwrap_or(u32::MAX);
+        if self.packets.tokens == 0 || self.bytes.tokens < len {
+            return false;
+        }
+        self.packets.tokens -= 1;
+        self.bytes.tokens -= len;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limit_burst_then_refill() {
+        let mut limit = RateLimit::new();
+        for _ in 0..PACKET_BURST {
+            assert!(limit.allow(0, 1));
+        }
+        assert!(!limit.allow(0, 1));
+
+        for _ in 0..PACKETS_PER_TICK {
+            assert!(limit.allow(1, 1));
+        }
+        assert!(!limit.allow(1, 1));
+    }
+
+    #[test]
+    fn rate_limit_bytes() {
+        let mut limit = RateLimit::new();
+        assert!(limit.allow(0, BYTE_BURST as usize));
+        assert!(!limit.allow(0, 1));
+        assert!(limit.allow(1, BYTES_PER_TICK as usize));
+        assert!(!limit.allow(100, usize::MAX));
+    }
+}
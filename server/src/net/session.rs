@@ -0,0 +1,211 @@
+//! Provides resumable sessions: a player who drops for a few seconds
+//! can log in again with the reconnect token from `LoginSuccess`
+//! and continue controlling the same snake.
+//!
+//! The session also holds the username of the player, so the name stays
+//! taken while the snake waits for its player.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use common::net::username::same_username;
+
+use crate::{entity::EntityId, net::connection::ConnectionId};
+
+/// Secret given to the client at `LoginSuccess`, `0` is reserved for "no token"
+pub type Token = u64;
+
+/// How long the snake of a dropped player waits for them
+pub const RESUME_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Session {
+    entity_id: EntityId,
+    username: String,
+
+    /// `None` while the player is away
+    connection_id: Option<ConnectionId>,
+
+    /// When the player dropped, used to expire the session
+    suspended_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct SessionManager {
+    sessions: HashMap<Token, Session>,
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        SessionManager::new()
+    }
+}
+
+impl SessionManager {
+    pub fn new() -> SessionManager {
+        SessionManager {
+            sessions: HashMap::new(),
+        }
+    }
+
+    /// Starts a session for a freshly spawned snake
+    pub fn create(
+        &mut self,
+        entity_id: EntityId,
+        connection_id: ConnectionId,
+        username: &str,
+    ) -> Token {
+        let token = loop {
+            let token: Token = rand::random();
+            if token != 0 && !self.sessions.contains_key(&token) {
+                break token;
+            }
+        };
+        self.sessions.insert(
+            token,
+            Session {
+                entity_id,
+                username: username.to_string(),
+                connection_id: Some(connection_id),
+                suspended_at: None,
+            },
+        );
+        token
+    }
+
+    /// Reattaches a suspended session of `username` to a new connection.
+    /// Returns `None` for unknown tokens, for sessions of other players
+    /// and for sessions that are still controlled by another connection.
+    pub fn resume(
+        &mut self,
+        token: Token,
+        connection_id: ConnectionId,
+        username: &str,
+    ) -> Option<EntityId> {
+        let session = self.sessions.get_mut(&token)?;
+        session.suspended_at?;
+        if !same_username(&session.username, username) {
+            return None;
+        }
+
+        session.connection_id = Some(connection_id);
+        session.suspended_at = None;
+        Some(session.entity_id)
+    }
+
+    /// Whether a player with this name is in the game, playing or awaited.
+    /// A player whose snake died is only known to `ConnectionManager`.
+    pub fn username_taken(&self, username: &str) -> bool {
+        self.sessions
+            .values()
+            .any(|session| same_username(&session.username, username))
+    }
+
+    /// The name of the player controlling the snake
+    pub fn username(&self, entity_id: EntityId) -> Option<&str> {
+        self.sessions
+            .values()
+            .find(|session| session.entity_id == entity_id)
+            .map(|session| session.username.as_str())
+    }
+
+    /// Marks the session of a dropped connection as waiting for the player
+    pub fn suspend(&mut self, connection_id: ConnectionId, now: Instant) {
+        for session in self.sessions.values_mut() {
+            if session.connection_id == Some(connection_id) {
+                session.connection_id = None;
+                session.suspended_at = Some(now);
+            }
+        }
+    }
+
+    /// Suspends every session, e.g. when the server shuts down
+    pub fn suspend_all(&mut self, now: Instant) {
+        for session in self.sessions.values_mut() {
+            session.connection_id = None;
+            session.suspended_at = Some(now);
+        }
+    }
+
+    /// Forgets the sessions that waited longer than `RESUME_WINDOW`
+    /// and returns their entities, which must be removed from the game
+    pub fn expire(&mut self, now: Instant) -> Vec<EntityId> {
+        let mut expired = Vec::new();
+        self.sessions
+            .retain(|_, session| match session.suspended_at {
+                Some(at) if now.duration_since(at) >= RESUME_WINDOW => {
+                    expired.push(session.entity_id);
+                    false
+                }
+                _ => true,
+            });
+        expired
+    }
+
+    /// Ends the session of a dead snake, there is nothing to resume anymore
+    pub fn remove_entity(&mut self, entity_id: EntityId) {
+        self.sessions
+            .retain(|_, session| session.entity_id != entity_id);
+    }
+
+    /// `(token, entity, username)` of every session, for snapshots
+    pub fn iter(&self) -> impl Iterator<Item = (Token, EntityId, &str)> {
+        self.sessions
+            .iter()
+            .map(|(token, session)| (*token, session.entity_id, session.username.as_str()))
+    }
+
+    /// Puts a session restored from a snapshot, waiting for its player
+    pub fn restore(&mut self, token: Token, entity_id: EntityId, username: &str, now: Instant) {
+        self.sessions.insert(
+            token,
+            Session {
+                entity_id,
+                username: username.to_string(),
+                connection_id: None,
+                suspended_at: Some(now),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_resume_after_suspend() {
+        let mut sessions = SessionManager::new();
+        let now = Instant::now();
+        let token = sessions.create(42, 1, "viper");
+
+        // Still controlled by the first connection
+        assert_eq!(sessions.resume(token, 2, "viper"), None);
+
+        sessions.suspend(1, now);
+        // The token alone is not enough
+        assert_eq!(sessions.resume(token, 2, "cobra"), None);
+        assert_eq!(sessions.resume(token, 2, "Viper"), Some(42));
+        assert_eq!(sessions.resume(0, 3, "viper"), None);
+    }
+
+    #[test]
+    fn session_expire() {
+        let mut sessions = SessionManager::new();
+        let now = Instant::now();
+        let token = sessions.create(42, 1, "viper");
+        sessions.create(43, 2, "cobra");
+
+        sessions.suspend(1, now);
+        assert!(sessions.expire(now + RESUME_WINDOW / 2).is_empty());
+        // Taken until the session expires
+        assert!(sessions.username_taken("VIPER"));
+        assert_eq!(sessions.expire(now + RESUME_WINDOW), vec![42]);
+        assert!(!sessions.username_taken("viper"));
+        assert_eq!(sessions.resume(token, 3, "viper"), None);
+        assert_eq!(sessions.iter().count(), 1);
+        assert_eq!(sessions.username(43), Some("cobra"));
+    }
+}
@@ -0,0 +1,192 @@
+//! In-process transport for tests.
+//!
+//! A `MemoryClient` talks to the game through channels only, without
+//! sockets, frames or tasks. Everything it sends is in the event queue
+//! of the game right away, and everything the game sends during a tick
+//! can be read right after it, so tests are fully deterministic.
+//!
+//! Without frames there is no compression either,
+//! `Outbound::Compress` is just one more packet.
+//!
+//! `login_data` and `join_packets` are what every test of the server
+//! logs in with, whether it drives the game through a transport or not.
+
+use crate::net::{
+    NetEventSender,
+    connection::{ConnectionId, Outbound},
+    queue::OutboundReceiver,
+    transport::{ConnectionIds, Transport},
+};
+use common::net::{
+    datagram::{self, Tick},
+    packets::{
+        Login, LoginData, Packet, ReconnectToken, SetDrawDistanceConfigure,
+        SetDrawDistanceConfigureData,
+    },
+    version::{Features, PROTOCOL_VERSION},
+};
+use protocol::{
+    error::ProtocolError,
+    primitives::{string::StringProto, uvarint::UVarInt},
+};
+
+#[derive(Clone)]
+pub struct MemoryTransport {
+    net_events: NetEventSender,
+    connection_ids: ConnectionIds,
+}
+
+impl MemoryTransport {
+    pub fn new(net_events: NetEventSender, connection_ids: ConnectionIds) -> MemoryTransport {
+        MemoryTransport {
+            net_events,
+            connection_ids,
+        }
+    }
+
+    pub fn connect(&self) -> MemoryClient {
+        let (id, inbound) = self.register();
+        MemoryClient {
+            id,
+            transport: self.clone(),
+            inbound,
+        }
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn net_events(&self) -> &NetEventSender {
+        &self.net_events
+    }
+
+    fn connection_ids(&self) -> &ConnectionIds {
+        &self.connection_ids
+    }
+}
+
+/// What test clients log in with: the current protocol and no auth token
+pub fn login_data(username: &str, features: Features, reconnect_token: i64) -> LoginData {
+    LoginData {
+        protocol_version: UVarInt(PROTOCOL_VERSION),
+        client_name: StringProto("test".to_string()),
+        username: StringProto(username.to_string()),
+        auth_token: StringProto(String::new()),
+        features,
+        reconnect_token: ReconnectToken::from(reconnect_token),
+    }
+}
+
+/// `Login` and `SetDrawDistanceConfigure` right after it, the game
+/// handles both in order and the client ends up in `Play`
+pub fn join_packets(username: &str, features: Features) -> Result<[Vec<u8>; 2], ProtocolError> {
+    Ok([
+        Login::encode(&login_data(username, features, 0))?,
+        SetDrawDistanceConfigure::encode(&SetDrawDistanceConfigureData {})?,
+    ])
+}
+
+/// What a `MemoryClient` received
+#[derive(Debug, PartialEq, Eq)]
+pub enum Received {
+    /// Packet id + payload
+    Reliable(Vec<u8>),
+
+    Datagram {
+        tick: Tick,
+        packet: Vec<u8>,
+    },
+}
+
+/// The client end of an in-process connection.
+/// Dropping it is the same as closing the socket.
+pub struct MemoryClient {
+    id: ConnectionId,
+    transport: MemoryTransport,
+    inbound: OutboundReceiver,
+}
+
+impl MemoryClient {
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+
+    pub fn send<P: Packet>(&self, data: &P::Data) -> Result<(), ProtocolError> {
+        self.send_raw(P::encode(data)?);
+        Ok(())
+    }
+
+    /// Sends a frame body as is, e.g. a malformed one
+    pub fn send_raw(&self, body: Vec<u8>) {
+        self.transport.deliver(self.id, body);
+    }
+
+    /// The next packet sent by the game, `None` if there is none yet
+    pub fn try_recv(&mut self) -> Result<Option<Received>, ProtocolError> {
+        match self.inbound.try_recv() {
+            Ok(Outbound::Reliable(body) | Outbound::Compress { body, .. }) => {
+                Ok(Some(Received::Reliable(body)))
+            }
+            Ok(Outbound::Datagram(bytes)) => {
+                let (tick, packet) = datagram::decode_datagram(&bytes)?;
+                Ok(Some(Received::Datagram {
+                    tick,
+                    packet: packet.to_vec(),
+                }))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Whether the game has closed the connection and
+    /// everything it sent before has been read
+    pub fn is_closed(&self) -> bool {
+        self.inbound.is_closed() && self.inbound.is_empty()
+    }
+}
+
+impl Drop for MemoryClient {
+    fn drop(&mut self) {
+        self.transport.unregister(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::NetEvent;
+    use assert_matches::assert_matches;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn memory_transport_events() {
+        let (net_events, mut events) = mpsc::unbounded_channel();
+        let transport = MemoryTransport::new(net_events, ConnectionIds::new());
+
+        let mut client = transport.connect();
+        assert_eq!(client.id(), 1);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+
+        client.send_raw(vec![1, 2]);
+        assert_matches!(
+            events.try_recv(),
+            Ok(NetEvent::Packet { connection_id: 1, packet }) if packet == [1, 2]
+        );
+
+        outbound.send_reliable(vec![3]);
+        drop(outbound);
+        assert!(!client.is_closed());
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![3]))
+        );
+        assert!(client.is_closed());
+
+        drop(client);
+        assert_matches!(
+            events.try_recv(),
+            Ok(NetEvent::Disconnected { connection_id: 1 })
+        );
+    }
+}
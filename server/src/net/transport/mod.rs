@@ -0,0 +1,82 @@
+//! Transports bring clients to the game.
+//!
+//! Whatever moves the bytes (QUIC, in-process channels), a transport
+//! keeps the same contract with the game, implemented once by `Transport`:
+//!
+//! 1. `register` a new client, which sends `NetEvent::Connected` with
+//!    a fresh id and the queue of its `Outbound` packets;
+//! 2. `deliver` every frame body of the client, in order;
+//! 3. send `Outbound::Reliable` in order and `Outbound::Datagram`
+//!    best effort, until the game drops the `Connection` and the queue ends,
+//!    then close the client. Reading the queue only as fast as the client
+//!    takes the data is fine, the queue is bounded by the game;
+//! 4. `unregister` the client once it's gone, exactly once.
+//!
+//! `simulator::NetworkSimulator` can be put between any transport
+//! and the game to make the network worse.
+
+pub mod memory;
+pub mod quic;
+pub mod simulator;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crate::net::{
+    NetEvent, NetEventSender,
+    connection::ConnectionId,
+    queue::{self, OutboundReceiver},
+};
+
+/// Hands out connection ids, shared by all transports of a server
+/// so the ids never collide
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionIds(Arc<AtomicU64>);
+
+impl ConnectionIds {
+    pub fn new() -> ConnectionIds {
+        ConnectionIds::default()
+    }
+
+    /// Starts at 1
+    pub fn next(&self) -> ConnectionId {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+}
+
+pub trait Transport {
+    fn net_events(&self) -> &NetEventSender;
+
+    fn connection_ids(&self) -> &ConnectionIds;
+
+    /// Reports a new client to the game.
+    /// The packets for it arrive in the returned queue.
+    fn register(&self) -> (ConnectionId, OutboundReceiver) {
+        let connection_id = self.connection_ids().next();
+        let (outbound, queue) = queue::channel();
+        // If the game is gone, the queue ends right away
+        // and the client gets closed
+        let _ = self.net_events().send(NetEvent::Connected {
+            connection_id,
+            outbound,
+        });
+        (connection_id, queue)
+    }
+
+    /// Passes a frame body of the client to the game
+    fn deliver(&self, connection_id: ConnectionId, packet: Vec<u8>) {
+        let _ = self.net_events().send(NetEvent::Packet {
+            connection_id,
+            packet,
+        });
+    }
+
+    /// Reports that the client is gone
+    fn unregister(&self, connection_id: ConnectionId) {
+        let _ = self
+            .net_events()
+            .send(NetEvent::Disconnected { connection_id });
+    }
+}
@@ -0,0 +1,215 @@
+//! QUIC transport.
+//!
+//! Every client opens a single bidirectional stream with its first packet.
+//! Frames (see `protocol::frame`) of both directions go over that stream,
+//! `Outbound::Datagram`s go as QUIC datagrams next to it.
+//!
+//! On `Outbound::Compress` the reader switches to compressed frames
+//! before the writer sends the packet: the client can't send anything
+//! compressed before it gets it, and sends nothing in between.
+//!
+//! The server certificate is self-signed for `localhost` and regenerated
+//! on every start, clients get it out of band (`main` writes it to disk).
+
+use std::{fmt, io, net::SocketAddr, time::Duration};
+
+use protocol::{
+    async_io::{FrameReader, write_frame},
+    compression::Compression,
+};
+use quinn::{
+    Endpoint, Incoming, ServerConfig,
+    rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+};
+
+use tokio::sync::watch;
+
+use crate::net::{
+    NetEventSender,
+    connection::Outbound,
+    transport::{ConnectionIds, Transport},
+};
+
+/// The name the certificate is issued for
+pub const SERVER_NAME: &str = "localhost";
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(io::Error),
+    Certificate(rcgen::Error),
+    Tls(quinn::rustls::Error),
+    Connection(quinn::ConnectionError),
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Io(err) => write!(f, "io error: {err}"),
+            TransportError::Certificate(err) => write!(f, "certificate error: {err}"),
+            TransportError::Tls(err) => write!(f, "tls error: {err}"),
+            TransportError::Connection(err) => write!(f, "connection error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+impl From<io::Error> for TransportError {
+    fn from(value: io::Error) -> Self {
+        TransportError::Io(value)
+    }
+}
+
+impl From<rcgen::Error> for TransportError {
+    fn from(value: rcgen::Error) -> Self {
+        TransportError::Certificate(value)
+    }
+}
+
+impl From<quinn::rustls::Error> for TransportError {
+    fn from(value: quinn::rustls::Error) -> Self {
+        TransportError::Tls(value)
+    }
+}
+
+impl From<quinn::ConnectionError> for TransportError {
+    fn from(value: quinn::ConnectionError) -> Self {
+        TransportError::Connection(value)
+    }
+}
+
+#[derive(Clone)]
+pub struct QuicTransport {
+    endpoint: Endpoint,
+    net_events: NetEventSender,
+    connection_ids: ConnectionIds,
+}
+
+impl QuicTransport {
+    /// Listens on `addr` with a fresh self-signed certificate,
+    /// which is returned for the clients to trust
+    pub fn bind(
+        addr: SocketAddr,
+        net_events: NetEventSender,
+        connection_ids: ConnectionIds,
+    ) -> Result<(QuicTransport, CertificateDer<'static>), TransportError> {
+        let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])?;
+        let cert = certified.cert.der().clone();
+        let key =
+            PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+        let config = ServerConfig::with_single_cert(vec![cert.clone()], key)?;
+        let endpoint = Endpoint::server(config, addr)?;
+        let transport = QuicTransport {
+            endpoint,
+            net_events,
+            connection_ids,
+        };
+        Ok((transport, cert))
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, TransportError> {
+        Ok(self.endpoint.local_addr()?)
+    }
+
+    /// Accepts clients until the endpoint is closed
+    pub async fn serve(self) {
+        while let Some(incoming) = self.endpoint.accept().await {
+            let transport = self.clone();
+            tokio::spawn(async move {
+                let remote = incoming.remote_address();
+                if let Err(err) = transport.handle(incoming).await {
+                    eprintln!("Connection from {remote} failed: {err}");
+                }
+            });
+        }
+    }
+
+    /// Stops accepting clients and waits up to `timeout` for the writers
+    /// to deliver what the game sent before it stopped, then closes the rest
+    pub async fn shutdown(&self, timeout: Duration) {
+        self.endpoint.set_server_config(None);
+        let _ = tokio::time::timeout(timeout, self.endpoint.wait_idle()).await;
+        self.endpoint.close(0u32.into(), b"");
+    }
+
+    async fn handle(&self, incoming: Incoming) -> Result<(), TransportError> {
+        let connection = incoming.await?;
+        let (mut send, recv) = connection.accept_bi().await?;
+        let (connection_id, mut queue) = self.register();
+        let (compression_tx, mut compression_rx) = watch::channel(None::<Compression>);
+
+        let reader = async {
+            let mut frames = FrameReader::new(recv);
+            loop {
+                // `read_frame` is cancel safe, a half read frame stays buffered
+                let frame = tokio::select! {
+                    biased;
+                    Ok(()) = compression_rx.changed() => {
+                        frames.set_compression(*compression_rx.borrow_and_update());
+                        continue;
+                    }
+                    frame = frames.read_frame() => frame,
+                };
+                match frame {
+                    Ok(Some(body)) => self.deliver(connection_id, body.into_owned()),
+                    Ok(None) => break,
+                    Err(err) => {
+                        eprintln!("Failed to read from {connection_id}: {err}");
+                        break;
+                    }
+                }
+            }
+            self.unregister(connection_id);
+        };
+
+        let writer = async {
+            let mut compression = None;
+            while let Some(outbound) = queue.recv().await {
+                match outbound {
+                    Outbound::Reliable(body) => {
+                        if write_frame(&mut send, &body, compression.as_ref())
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Outbound::Compress {
+                        body,
+                        compression: enabled,
+                    } => {
+                        compression_tx.send_replace(Some(enabled));
+                        if write_frame(&mut send, &body, None).await.is_err() {
+                            break;
+                        }
+                        compression = Some(enabled);
+                    }
+                    // Too large or not supported by the client,
+                    // the same as if it was lost on the way
+                    Outbound::Datagram(datagram) => {
+                        let _ = connection.send_datagram(datagram.into());
+                    }
+                }
+            }
+            // The game dropped the connection, let the client read
+            // the rest of the stream (e.g. the disconnect reason) first
+            let _ = send.finish();
+            let _ = send.stopped().await;
+            connection.close(0u32.into(), b"");
+        };
+
+        tokio::join!(reader, writer);
+        Ok(())
+    }
+}
+
+impl Transport for QuicTransport {
+    fn net_events(&self) -> &NetEventSender {
+        &self.net_events
+    }
+
+    fn connection_ids(&self) -> &ConnectionIds {
+        &self.connection_ids
+    }
+}
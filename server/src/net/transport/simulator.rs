@@ -0,0 +1,630 @@
+//! Bad network on demand.
+//!
+//! `NetworkSimulator` sits between the transports and the game: transports
+//! deliver their events to `NetworkSimulator::net_events` instead of the game,
+//! and every client gets a queue of its own that the simulator fills from
+//! the `Outbound` queue of the game. Both directions are held back by
+//! the configured latency and jitter, and datagrams are lost, duplicated
+//! and reordered on the way. Every connection gets the same conditions,
+//! unless `NetworkSimulator::set_conditions` gives it its own.
+//!
+//! The reliable stream stays reliable: a lost frame costs a round trip
+//! for the retransmission, and frames always arrive in order and once.
+//!
+//! A client that doesn't read holds up its link: nothing more is taken
+//! from the queue of the game until the client has read what was delivered.
+//! The queue of the game then coalesces datagrams and overflows the same
+//! way it does behind a real transport that can't send.
+//!
+//! Nothing moves by itself, `pump` delivers what is due at the given
+//! instant. Tests pump with a clock of their own, `run` pumps in real time.
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use tokio::sync::mpsc;
+
+use crate::net::{
+    NetEvent, NetEventReceiver, NetEventSender,
+    connection::{ConnectionId, Outbound},
+    queue::{self, OutboundReceiver, OutboundSender},
+};
+
+/// How often `run` pumps
+const PUMP_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Both directions of a connection
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConditions {
+    /// One way delay
+    pub latency: Duration,
+
+    /// Every packet is delayed by `latency` plus or minus up to this much
+    pub jitter: Duration,
+
+    /// Probability of a packet to be lost, 0.0 to 1.0.
+    /// Lost datagrams are gone, lost frames are retransmitted.
+    pub loss: f64,
+
+    /// Probability of a datagram to arrive twice
+    pub duplication: f64,
+
+    /// Probability of a datagram to be held back by one more `latency`,
+    /// so the next ones overtake it
+    pub reordering: f64,
+}
+
+impl NetworkConditions {
+    /// Changes nothing, the same as no simulator at all
+    pub const PERFECT: NetworkConditions = NetworkConditions {
+        latency: Duration::ZERO,
+        jitter: Duration::ZERO,
+        loss: 0.0,
+        duplication: 0.0,
+        reordering: 0.0,
+    };
+}
+
+impl Default for NetworkConditions {
+    fn default() -> Self {
+        NetworkConditions::PERFECT
+    }
+}
+
+/// Where a packet goes once it's due
+#[derive(Debug)]
+enum Delivery {
+    ToGame(NetEvent),
+    ToClient(ConnectionId, Outbound),
+
+    /// The game closed the connection, the client queue
+    /// ends after everything sent before
+    Close(ConnectionId),
+}
+
+#[derive(Debug)]
+struct Scheduled {
+    at: Instant,
+
+    /// Keeps the order of packets due at the same instant
+    seq: u64,
+
+    delivery: Delivery,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// Both directions of one client
+struct Link {
+    /// Filled by the game, `None` once it closed the connection
+    from_game: Option<OutboundReceiver>,
+
+    /// Drained by the transport
+    to_client: OutboundSender,
+
+    /// When the last reliable frame of each direction is due,
+    /// the next ones can't arrive earlier
+    last_to_game: Instant,
+    last_to_client: Instant,
+}
+
+pub struct NetworkSimulator {
+    conditions: NetworkConditions,
+    /// Connections with conditions of their own
+    overrides: HashMap<ConnectionId, NetworkConditions>,
+    rng: StdRng,
+
+    /// For the transports, in place of the sender of the game
+    events_tx: NetEventSender,
+    events: NetEventReceiver,
+
+    game: NetEventSender,
+    links: HashMap<ConnectionId, Link>,
+
+    scheduled: BinaryHeap<Reverse<Scheduled>>,
+    next_seq: u64,
+}
+
+impl NetworkSimulator {
+    /// Delivers to `game` (see `Game::net_events`).
+    /// The same `seed` gives the same losses and delays.
+    pub fn new(conditions: NetworkConditions, seed: u64, game: NetEventSender) -> NetworkSimulator {
+        let (events_tx, events) = mpsc::unbounded_channel();
+        NetworkSimulator {
+            conditions,
+            overrides: HashMap::new(),
+            rng: StdRng::seed_from_u64(seed),
+            events_tx,
+            events,
+            game,
+            links: HashMap::new(),
+            scheduled: BinaryHeap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// The sender for transports, in place of `Game::net_events`
+    pub fn net_events(&self) -> NetEventSender {
+        self.events_tx.clone()
+    }
+
+    pub fn conditions(&self) -> &NetworkConditions {
+        &self.conditions
+    }
+
+    /// Gives a connection conditions of its own, for the packets
+    /// taken in from now on. May be set before the connection is pumped.
+    pub fn set_conditions(&mut self, connection_id: ConnectionId, conditions: NetworkConditions) {
+        self.overrides.insert(connection_id, conditions);
+    }
+
+    /// Back to the conditions of every connection
+    pub fn reset_conditions(&mut self, connection_id: ConnectionId) {
+        self.overrides.remove(&connection_id);
+    }
+
+    /// What the packets of the connection go through
+    pub fn conditions_of(&self, connection_id: ConnectionId) -> &NetworkConditions {
+        self.overrides
+            .get(&connection_id)
+            .unwrap_or(&self.conditions)
+    }
+
+    /// Takes in what the transports and the game sent until `now`
+    /// and delivers everything that is due by then
+    pub fn pump(&mut self, now: Instant) {
+        while let Ok(event) = self.events.try_recv() {
+            self.take_from_client(event, now);
+        }
+        self.take_from_game(now);
+
+        while let Some(Reverse(next)) = self.scheduled.peek() {
+            if next.at > now {
+                break;
+            }
+            let Some(Reverse(next)) = self.scheduled.pop() else {
+                break;
+            };
+            self.deliver(next.delivery);
+        }
+    }
+
+    /// When the next scheduled packet is due, `None` if there is none
+    pub fn next_due(&self) -> Option<Instant> {
+        self.scheduled.peek().map(|Reverse(next)| next.at)
+    }
+
+    /// Pumps in real time until the game is gone
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(PUMP_INTERVAL);
+        while !self.game.is_closed() {
+            interval.tick().await;
+            self.pump(Instant::now());
+        }
+    }
+
+    fn take_from_client(&mut self, event: NetEvent, now: Instant) {
+        match event {
+            NetEvent::Connected {
+                connection_id,
+                outbound,
+            } => {
+                // The game writes to the link, the client reads what's due
+                let (to_link, from_game) = queue::channel();
+                self.links.insert(
+                    connection_id,
+                    Link {
+                        from_game: Some(from_game),
+                        to_client: outbound,
+                        last_to_game: now,
+                        last_to_client: now,
+                    },
+                );
+                let event = NetEvent::Connected {
+                    connection_id,
+                    outbound: to_link,
+                };
+                self.schedule_reliable(connection_id, Delivery::ToGame(event), now);
+            }
+            NetEvent::Packet { connection_id, .. } => {
+                self.schedule_reliable(connection_id, Delivery::ToGame(event), now);
+            }
+            // The connection is gone after the frames sent before
+            NetEvent::Disconnected { connection_id } => {
+                let at = match self.links.get(&connection_id) {
+                    Some(link) => link.last_to_game.max(now),
+                    None => now,
+                };
+                self.schedule(at, Delivery::ToGame(event));
+            }
+        }
+    }
+
+    fn take_from_game(&mut self, now: Instant) {
+        let mut received = Vec::new();
+        let mut closed = Vec::new();
+        for (connection_id, link) in self.links.iter_mut() {
+            let Some(from_game) = &mut link.from_game else {
+                continue;
+            };
+            // Backpressure, unless the game is done with the connection
+            let stats = link.to_client.stats();
+            if (stats.frames > 0 || stats.datagrams > 0) && !from_game.is_closed() {
+                continue;
+            }
+            while let Ok(outbound) = from_game.try_recv() {
+                received.push((*connection_id, outbound));
+            }
+            if from_game.is_closed() && from_game.is_empty() {
+                link.from_game = None;
+                closed.push(*connection_id);
+            }
+        }
+        // Ids only grow, sorting keeps the random numbers deterministic
+        received.sort_by_key(|(connection_id, _)| *connection_id);
+        closed.sort();
+
+        for (connection_id, outbound) in received {
+            match outbound {
+                Outbound::Reliable(_) | Outbound::Compress { .. } => {
+                    let delivery = Delivery::ToClient(connection_id, outbound);
+                    self.schedule_reliable(connection_id, delivery, now);
+                }
+                Outbound::Datagram(_) => self.schedule_datagram(connection_id, outbound, now),
+            }
+        }
+        for connection_id in closed {
+            if let Some(link) = self.links.get(&connection_id) {
+                let at = link.last_to_client.max(now);
+                self.schedule(at, Delivery::Close(connection_id));
+            }
+        }
+    }
+
+    fn deliver(&mut self, delivery: Delivery) {
+        match delivery {
+            Delivery::ToGame(event) => {
+                if let NetEvent::Disconnected { connection_id } = &event {
+                    self.links.remove(connection_id);
+                    self.overrides.remove(connection_id);
+                }
+                let _ = self.game.send(event);
+            }
+            // The client may be closed already, the same as a lost packet
+            Delivery::ToClient(connection_id, outbound) => {
+                if let Some(link) = self.links.get(&connection_id) {
+                    match outbound {
+                        Outbound::Reliable(body) => link.to_client.send_reliable(body),
+                        // The transport behind compresses from then on
+                        Outbound::Compress { body, compression } => {
+                            link.to_client.send_compress(body, compression)
+                        }
+                        // Coalescing is up to the queue of the game
+                        Outbound::Datagram(bytes) => link.to_client.send_datagram(None, bytes),
+                    }
+                }
+            }
+            // Dropping the sender ends the queue of the transport
+            Delivery::Close(connection_id) => {
+                self.links.remove(&connection_id);
+            }
+        }
+    }
+
+    /// In order, and a lost frame is sent again a round trip later
+    fn schedule_reliable(&mut self, connection_id: ConnectionId, delivery: Delivery, now: Instant) {
+        let conditions = self.conditions_of(connection_id).clone();
+        let mut delay = self.delay(&conditions);
+        if self.happens(conditions.loss) {
+            delay += conditions.latency * 2;
+        }
+        let Some(link) = self.links.get_mut(&connection_id) else {
+            return;
+        };
+        let last = match delivery {
+            Delivery::ToGame(_) => &mut link.last_to_game,
+            _ => &mut link.last_to_client,
+        };
+        let at = (now + delay).max(*last);
+        *last = at;
+        self.schedule(at, delivery);
+    }
+
+    fn schedule_datagram(&mut self, connection_id: ConnectionId, datagram: Outbound, now: Instant) {
+        let conditions = self.conditions_of(connection_id).clone();
+        if self.happens(conditions.loss) {
+            return;
+        }
+        if self.happens(conditions.duplication) {
+            let Outbound::Datagram(bytes) = &datagram else {
+                return;
+            };
+            let copy = Outbound::Datagram(bytes.clone());
+            let at = now + self.delay(&conditions);
+            self.schedule(at, Delivery::ToClient(connection_id, copy));
+        }
+
+        let mut delay = self.delay(&conditions);
+        if self.happens(conditions.reordering) {
+            delay += conditions.latency;
+        }
+        self.schedule(now + delay, Delivery::ToClient(connection_id, datagram));
+    }
+
+    fn schedule(&mut self, at: Instant, delivery: Delivery) {
+        self.scheduled.push(Reverse(Scheduled {
+            at,
+            seq: self.next_seq,
+            delivery,
+        }));
+        self.next_seq += 1;
+    }
+
+    /// `latency` plus or minus `jitter`
+    fn delay(&mut self, conditions: &NetworkConditions) -> Duration {
+        let NetworkConditions {
+            latency, jitter, ..
+        } = *conditions;
+        if jitter.is_zero() {
+            return latency;
+        }
+        let offset = self.rng.gen_range(0..=jitter.as_micros() as u64 * 2);
+        (latency + Duration::from_micros(offset)).saturating_sub(jitter)
+    }
+
+    fn happens(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.gen_bool(probability.min(1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{
+        queue::MAX_QUEUED_BYTES,
+        transport::{ConnectionIds, memory::MemoryTransport, memory::Received},
+    };
+    use assert_matches::assert_matches;
+    use common::net::datagram;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// A client through the simulator and the queue of the game
+    fn setup(
+        conditions: NetworkConditions,
+    ) -> (NetworkSimulator, MemoryTransport, NetEventReceiver) {
+        let (game, events) = mpsc::unbounded_channel();
+        let simulator = NetworkSimulator::new(conditions, 0, game);
+        let transport = MemoryTransport::new(simulator.net_events(), ConnectionIds::new());
+        (simulator, transport, events)
+    }
+
+    fn datagram(tick: u32) -> Vec<u8> {
+        datagram::encode_datagram(tick, &[0]).unwrap()
+    }
+
+    #[test]
+    fn simulator_latency_both_ways() {
+        let conditions = NetworkConditions {
+            latency: 50 * MS,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(conditions);
+        let start = Instant::now();
+
+        let mut client = transport.connect();
+        client.send_raw(vec![1]);
+        simulator.pump(start);
+        assert!(events.try_recv().is_err());
+
+        simulator.pump(start + 50 * MS);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+        assert_matches!(events.try_recv(), Ok(NetEvent::Packet { packet, .. }) if packet == [1]);
+
+        outbound.send_reliable(vec![2]);
+        simulator.pump(start + 60 * MS);
+        assert_eq!(client.try_recv().unwrap(), None);
+        simulator.pump(start + 110 * MS);
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![2]))
+        );
+    }
+
+    #[test]
+    fn simulator_conditions_per_connection() {
+        let conditions = NetworkConditions {
+            latency: 50 * MS,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(conditions);
+        let start = Instant::now();
+
+        let slow = transport.connect();
+        let fast = transport.connect();
+        simulator.set_conditions(fast.id(), NetworkConditions::PERFECT);
+        assert_eq!(simulator.conditions_of(slow.id()).latency, 50 * MS);
+        fast.send_raw(vec![1]);
+        slow.send_raw(vec![2]);
+
+        simulator.pump(start);
+        assert_matches!(
+            events.try_recv(),
+            Ok(NetEvent::Connected { connection_id, .. }) if connection_id == fast.id()
+        );
+        assert_matches!(events.try_recv(), Ok(NetEvent::Packet { packet, .. }) if packet == [1]);
+        assert!(events.try_recv().is_err());
+
+        simulator.pump(start + 50 * MS);
+        assert_matches!(
+            events.try_recv(),
+            Ok(NetEvent::Connected { connection_id, .. }) if connection_id == slow.id()
+        );
+        assert_matches!(events.try_recv(), Ok(NetEvent::Packet { packet, .. }) if packet == [2]);
+    }
+
+    #[test]
+    fn simulator_reliable_in_order() {
+        let conditions = NetworkConditions {
+            latency: 50 * MS,
+            jitter: 40 * MS,
+            loss: 0.5,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(conditions);
+        let start = Instant::now();
+
+        let client = transport.connect();
+        for i in 0..100 {
+            client.send_raw(vec![i]);
+        }
+        drop(client);
+        simulator.pump(start);
+        simulator.pump(start + Duration::from_secs(1));
+
+        assert_matches!(events.try_recv(), Ok(NetEvent::Connected { .. }));
+        for i in 0..100 {
+            assert_matches!(
+                events.try_recv(),
+                Ok(NetEvent::Packet { packet, .. }) if packet == [i]
+            );
+        }
+        assert_matches!(events.try_recv(), Ok(NetEvent::Disconnected { .. }));
+    }
+
+    #[test]
+    fn simulator_datagrams_lost_and_duplicated() {
+        let lossy = NetworkConditions {
+            loss: 1.0,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(lossy);
+        let now = Instant::now();
+        let mut client = transport.connect();
+        simulator.pump(now);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+        outbound.send_datagram(None, datagram(1));
+        // Late, but there
+        outbound.send_reliable(vec![1]);
+        simulator.pump(now);
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![1]))
+        );
+        assert_eq!(client.try_recv().unwrap(), None);
+
+        let duplicating = NetworkConditions {
+            duplication: 1.0,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(duplicating);
+        let mut client = transport.connect();
+        simulator.pump(now);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+        outbound.send_datagram(None, datagram(1));
+        simulator.pump(now);
+        for _ in 0..2 {
+            assert_matches!(
+                client.try_recv(),
+                Ok(Some(Received::Datagram { tick: 1, .. }))
+            );
+        }
+        assert_eq!(client.try_recv().unwrap(), None);
+    }
+
+    #[test]
+    fn simulator_stalled_client_backs_up_game() {
+        let (mut simulator, transport, mut events) = setup(NetworkConditions::PERFECT);
+        let now = Instant::now();
+        let mut client = transport.connect();
+        simulator.pump(now);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+
+        // Delivered, but the client doesn't read it
+        outbound.send_reliable(vec![1]);
+        simulator.pump(now);
+        outbound.send_reliable(vec![2]);
+        outbound.send_datagram(Some((0, 1)), datagram(1));
+        outbound.send_datagram(Some((0, 1)), datagram(2));
+        simulator.pump(now);
+
+        // Still in the queue of the game, where the positions coalesce
+        let stats = outbound.stats();
+        assert_eq!((stats.frames, stats.datagrams, stats.coalesced), (1, 1, 1));
+        for _ in 0..MAX_QUEUED_BYTES / 1024 {
+            outbound.send_reliable(vec![0; 1024]);
+        }
+        simulator.pump(now);
+        assert!(outbound.overflowed());
+
+        // Reading makes room again
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![1]))
+        );
+        simulator.pump(now);
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![2]))
+        );
+        assert!(!outbound.overflowed());
+    }
+
+    #[test]
+    fn simulator_closes_after_last_frame() {
+        let conditions = NetworkConditions {
+            latency: 10 * MS,
+            ..NetworkConditions::PERFECT
+        };
+        let (mut simulator, transport, mut events) = setup(conditions);
+        let start = Instant::now();
+        let mut client = transport.connect();
+        simulator.pump(start);
+        simulator.pump(start + 10 * MS);
+        let Ok(NetEvent::Connected { outbound, .. }) = events.try_recv() else {
+            panic!("Expected Connected");
+        };
+
+        outbound.send_reliable(vec![1]);
+        drop(outbound);
+        simulator.pump(start + 10 * MS);
+        assert!(!client.is_closed());
+
+        simulator.pump(start + 20 * MS);
+        assert_eq!(
+            client.try_recv().unwrap(),
+            Some(Received::Reliable(vec![1]))
+        );
+        assert!(client.is_closed());
+    }
+}
@@ -0,0 +1,270 @@
+//! Provides versioned binary snapshots of the game state on disk,
+//! so restarting the server doesn't lose the world and the snakes in it.
+//!
+//! The snapshot reuses the `Codec` primitives of the protocol crate:
+//!
+//! ```text
+//! magic    "VNMZ"      4 bytes
+//! version  UVarInt
+//! world    World       (see `impl Codec for World`)
+//! entities VarInt      amount of entities, then for each of them:
+//!     id    VarLong
+//!     snake Snake
+//! sessions VarInt      amount of sessions, then for each of them:
+//!     token    VarLong
+//!     id       VarLong
+//!     username String
+//! scores   VarInt      amount of scores, then for each of them:
+//!     id       VarLong
+//!     apples   UVarInt
+//!     kills    UVarInt
+//!     survived UVarInt ticks the snake has been alive for
+//! ```
+//!
+//! Sessions are stored so that players can resume after a restart.
+//! The tick starts over at 0 after a restart, so restored scores
+//! are spawned `survived` ticks before it.
+
+use std::{
+    fs,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::Instant,
+};
+
+use common::{entities::snake::Snake, net::datagram::Tick, world::world::World as CommonWorld};
+use protocol::{
+    codec::Codec,
+    error::ProtocolError,
+    primitives::{string::StringProto, uvarint::UVarInt, varint::VarInt, varlong::VarLong},
+};
+
+use crate::{
+    entity::EntityManager,
+    net::session::SessionManager,
+    systems::score::{Score, ScoreSystem},
+    world::World,
+};
+
+const MAGIC: [u8; 4] = *b"VNMZ";
+
+/// Must be bumped on every change of the layout
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Protocol(ProtocolError),
+    /// The file is not a snapshot at all
+    BadMagic,
+    /// The snapshot was written by an incompatible server
+    UnsupportedVersion(u32),
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        SnapshotError::Io(value)
+    }
+}
+
+impl From<ProtocolError> for SnapshotError {
+    fn from(value: ProtocolError) -> Self {
+        // The codec wraps io errors of the underlying file too
+        match value {
+            ProtocolError::Io(err) => SnapshotError::Io(err),
+            other => SnapshotError::Protocol(other),
+        }
+    }
+}
+
+pub struct Snapshot;
+
+impl Snapshot {
+    /// Writes the snapshot into a temporary file first and then renames it,
+    /// so a crash in the middle of writing never corrupts the previous one.
+    pub fn save(
+        world: &World,
+        sessions: &SessionManager,
+        scores: &ScoreSystem,
+        tick: Tick,
+        path: impl AsRef<Path>,
+    ) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("tmp");
+
+        let mut writer = BufWriter::new(fs::File::create(&tmp_path)?);
+        Self::write(world, sessions, scores, tick, &mut writer)?;
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+
+        fs::rename(tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Restored sessions wait for their players from this moment,
+    /// restored scores count their survival from tick 0
+    pub fn load(
+        path: impl AsRef<Path>,
+    ) -> Result<(World, SessionManager, ScoreSystem), SnapshotError> {
+        let mut reader = BufReader::new(fs::File::open(path)?);
+        Self::read(&mut reader)
+    }
+
+    /// `tick` is the current one, survival is counted up to it
+    pub fn write(
+        world: &World,
+        sessions: &SessionManager,
+        scores: &ScoreSystem,
+        tick: Tick,
+        writer: &mut impl Write,
+    ) -> Result<(), SnapshotError> {
+        writer.write_all(&MAGIC)?;
+        UVarInt(SNAPSHOT_VERSION).encode(writer)?;
+
+        world.world.encode(writer)?;
+
+        // Same layout as `PrefixedArray`, but without cloning every snake
+        let entities = &world.entity_manager.entities;
+        VarInt(entities.len() as i32).encode(writer)?;
+        for (id, snake) in entities.iter() {
+            VarLong(*id as i64).encode(writer)?;
+            snake.encode(writer)?;
+        }
+
+        VarInt(sessions.iter().count() as i32).encode(writer)?;
+        for (token, entity_id, username) in sessions.iter() {
+            VarLong(token as i64).encode(writer)?;
+            VarLong(entity_id as i64).encode(writer)?;
+            StringProto(username.to_string()).encode(writer)?;
+        }
+
+        VarInt(scores.iter().count() as i32).encode(writer)?;
+        for (entity_id, score) in scores.iter() {
+            VarLong(entity_id as i64).encode(writer)?;
+            UVarInt(score.apples).encode(writer)?;
+            UVarInt(score.kills).encode(writer)?;
+            UVarInt(tick.wrapping_sub(score.spawned)).encode(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(
+        reader: &mut impl Read,
+    ) -> Result<(World, SessionManager, ScoreSystem), SnapshotError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+
+        let version = UVarInt::decode(reader)?.0;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let common_world = CommonWorld::decode(reader)?;
+
+        let mut entity_manager = EntityManager::new();
+        let count = VarInt::decode(reader)?.0;
+        for _ in 0..count {
+            let id = VarLong::decode(reader)?.0 as u64;
+            let snake = Snake::decode(reader)?;
+            entity_manager.insert(id, snake);
+        }
+
+        let mut sessions = SessionManager::new();
+        let now = Instant::now();
+        let count = VarInt::decode(reader)?.0;
+        for _ in 0..count {
+            let token = VarLong::decode(reader)?.0 as u64;
+            let entity_id = VarLong::decode(reader)?.0 as u64;
+            let username = StringProto::decode(reader)?.0;
+            sessions.restore(token, entity_id, &username, now);
+        }
+
+        let mut scores = ScoreSystem::new();
+        let count = VarInt::decode(reader)?.0;
+        for _ in 0..count {
+            let entity_id = VarLong::decode(reader)?.0 as u64;
+            let apples = UVarInt::decode(reader)?.0;
+            let kills = UVarInt::decode(reader)?.0;
+            let survived = UVarInt::decode(reader)?.0;
+            let score = Score {
+                apples,
+                kills,
+                spawned: (0 as Tick).wrapping_sub(survived),
+            };
+            scores.restore(entity_id, score);
+        }
+
+        let world = World {
+            world: common_world,
+            entity_manager,
+        };
+        Ok((world, sessions, scores))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use common::world::{chunk::Tile, types::GridPos};
+
+    #[test]
+    fn snapshot_roundtrip() -> Result<(), SnapshotError> {
+        let mut world = World::new(32, 32, Default::default());
+        world.world.set_tile(&GridPos { x: 3, y: 7 }, Tile::Wall);
+        let mut snake = Snake::new();
+        snake.body.push_back(GridPos { x: 10, y: 10 });
+        world.entity_manager.insert(u64::MAX, snake);
+        let mut sessions = SessionManager::new();
+        let token = sessions.create(u64::MAX, 1, "viper");
+        let mut scores = ScoreSystem::new();
+        scores.tick(&world.entity_manager, &[], 100);
+        scores.apple_eaten(u64::MAX);
+
+        let path = std::env::temp_dir().join("venomized_snapshot_roundtrip.snapshot");
+        Snapshot::save(&world, &sessions, &scores, 130, &path)?;
+        let (restored, mut restored_sessions, restored_scores) = Snapshot::load(&path)?;
+        fs::remove_file(&path)?;
+
+        assert_eq!(
+            restored.world.get_tile(&GridPos { x: 3, y: 7 }),
+            Some(&Tile::Wall)
+        );
+        let snake = restored.entity_manager.get(&u64::MAX).unwrap();
+        assert_eq!(snake.body, [GridPos { x: 10, y: 10 }]);
+        // The session waits for its player after the restart
+        assert!(restored_sessions.username_taken("viper"));
+        assert_eq!(restored_sessions.resume(token, 2, "viper"), Some(u64::MAX));
+        // The tick starts over, the snake has still survived for 30 ticks
+        let standing = &restored_scores.top(&restored.entity_manager, 0, 1)[0];
+        assert_eq!(standing.apples, 1);
+        assert_eq!(standing.kills, 0);
+        assert_eq!(standing.survived, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_read_fail_bad_magic() {
+        let buf = b"NOPE\x01";
+
+        assert_matches!(Snapshot::read(&mut &buf[..]), Err(SnapshotError::BadMagic));
+    }
+
+    #[test]
+    fn snapshot_read_fail_version() -> Result<(), SnapshotError> {
+        let mut buf = MAGIC.to_vec();
+        UVarInt(SNAPSHOT_VERSION + 1).encode(&mut buf)?;
+
+        assert_matches!(
+            Snapshot::read(&mut &buf[..]),
+            Err(SnapshotError::UnsupportedVersion(v)) if v == SNAPSHOT_VERSION + 1
+        );
+        Ok(())
+    }
+}
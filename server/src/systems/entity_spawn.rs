@@ -1,7 +1,84 @@
 //! Provides a mechanism for new players to appear on the map
 
+use common::{
+    entities::snake::{Direction, Snake},
+    world::{chunk::Tile, types::GridPos},
+};
+use rand::{Rng, seq::SliceRandom};
+
+use crate::{entity::EntityId, systems::presence::PresenceSystem, world::World};
+
+/// Length of a freshly spawned snake
+pub const INITIAL_LENGTH: u32 = 3;
+
+/// How many random places are tried before giving up
+const SPAWN_ATTEMPTS: u32 = 64;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
 pub struct EntitySpawnSystem;
 
 impl EntitySpawnSystem {
-    pub fn tick() {}
+    /// Places a new snake on a free spot, preferring the spawn points of the map.
+    /// Returns `None` if no free spot was found, e.g. the arena is full.
+    pub fn spawn(world: &mut World, presence_system: &mut PresenceSystem) -> Option<EntityId> {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..SPAWN_ATTEMPTS {
+            let head = match world.world.spawn_points.choose(&mut rng) {
+                Some(pos) => pos.clone(),
+                None => GridPos {
+                    x: rng.gen_range(0..world.world.width),
+                    y: rng.gen_range(0..world.world.height),
+                },
+            };
+            let direction = DIRECTIONS[rng.gen_range(0..DIRECTIONS.len())];
+
+            if let Some(snake) = Self::try_place(world, head, direction) {
+                let entity_id = world.entity_manager.add(snake);
+                let snake = world.entity_manager.get(&entity_id)?;
+                presence_system.register_new_entity(entity_id, snake, &world.world);
+                return Some(entity_id);
+            }
+        }
+        None
+    }
+
+    /// The body trails behind the head, opposite to the direction.
+    /// Every part must be on an empty tile not taken by another snake.
+    fn try_place(world: &World, head: GridPos, direction: Direction) -> Option<Snake> {
+        let (dx, dy): (i64, i64) = match direction {
+            Direction::North => (0, 1),
+            Direction::South => (0, -1),
+            Direction::West => (1, 0),
+            Direction::East => (-1, 0),
+        };
+
+        let mut snake = Snake::new();
+        snake.direction = direction;
+
+        for i in 0..INITIAL_LENGTH as i64 {
+            let pos = GridPos {
+                x: u32::try_from(head.x as i64 + dx * i).ok()?,
+                y: u32::try_from(head.y as i64 + dy * i).ok()?,
+            };
+            if world.world.get_tile(&pos) != Some(&Tile::Empty) || Self::occupied(world, &pos) {
+                return None;
+            }
+            snake.body.push_back(pos);
+        }
+        Some(snake)
+    }
+
+    fn occupied(world: &World, pos: &GridPos) -> bool {
+        world
+            .entity_manager
+            .iter()
+            .any(|snake| snake.body.contains(pos))
+    }
 }
@@ -6,3 +6,4 @@ pub mod entity_spawn;
 pub mod movement;
 pub mod physics;
 pub mod presence;
+pub mod score;
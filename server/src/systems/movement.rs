@@ -1,7 +1,7 @@
 //! Provides a wrapper over `snake.move_forward` method that
 //! causes movement in the specified direction (which is a state).
 
-use common::world::types::GridPos;
+use common::world::{types::GridPos, world::World};
 
 use crate::entity::{EntityId, EntityManager};
 
@@ -13,20 +13,38 @@ pub enum MovementEvent {
         new_head: GridPos,
         removed_tail: GridPos,
     },
+    /// Сущность упёрлась в границу мира и осталась на месте.
+    /// Физика её убивает, как при столкновении со стеной.
+    EntityHitEdge { entity_id: EntityId },
 }
 
 pub struct MovementSystem;
 
 impl MovementSystem {
     /// Двигает все сущности и генерирует события об их перемещении.
-    pub fn tick(entities: &mut EntityManager, events_bus: &mut Vec<MovementEvent>) {
+    pub fn tick(
+        entities: &mut EntityManager,
+        world: &World,
+        events_bus: &mut Vec<MovementEvent>,
+    ) {
         // Проходимся по всем змейкам, чтобы их подвинуть
         for (entity_id, snake) in entities.entities.iter_mut() {
             // 1. Запоминаем позицию хвоста ДО его удаления
             // .cloned() нужен, так как .back() возвращает ссылку, а нам нужно владение.
             // Если у змейки нет тела, то и хвоста нет.
             if let Some(old_tail_pos) = snake.body.back().cloned() {
-                
+                // Голова не должна покидать мир: координат за его границей
+                // нет ни в одном чанке
+                let inside = snake
+                    .next_head()
+                    .is_some_and(|head| world.get_tile(&head).is_some());
+                if !inside {
+                    events_bus.push(MovementEvent::EntityHitEdge {
+                        entity_id: *entity_id,
+                    });
+                    continue;
+                }
+
                 // 2. Выполняем само движение (добавляется голова, удаляется хвост)
                 snake.move_forward();
 
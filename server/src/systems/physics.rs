@@ -1,11 +1,11 @@
 //! Provides processing of physical interactions in the game.
 
 // --- Ваши импорты ---
-use std::collections::HashSet;
-use common::{entities::snake::Snake, world::world::World}; // World все еще нужен для chunk_at
+use std::collections::{HashMap, hash_map::Entry};
+use common::{entities::snake::Snake, world::{chunk::Tile, world::World}}; // World все еще нужен для chunk_at
 use crate::{
     entity::{EntityId, EntityManager},
-    systems::presence::PresenceSystem,
+    systems::{movement::MovementEvent, presence::PresenceSystem},
 };
 use rand::Rng; // Для случайного выбора при столкновении лбами
 
@@ -13,7 +13,12 @@ use rand::Rng; // Для случайного выбора при столкно
 /// Сообщает о том, какая сущность должна быть уничтожена.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum PhysicsEvent {
-    EntityDied(EntityId),
+    EntityDied {
+        entity_id: EntityId,
+        /// The snake it crashed into, `None` for walls and its own body.
+        /// The killer may have died in the same tick.
+        killer: Option<EntityId>,
+    },
 }
 
 pub struct PhysicsSystem;
@@ -25,10 +30,24 @@ impl PhysicsSystem {
         presence_system: &mut PresenceSystem,
         entities: &EntityManager,
         world: &World, // world нужен для вызова world.chunk_at()
+        movement_events: &[MovementEvent],
         events_bus: &mut Vec<PhysicsEvent>,
     ) {
-        // Используем HashSet, чтобы избежать дублирования событий смерти для одной и той же сущности
-        let mut entities_to_remove: HashSet<EntityId> = HashSet::new();
+        // Используем HashMap, чтобы избежать дублирования событий смерти для одной и той же сущности.
+        // Значение - убийца, засчитывается первая причина смерти
+        let mut entities_to_remove: HashMap<EntityId, Option<EntityId>> = HashMap::new();
+
+        // Граница мира убивает так же, как стена
+        for event in movement_events {
+            if let MovementEvent::EntityHitEdge { entity_id } = event {
+                entities_to_remove.insert(*entity_id, None);
+            }
+        }
+        for (entity_id, snake) in entities.entities.iter() {
+            if Self::check_wall_collision(snake, world) {
+                entities_to_remove.insert(*entity_id, None);
+            }
+        }
 
         for entities_in_chunk in presence_system.presence_map.values() {
             // Проход для определения, кто должен умереть
@@ -36,32 +55,33 @@ impl PhysicsSystem {
                 let entity_a_id = entities_in_chunk[i];
 
                 // Если сущность уже помечена на удаление, пропускаем ее
-                if entities_to_remove.contains(&entity_a_id) {
+                if entities_to_remove.contains_key(&entity_a_id) {
                     continue;
                 }
 
                 if let Some(snake_a) = entities.get(&entity_a_id) {
                     // 1. Проверка на поедание самого себя
                     if Self::check_self_collision(snake_a) {
-                        entities_to_remove.insert(entity_a_id);
+                        entities_to_remove.insert(entity_a_id, None);
                         continue; // Переходим к следующей сущности
                     }
 
                     // 2. Проверка столкновений с другими
-                    for j in (i + 1)..entities_in_chunk.len() {
-                        let entity_b_id = entities_in_chunk[j];
-                        if entities_to_remove.contains(&entity_b_id) {
+                    for &entity_b_id in &entities_in_chunk[i + 1..] {
+                        if entities_to_remove.contains_key(&entity_b_id) {
                             continue;
                         }
 
                         if let Some(snake_b) = entities.get(&entity_b_id) {
-                            for dead_id in Self::get_dead_entities_on_collision(
+                            for (dead_id, killer) in Self::get_dead_entities_on_collision(
                                 entity_a_id,
                                 snake_a,
                                 entity_b_id,
                                 snake_b,
                             ) {
-                                entities_to_remove.insert(dead_id);
+                                if let Entry::Vacant(entry) = entities_to_remove.entry(dead_id) {
+                                    entry.insert(Some(killer));
+                                }
                             }
                         }
                     }
@@ -71,9 +91,9 @@ impl PhysicsSystem {
 
         // --- Финальная обработка ---
         // Генерируем события и чистим PresenceSystem
-        for entity_id in entities_to_remove {
+        for (entity_id, killer) in entities_to_remove {
             // 1. Добавляем событие в шину для дальнейшей обработки (например, в EntityManager)
-            events_bus.push(PhysicsEvent::EntityDied(entity_id));
+            events_bus.push(PhysicsEvent::EntityDied { entity_id, killer });
 
             // 2. Удаляем сущность из PresenceSystem (как и было запрошено)
             if let Some(snake_to_remove) = entities.get(&entity_id) {
@@ -85,6 +105,14 @@ impl PhysicsSystem {
         }
     }
 
+    /// Проверяет, врезалась ли голова змейки в стену.
+    fn check_wall_collision(snake: &Snake, world: &World) -> bool {
+        match snake.body.front() {
+            Some(head) => world.get_tile(head) == Some(&Tile::Wall),
+            None => false,
+        }
+    }
+
     /// Проверяет, столкнулась ли голова змейки с её телом.
     fn check_self_collision(snake: &Snake) -> bool {
         if let Some(head) = snake.body.front() {
@@ -94,12 +122,13 @@ impl PhysicsSystem {
     }
 
     /// Определяет, какие сущности умирают при столкновении, согласно правилам.
+    /// Возвращает пары (погибший, убийца).
     fn get_dead_entities_on_collision(
         id_a: EntityId,
         snake_a: &Snake,
         id_b: EntityId,
         snake_b: &Snake,
-    ) -> Vec<EntityId> {
+    ) -> Vec<(EntityId, EntityId)> {
         let mut dead_ids = Vec::new();
         let head_a = snake_a.body.front().unwrap();
         let head_b = snake_b.body.front().unwrap();
@@ -108,23 +137,123 @@ impl PhysicsSystem {
         if head_a == head_b {
             // Удаляется случайная змейка
             if rand::thread_rng().gen_bool(0.5) {
-                dead_ids.push(id_a);
+                dead_ids.push((id_a, id_b));
             } else {
-                dead_ids.push(id_b);
+                dead_ids.push((id_b, id_a));
             }
             return dead_ids;
         }
 
         // Правило 2: Голова А врезалась в тело Б
         if snake_b.body.iter().any(|part| part == head_a) {
-            dead_ids.push(id_a);
+            dead_ids.push((id_a, id_b));
         }
 
         // Правило 2: Голова Б врезалась в тело А
         if snake_a.body.iter().any(|part| part == head_b) {
-            dead_ids.push(id_b);
+            dead_ids.push((id_b, id_a));
         }
 
         dead_ids
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::movement::MovementSystem;
+    use common::{
+        entities::snake::Direction,
+        world::types::{ChunkSize, GridPos},
+    };
+
+    fn snake_at(x: u32, y: u32, direction: Direction) -> Snake {
+        let mut snake = Snake::new();
+        snake.direction = direction;
+        snake.body.push_back(GridPos { x, y });
+        snake
+    }
+
+    /// One move and physics tick over `snakes`, the deaths it found
+    /// and the presence map left after it
+    fn deaths(
+        world: &World,
+        snakes: Vec<(EntityId, Snake)>,
+    ) -> (Vec<PhysicsEvent>, PresenceSystem) {
+        let mut presence_system = PresenceSystem::new();
+        presence_system.add_chunks(world.chunks_total());
+        let mut entities = EntityManager::new();
+        for (entity_id, snake) in snakes {
+            presence_system.register_new_entity(entity_id, &snake, world);
+            entities.insert(entity_id, snake);
+        }
+
+        let mut movement_events = Vec::new();
+        MovementSystem::tick(&mut entities, world, &mut movement_events);
+        presence_system.tick(world, &movement_events, &mut Vec::new());
+        let mut events = Vec::new();
+        PhysicsSystem::tick(
+            &mut presence_system,
+            &entities,
+            world,
+            &movement_events,
+            &mut events,
+        );
+        (events, presence_system)
+    }
+
+    #[test]
+    fn physics_wall_kills() {
+        let mut world = World::new(32, 32, ChunkSize::default()).unwrap();
+        world.set_tile(&GridPos { x: 5, y: 5 }, Tile::Wall);
+
+        let (events, _) = deaths(
+            &world,
+            vec![
+                (1, snake_at(5, 6, Direction::North)),
+                (2, snake_at(6, 6, Direction::North)),
+            ],
+        );
+        assert_eq!(
+            events,
+            [PhysicsEvent::EntityDied {
+                entity_id: 1,
+                killer: None
+            }]
+        );
+    }
+
+    #[test]
+    fn physics_world_edge_kills() {
+        // More than 16 chunks in a row, where a position past the edge
+        // used to overflow the chunk id
+        let world = World::new(288, 32, ChunkSize::default()).unwrap();
+
+        let (events, presence_system) = deaths(
+            &world,
+            vec![
+                (1, snake_at(0, 0, Direction::North)),
+                (2, snake_at(0, 0, Direction::West)),
+                (3, snake_at(287, 31, Direction::East)),
+                (4, snake_at(287, 31, Direction::South)),
+                (5, snake_at(100, 10, Direction::East)),
+            ],
+        );
+        let mut dead = events
+            .iter()
+            .map(|PhysicsEvent::EntityDied { entity_id, killer }| {
+                assert_eq!(*killer, None);
+                *entity_id
+            })
+            .collect::<Vec<_>>();
+        dead.sort();
+        assert_eq!(dead, [1, 2, 3, 4]);
+
+        let present = presence_system
+            .presence_map
+            .values()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(present, [&5]);
+    }
+}
@@ -1,9 +1,12 @@
 //! Provides a mechanism for tracking which entities are in which chunks.
 //! This system is event-driven and reacts to entity movements and deaths.
 
+use crate::{entity::EntityId, systems::movement::MovementEvent};
+use common::{
+    entities::snake::Snake,
+    world::world::{ChunkId, World},
+};
 use std::collections::{HashMap, HashSet};
-use common::{entities::snake::Snake, world::{types::GridPos, world::{ChunkId, World}}};
-use crate::{entity::{EntityId, EntityManager}, systems::movement::MovementEvent};
 
 // --- Event Definitions ---
 // NOTE: You would likely place this in your `movement_system.rs` file.
@@ -32,6 +35,12 @@ pub struct PresenceSystem {
     pub presence_map: PresenceMap,
 }
 
+impl Default for PresenceSystem {
+    fn default() -> Self {
+        PresenceSystem::new()
+    }
+}
+
 impl PresenceSystem {
     /// Creates a new, empty PresenceSystem.
     pub fn new() -> PresenceSystem {
@@ -47,7 +56,7 @@ impl PresenceSystem {
             self.presence_map.insert(chunk_id, Vec::new());
         }
     }
-    
+
     /// Populates the PresenceSystem for a newly created entity.
     /// This should be called whenever a snake is spawned.
     pub fn register_new_entity(&mut self, entity_id: EntityId, snake: &Snake, world: &World) {
@@ -71,7 +80,11 @@ impl PresenceSystem {
     ) {
         for event in movement_events {
             match event {
-                MovementEvent::EntityMoved { entity_id, new_head, removed_tail } => {
+                MovementEvent::EntityMoved {
+                    entity_id,
+                    new_head,
+                    removed_tail,
+                } => {
                     let new_chunk = world.chunk_at(&new_head.clone());
                     let old_chunk = world.chunk_at(&removed_tail.clone());
 
@@ -89,7 +102,7 @@ impl PresenceSystem {
                         // NOTE: This is a complex check. A simpler, more robust approach
                         // is to do a full resync on death/spawn and only handle head/tail here.
                         // For now, we will assume a simple enter/leave event model.
-                        
+
                         // We remove the entity from the old chunk's list.
                         self.remove_entity_from_chunk(old_chunk, entity_id);
                         events_bus.push(PresenceEvent::EntityLeftChunk {
@@ -98,10 +111,12 @@ impl PresenceSystem {
                         });
                     }
                 }
+                // The entity stayed where it was
+                MovementEvent::EntityHitEdge { .. } => {}
             }
         }
     }
-    
+
     /// Handles the removal of a dead entity from the presence system.
     /// This should be called after processing PhysicsEvents.
     pub fn handle_entity_death(&mut self, entity_id: EntityId, snake: &Snake, world: &World) {
@@ -123,7 +138,10 @@ impl PresenceSystem {
             }
         } else {
             // This case can be treated as an error if add_chunks() was called correctly.
-            eprintln!("Attempted to add entity to non-existent chunk_id: {}", chunk_id);
+            eprintln!(
+                "Attempted to add entity to non-existent chunk_id: {}",
+                chunk_id
+            );
         }
     }
 
@@ -136,7 +154,10 @@ impl PresenceSystem {
             }
         } else {
             // This case can be treated as an error if add_chunks() was called correctly.
-            eprintln!("Attempted to remove entity from non-existent chunk_id: {}", chunk_id);
+            eprintln!(
+                "Attempted to remove entity from non-existent chunk_id: {}",
+                chunk_id
+            );
         }
     }
-}
\ No newline at end of file
+}
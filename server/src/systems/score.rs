@@ -0,0 +1,176 @@
+//! Keeps the score of every snake: apples eaten, kills and when it spawned.
+//! The length is read from the snake itself when ranking.
+//!
+//! Scores live as long as their snake. They are part of the snapshot,
+//! with the survival time instead of the spawn tick, since the tick
+//! starts over after a restart.
+
+use std::{cmp::Reverse, collections::HashMap};
+
+use common::net::datagram::Tick;
+
+use crate::{
+    entity::{EntityId, EntityManager},
+    systems::physics::PhysicsEvent,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub apples: u32,
+    pub kills: u32,
+    /// Tick the snake was first seen in
+    pub spawned: Tick,
+}
+
+/// A row of the leaderboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standing {
+    pub entity_id: EntityId,
+    pub length: u32,
+    pub apples: u32,
+    pub kills: u32,
+    /// In ticks
+    pub survived: Tick,
+}
+
+#[derive(Debug, Default)]
+pub struct ScoreSystem {
+    scores: HashMap<EntityId, Score>,
+}
+
+impl ScoreSystem {
+    pub fn new() -> ScoreSystem {
+        ScoreSystem::default()
+    }
+
+    /// Credits the kills of `physics_events` and follows the snakes
+    /// of `entities`: new ones start with an empty score in `tick`,
+    /// the scores of removed ones are dropped.
+    /// Must run after the dead snakes are removed.
+    pub fn tick(&mut self, entities: &EntityManager, physics_events: &[PhysicsEvent], tick: Tick) {
+        // A snake may kill in the tick it spawned in
+        for entity_id in entities.entities.keys() {
+            self.scores.entry(*entity_id).or_insert(Score {
+                apples: 0,
+                kills: 0,
+                spawned: tick,
+            });
+        }
+
+        for event in physics_events {
+            let PhysicsEvent::EntityDied { killer, .. } = event;
+            if let Some(score) = killer.and_then(|killer| self.scores.get_mut(&killer)) {
+                score.kills += 1;
+            }
+        }
+
+        self.scores
+            .retain(|entity_id, _| entities.entities.contains_key(entity_id));
+    }
+
+    /// The head of the snake entered an apple, see `Game::tick`
+    pub fn apple_eaten(&mut self, entity_id: EntityId) {
+        if let Some(score) = self.scores.get_mut(&entity_id) {
+            score.apples += 1;
+        }
+    }
+
+    pub fn get(&self, entity_id: EntityId) -> Option<&Score> {
+        self.scores.get(&entity_id)
+    }
+
+    /// `(entity, score)` of every snake, for snapshots
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &Score)> {
+        self.scores
+            .iter()
+            .map(|(entity_id, score)| (*entity_id, score))
+    }
+
+    /// Puts a score restored from a snapshot
+    pub fn restore(&mut self, entity_id: EntityId, score: Score) {
+        self.scores.insert(entity_id, score);
+    }
+
+    /// The best `limit` snakes: most kills first, then the longest,
+    /// then the one alive for longer
+    pub fn top(&self, entities: &EntityManager, tick: Tick, limit: usize) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .scores
+            .iter()
+            .filter_map(|(entity_id, score)| {
+                let snake = entities.get(entity_id)?;
+                Some(Standing {
+                    entity_id: *entity_id,
+                    length: snake.body.len() as u32,
+                    apples: score.apples,
+                    kills: score.kills,
+                    survived: tick.wrapping_sub(score.spawned),
+                })
+            })
+            .collect();
+        // The id only makes the order stable between ticks
+        standings.sort_by_key(|standing| {
+            (
+                Reverse(standing.kills),
+                Reverse(standing.length),
+                Reverse(standing.survived),
+                standing.entity_id,
+            )
+        });
+        standings.truncate(limit);
+        standings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{entities::snake::Snake, world::types::GridPos};
+
+    fn snake(length: u32) -> Snake {
+        let mut snake = Snake::new();
+        snake.body.extend((0..length).map(|y| GridPos { x: 0, y }));
+        snake
+    }
+
+    #[test]
+    fn score_kills_credited() {
+        let mut entities = EntityManager::new();
+        entities.insert(1, snake(2));
+        entities.insert(2, snake(2));
+        let mut scores = ScoreSystem::new();
+        scores.tick(&entities, &[], 5);
+
+        // 2 crashed into 1
+        entities.remove(2);
+        let died = PhysicsEvent::EntityDied {
+            entity_id: 2,
+            killer: Some(1),
+        };
+        scores.tick(&entities, &[died], 6);
+
+        assert_eq!(scores.get(1).map(|score| score.kills), Some(1));
+        assert_eq!(scores.get(2), None);
+    }
+
+    #[test]
+    fn score_ranking() {
+        let mut entities = EntityManager::new();
+        entities.insert(1, snake(3));
+        let mut scores = ScoreSystem::new();
+        scores.tick(&entities, &[], 0);
+        entities.insert(2, snake(5));
+        entities.insert(3, snake(3));
+        scores.tick(&entities, &[], 10);
+        scores.apple_eaten(3);
+        scores.scores.get_mut(&3).unwrap().kills = 1;
+
+        let top = scores.top(&entities, 20, 10);
+        let order: Vec<EntityId> = top.iter().map(|standing| standing.entity_id).collect();
+        assert_eq!(order, [3, 2, 1]);
+        assert_eq!(top[0].apples, 1);
+        assert_eq!(top[2].survived, 20);
+
+        assert_eq!(scores.top(&entities, 20, 1).len(), 1);
+    }
+}
@@ -1,16 +1,22 @@
-use common::world::world::World as CommonWorld;
+use common::world::{types::ChunkSize, world::World as CommonWorld};
 
 use crate::entity::EntityManager;
 
+#[derive(Debug)]
 pub struct World {
     pub world: CommonWorld,
     pub entity_manager: EntityManager,
 }
 
 impl World {
-    pub fn new(width: u32, height: u32) -> World {
+    pub fn new(width: u32, height: u32, chunk_size: ChunkSize) -> World {
+        World::from_world(CommonWorld::new(width, height, chunk_size).unwrap())
+    }
+
+    /// Wraps an already built world, e.g. loaded from a map file
+    pub fn from_world(world: CommonWorld) -> World {
         World {
-            world: CommonWorld::new(width, height).unwrap(),
+            world,
             entity_manager: EntityManager::new(),
         }
     }
@@ -0,0 +1,172 @@
+//! A whole server in one process: the `Game` with a `MemoryTransport`,
+//! ticked by the test itself.
+//!
+//! With `TestServer::with_network` the clients go through
+//! a `NetworkSimulator`, on a clock that only moves with the ticks.
+
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+use common::{
+    net::{
+        packets::{ConfigureAcknowledged, LoginSuccess, LoginSuccessData, Packet, WorldInfo},
+        version::Features,
+    },
+    world::types::{ChunkSize, GridPos},
+};
+use protocol::context::DecodeContext;
+use venomized_server::{
+    game::Game,
+    net::{
+        auth::Auth,
+        session::SessionManager,
+        transport::{
+            ConnectionIds,
+            memory::{MemoryClient, MemoryTransport, Received, join_packets},
+            simulator::{NetworkConditions, NetworkSimulator},
+        },
+    },
+    world::World,
+};
+
+/// Time between two `TestServer::tick`s, the same as in a real game
+pub const TICK: Duration = Duration::from_millis(100);
+
+pub struct TestServer {
+    pub game: Game,
+    /// For other transports of the same server
+    pub connection_ids: ConnectionIds,
+    transport: MemoryTransport,
+    simulator: Option<NetworkSimulator>,
+
+    /// The clock of the simulator
+    now: Instant,
+}
+
+impl TestServer {
+    /// A small arena whose spawn points are far from the walls and from
+    /// each other, so snakes survive the first few ticks whatever happens
+    pub fn new() -> TestServer {
+        TestServer::with_game(TestServer::new_game())
+    }
+
+    /// The same arena, open only to the players `auth` agrees to
+    pub fn with_auth(auth: Auth) -> TestServer {
+        TestServer::with_game(TestServer::new_game().with_auth(auth))
+    }
+
+    fn with_game(game: Game) -> TestServer {
+        let connection_ids = ConnectionIds::new();
+        let transport = MemoryTransport::new(game.net_events(), connection_ids.clone());
+        TestServer {
+            game,
+            connection_ids,
+            transport,
+            simulator: None,
+            now: Instant::now(),
+        }
+    }
+
+    /// The same arena behind a bad network, `seed` picks the losses
+    pub fn with_network(conditions: NetworkConditions, seed: u64) -> TestServer {
+        let game = TestServer::new_game();
+        let connection_ids = ConnectionIds::new();
+        let simulator = NetworkSimulator::new(conditions, seed, game.net_events());
+        let transport = MemoryTransport::new(simulator.net_events(), connection_ids.clone());
+        TestServer {
+            game,
+            connection_ids,
+            transport,
+            simulator: Some(simulator),
+            now: Instant::now(),
+        }
+    }
+
+    fn new_game() -> Game {
+        let mut world = World::new(64, 64, ChunkSize::new(16, 16));
+        world.world.spawn_points = vec![
+            GridPos { x: 16, y: 16 },
+            GridPos { x: 48, y: 48 },
+            GridPos { x: 16, y: 48 },
+            GridPos { x: 48, y: 16 },
+        ];
+        Game::new(world, SessionManager::new())
+    }
+
+    pub fn connect(&self) -> MemoryClient {
+        self.transport.connect()
+    }
+
+    /// Without a simulator, everything the clients sent is handled
+    /// and everything the game sent is there right after the tick.
+    /// With it, what's due by the end of the tick is there.
+    pub fn tick(&mut self) {
+        self.pump();
+        self.game.tick();
+        self.pump();
+        self.wait(TICK);
+    }
+
+    /// Moves the clock without ticking the game
+    pub fn wait(&mut self, duration: Duration) {
+        self.now += duration;
+        self.pump();
+    }
+
+    fn pump(&mut self) {
+        if let Some(simulator) = &mut self.simulator {
+            simulator.pump(self.now);
+        }
+    }
+
+    /// Logs in as `username_of(client)` and goes through `Configure`
+    /// in one tick, checking every packet up to `ConfigureAcknowledged`.
+    /// The `SpawnEntity`s of the other snakes come after it.
+    pub fn join(&mut self, client: &mut MemoryClient) -> LoginSuccessData {
+        self.join_with(client, Features::none())
+    }
+
+    /// The same with `features`, which must not include compression
+    pub fn join_with(&mut self, client: &mut MemoryClient, features: Features) -> LoginSuccessData {
+        for packet in join_packets(&username_of(client), features).unwrap() {
+            client.send_raw(packet);
+        }
+        self.tick();
+        let login_success = expect::<LoginSuccess>(client);
+        expect::<WorldInfo>(client);
+        expect::<ConfigureAcknowledged>(client);
+        login_success
+    }
+}
+
+/// Unique for every client of a server
+pub fn username_of(client: &MemoryClient) -> String {
+    format!("player{}", client.id())
+}
+
+/// The next thing the client received must be the reliable packet `P`
+pub fn expect<P: Packet>(client: &mut MemoryClient) -> P::Data {
+    match client.try_recv().unwrap() {
+        Some(Received::Reliable(packet)) => P::decode(&packet, &DecodeContext::DEFAULT)
+            .unwrap_or_else(|err| panic!("Expected {}: {err}", P::NAME)),
+        other => panic!("Expected {}, got {other:?}", P::NAME),
+    }
+}
+
+/// The next thing the client received must be the datagram `P`
+pub fn expect_datagram<P: Packet>(client: &mut MemoryClient) -> (u32, P::Data) {
+    match client.try_recv().unwrap() {
+        Some(Received::Datagram { tick, packet }) => {
+            let data = P::decode(&packet, &DecodeContext::DEFAULT)
+                .unwrap_or_else(|err| panic!("Expected {}: {err}", P::NAME));
+            (tick, data)
+        }
+        other => panic!("Expected {} datagram, got {other:?}", P::NAME),
+    }
+}
+
+pub fn expect_nothing(client: &mut MemoryClient) {
+    let received = client.try_recv().unwrap();
+    assert!(received.is_none(), "Expected nothing, got {received:?}");
+}
@@ -0,0 +1,284 @@
+//! The server as a whole, with in-process clients and manual ticks
+
+mod common;
+
+use ::common::net::{
+    packets::{
+        ChatBroadcast, ChatMessage, ChatMessageData, DisconnectLogin, DisconnectPlay, Leaderboard,
+        Login, LoginSuccess, Packet, SetCompression, SpawnEntity, TurnSnake, TurnSnakeData,
+        UpdateEntityPositionAndDirection, WorldInfo,
+    },
+    version::{Feature, Features},
+};
+use common::{TestServer, expect, expect_datagram, expect_nothing, username_of};
+use protocol::{
+    context::DecodeContext,
+    primitives::{byte::Byte, string::StringProto, uvarint::UVarInt},
+};
+use venomized_server::net::{
+    anticheat::STRIKE_LIMIT,
+    auth::Auth,
+    transport::memory::{Received, login_data},
+};
+
+#[test]
+fn join_exact_packets() {
+    let mut server = TestServer::new();
+    let mut client = server.connect();
+
+    let login_success = server.join(&mut client);
+    assert_eq!(login_success.features, Features::none());
+    assert_eq!(login_success.reconnect_token.0, 0);
+    expect_nothing(&mut client);
+}
+
+#[test]
+fn positions_reach_other_players() {
+    let mut server = TestServer::new();
+    let mut first = server.connect();
+    let mut second = server.connect();
+    server.join(&mut first);
+    server.join(&mut second);
+    expect::<SpawnEntity>(&mut first);
+    expect::<SpawnEntity>(&mut second);
+
+    // The snake of the second one moves from its login on, two ticks
+    // before the first one reads, but only the newest position is left
+    let (play_tick, _) = expect_datagram::<UpdateEntityPositionAndDirection>(&mut first);
+    expect_nothing(&mut first);
+    let (tick, _) = expect_datagram::<UpdateEntityPositionAndDirection>(&mut second);
+    assert_eq!(tick, play_tick);
+    expect_nothing(&mut second);
+
+    server.tick();
+    let (first_tick, first_sees) = expect_datagram::<UpdateEntityPositionAndDirection>(&mut first);
+    let (second_tick, second_sees) =
+        expect_datagram::<UpdateEntityPositionAndDirection>(&mut second);
+    expect_nothing(&mut first);
+    expect_nothing(&mut second);
+
+    assert_eq!(first_tick, play_tick + 1);
+    assert_eq!(second_tick, first_tick);
+    // Nobody gets their own position
+    assert_ne!(first_sees.id.0, second_sees.id.0);
+}
+
+#[test]
+fn spawned_snakes_labeled() {
+    let mut server = TestServer::new();
+    let mut first = server.connect();
+    let mut second = server.connect();
+    server.join(&mut first);
+    expect_nothing(&mut first);
+
+    // Both learn about the other one when the second enters `Play`
+    server.join(&mut second);
+    let first_sees = expect::<SpawnEntity>(&mut first);
+    let second_sees = expect::<SpawnEntity>(&mut second);
+    assert_eq!(first_sees.username.0, username_of(&second));
+    assert_eq!(second_sees.username.0, username_of(&first));
+    assert_ne!(first_sees.id.0, second_sees.id.0);
+}
+
+#[test]
+fn username_checked_at_login() {
+    let mut server = TestServer::new();
+    let mut first = server.connect();
+    server.join(&mut first);
+
+    // Taken, whatever the case
+    let mut second = server.connect();
+    let username = username_of(&first).to_uppercase();
+    second
+        .send::<Login>(&login_data(&username, Features::none(), 0))
+        .unwrap();
+    server.tick();
+    let reason = expect::<DisconnectLogin>(&mut second).reason.0;
+    assert!(reason.contains("taken"), "{reason}");
+
+    let mut third = server.connect();
+    third
+        .send::<Login>(&login_data("no spaces", Features::none(), 0))
+        .unwrap();
+    server.tick();
+    let reason = expect::<DisconnectLogin>(&mut third).reason.0;
+    assert!(reason.contains("Invalid username"), "{reason}");
+}
+
+#[test]
+fn shared_secret_required() {
+    let mut server = TestServer::with_auth(Auth::SharedSecret("hiss".to_string()));
+
+    let mut stranger = server.connect();
+    stranger
+        .send::<Login>(&login_data("viper", Features::none(), 0))
+        .unwrap();
+    server.tick();
+    expect::<DisconnectLogin>(&mut stranger);
+    assert!(stranger.is_closed());
+
+    let mut friend = server.connect();
+    let mut login = login_data("viper", Features::none(), 0);
+    login.auth_token.0 = "hiss".to_string();
+    friend.send::<Login>(&login).unwrap();
+    server.tick();
+    expect::<LoginSuccess>(&mut friend);
+}
+
+#[test]
+fn chat_between_players() {
+    let mut server = TestServer::new();
+    let chat = Features::from_iter([Feature::Chat]);
+    let mut first = server.connect();
+    let mut second = server.connect();
+    let mut silent = server.connect();
+    server.join_with(&mut first, chat.clone());
+    server.join_with(&mut second, chat);
+    server.join(&mut silent);
+
+    // Everybody with the feature hears about joins
+    let joined = format!("{} joined the game", username_of(&first));
+    assert_eq!(expect::<ChatBroadcast>(&mut first).message.0, joined);
+    let joined = format!("{} joined the game", username_of(&second));
+    assert_eq!(expect::<ChatBroadcast>(&mut first).message.0, joined);
+    expect::<SpawnEntity>(&mut first);
+    assert_eq!(expect::<ChatBroadcast>(&mut second).message.0, joined);
+    expect::<SpawnEntity>(&mut second);
+    for client in [&mut first, &mut second, &mut silent] {
+        while client.try_recv().unwrap().is_some() {}
+    }
+
+    second
+        .send::<ChatMessage>(&ChatMessageData {
+            message: StringProto("holy shit\x07, an apple".to_string()),
+        })
+        .unwrap();
+    server.tick();
+    let author = username_of(&second);
+    for client in [&mut first, &mut second] {
+        let broadcast = expect::<ChatBroadcast>(client);
+        assert_eq!(broadcast.sender.0, author);
+        assert_eq!(broadcast.message.0, "holy ****, an apple");
+    }
+
+    // Without the feature the packet doesn't exist
+    silent
+        .send::<ChatMessage>(&ChatMessageData {
+            message: StringProto("hi".to_string()),
+        })
+        .unwrap();
+    server.tick();
+    expect::<DisconnectPlay>(&mut silent);
+}
+
+#[test]
+fn leaderboard_every_second() {
+    let mut server = TestServer::new();
+    let mut first = server.connect();
+    let mut second = server.connect();
+    server.join_with(&mut first, Features::from_iter([Feature::Leaderboard]));
+    server.join(&mut second);
+
+    let mut leaderboard = None;
+    for _ in 0..10 {
+        server.tick();
+        while let Some(received) = first.try_recv().unwrap() {
+            if let Received::Reliable(packet) = received
+                && let Ok(data) = Leaderboard::decode(&packet, &DecodeContext::DEFAULT)
+            {
+                leaderboard = Some(data);
+            }
+        }
+    }
+
+    let leaderboard = leaderboard.expect("A leaderboard within a second");
+    let mut usernames: Vec<String> = leaderboard
+        .entries
+        .data
+        .iter()
+        .map(|entry| entry.username.0.clone())
+        .collect();
+    usernames.sort();
+    assert_eq!(usernames, [username_of(&first), username_of(&second)]);
+    // Without the feature the packet doesn't exist
+    while let Some(received) = second.try_recv().unwrap() {
+        if let Received::Reliable(packet) = received {
+            assert!(Leaderboard::decode(&packet, &DecodeContext::DEFAULT).is_err());
+        }
+    }
+}
+
+#[test]
+fn malformed_login_kicks() {
+    let mut server = TestServer::new();
+    let mut client = server.connect();
+
+    // `Login` with a truncated version
+    client.send_raw(vec![0x00, 0x80]);
+    server.tick();
+
+    expect::<DisconnectLogin>(&mut client);
+    assert!(client.is_closed());
+}
+
+#[test]
+fn session_resumes_after_reconnect() {
+    let mut server = TestServer::new();
+    let resume = Features::from_iter([Feature::SessionResume]);
+
+    let mut first = server.connect();
+    first
+        .send::<Login>(&login_data("viper", resume.clone(), 0))
+        .unwrap();
+    server.tick();
+    let token = expect::<LoginSuccess>(&mut first).reconnect_token.0;
+    assert_ne!(token, 0);
+    drop(first);
+    server.tick();
+
+    let mut again = server.connect();
+    again
+        .send::<Login>(&login_data("viper", resume, token))
+        .unwrap();
+    server.tick();
+    assert_eq!(expect::<LoginSuccess>(&mut again).reconnect_token.0, token);
+}
+
+#[test]
+fn compression_after_set_compression() {
+    let mut server = TestServer::new();
+    let mut client = server.connect();
+    let features = Features::from_iter([Feature::Compression]);
+
+    client
+        .send::<Login>(&login_data("viper", features, 0))
+        .unwrap();
+    server.tick();
+    expect::<LoginSuccess>(&mut client);
+    // Compressing the frames is up to the transport
+    expect::<SetCompression>(&mut client);
+    expect::<WorldInfo>(&mut client);
+    expect_nothing(&mut client);
+}
+
+#[test]
+fn impossible_turns_kick() {
+    let mut server = TestServer::new();
+    let mut client = server.connect();
+    server.join(&mut client);
+
+    // Far outside of the arena, whatever the snake does
+    let turn = TurnSnakeData {
+        direction: Byte(0),
+        x: UVarInt(u32::MAX),
+        y: UVarInt(u32::MAX),
+    };
+    for _ in 0..STRIKE_LIMIT {
+        client.send::<TurnSnake>(&turn).unwrap();
+        server.tick();
+    }
+
+    let reason = expect::<DisconnectPlay>(&mut client).reason.0;
+    assert!(reason.contains("claimed"), "{reason}");
+    assert!(client.is_closed());
+}
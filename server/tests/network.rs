@@ -0,0 +1,58 @@
+//! The server behind a simulated bad network
+
+mod common;
+
+use std::time::Duration;
+
+use ::common::net::{
+    packets::{Login, LoginSuccess, SpawnEntity, WorldInfo},
+    version::Features,
+};
+use common::{TestServer, expect, expect_nothing};
+use venomized_server::net::transport::{memory::login_data, simulator::NetworkConditions};
+
+#[test]
+fn login_over_latency() {
+    let conditions = NetworkConditions {
+        latency: Duration::from_millis(150),
+        ..NetworkConditions::PERFECT
+    };
+    let mut server = TestServer::with_network(conditions, 0);
+    let mut client = server.connect();
+    client
+        .send::<Login>(&login_data("viper", Features::none(), 0))
+        .unwrap();
+
+    // 150 ms to the server, handled by the third tick at 200 ms,
+    // 150 ms back, there by the end of the fourth one
+    for _ in 0..4 {
+        expect_nothing(&mut client);
+        server.tick();
+    }
+    expect::<LoginSuccess>(&mut client);
+    expect::<WorldInfo>(&mut client);
+    expect_nothing(&mut client);
+}
+
+#[test]
+fn lost_datagrams_lost_frames_resent() {
+    let conditions = NetworkConditions {
+        loss: 1.0,
+        ..NetworkConditions::PERFECT
+    };
+    let mut server = TestServer::with_network(conditions, 0);
+    let mut first = server.connect();
+    let mut second = server.connect();
+
+    // Frames get through every time, without latency even right away
+    server.join(&mut first);
+    server.join(&mut second);
+    expect::<SpawnEntity>(&mut first);
+    expect::<SpawnEntity>(&mut second);
+
+    // Positions never do
+    server.tick();
+    server.wait(Duration::from_secs(1));
+    expect_nothing(&mut first);
+    expect_nothing(&mut second);
+}
@@ -0,0 +1,158 @@
+//! The QUIC transport on localhost, with a real client endpoint
+
+mod common;
+
+use std::{borrow::Cow, net::SocketAddr, sync::Arc, time::Duration};
+
+use ::common::net::{
+    packets::{
+        ConfigureAcknowledged, DisconnectConfigure, Login, LoginSuccess, Packet, SetCompression,
+        SetDrawDistanceConfigure, SetDrawDistanceConfigureData, WorldInfo,
+    },
+    version::{Feature, Features},
+};
+use common::TestServer;
+use protocol::{
+    async_io::{FrameReader, write_frame},
+    compression::Compression,
+    context::DecodeContext,
+};
+use quinn::{
+    ClientConfig, Endpoint, RecvStream, SendStream,
+    rustls::{RootCertStore, pki_types::CertificateDer},
+};
+use venomized_server::net::transport::{
+    memory::login_data,
+    quic::{QuicTransport, SERVER_NAME},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn client_endpoint(cert: CertificateDer<'static>) -> Endpoint {
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let config = ClientConfig::with_root_certificates(Arc::new(roots)).unwrap();
+
+    let mut endpoint = Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    endpoint.set_default_client_config(config);
+    endpoint
+}
+
+/// Ticks the game while waiting, the transport runs concurrently
+async fn next_frame(
+    server: &mut TestServer,
+    frames: &mut FrameReader<RecvStream>,
+) -> Option<Vec<u8>> {
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    loop {
+        tokio::select! {
+            frame = frames.read_frame() => return frame.unwrap().map(Cow::into_owned),
+            _ = tokio::time::sleep(Duration::from_millis(5)) => server.tick(),
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "No frame in {TIMEOUT:?}"
+        );
+    }
+}
+
+/// After `Game::shutdown` there is nothing to tick
+async fn last_frames(frames: &mut FrameReader<RecvStream>) -> Vec<Vec<u8>> {
+    let mut bodies = Vec::new();
+    while let Some(body) = tokio::time::timeout(TIMEOUT, frames.read_frame())
+        .await
+        .expect("The stream must end")
+        .unwrap()
+    {
+        bodies.push(body.into_owned());
+    }
+    bodies
+}
+
+/// The client end, the endpoint and the connection must outlive the stream
+struct QuicClient {
+    _endpoint: Endpoint,
+    _connection: quinn::Connection,
+    send: SendStream,
+    frames: FrameReader<RecvStream>,
+}
+
+/// A transport serving `server` and a client connected to it
+async fn connect(server: &TestServer) -> (QuicTransport, QuicClient) {
+    let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let (transport, cert) = QuicTransport::bind(
+        addr,
+        server.game.net_events(),
+        server.connection_ids.clone(),
+    )
+    .unwrap();
+    let addr = transport.local_addr().unwrap();
+    tokio::spawn(transport.clone().serve());
+
+    let endpoint = client_endpoint(cert);
+    let connection = endpoint.connect(addr, SERVER_NAME).unwrap().await.unwrap();
+    let (send, recv) = connection.open_bi().await.unwrap();
+    let client = QuicClient {
+        _endpoint: endpoint,
+        _connection: connection,
+        send,
+        frames: FrameReader::new(recv),
+    };
+    (transport, client)
+}
+
+#[tokio::test]
+async fn quic_login_and_shutdown() {
+    let mut server = TestServer::new();
+    let (transport, mut client) = connect(&server).await;
+
+    let login = Login::encode(&login_data("viper", Features::none(), 0)).unwrap();
+    write_frame(&mut client.send, &login, None).await.unwrap();
+
+    let ctx = DecodeContext::DEFAULT;
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    LoginSuccess::decode(&packet, &ctx).unwrap();
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    WorldInfo::decode(&packet, &ctx).unwrap();
+
+    // The reason arrives before the stream ends
+    server.game.shutdown();
+    let packets = last_frames(&mut client.frames).await;
+    assert_eq!(packets.len(), 1);
+    DisconnectConfigure::decode(&packets[0], &ctx).unwrap();
+
+    transport.shutdown(TIMEOUT).await;
+}
+
+#[tokio::test]
+async fn quic_compression_after_set_compression() {
+    let mut server = TestServer::new();
+    let (transport, mut client) = connect(&server).await;
+
+    let features = Features::from_iter([Feature::Compression]);
+    let login = Login::encode(&login_data("viper", features, 0)).unwrap();
+    write_frame(&mut client.send, &login, None).await.unwrap();
+
+    let ctx = DecodeContext::DEFAULT;
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    LoginSuccess::decode(&packet, &ctx).unwrap();
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    let threshold = SetCompression::decode(&packet, &ctx).unwrap().threshold.0;
+
+    // Both directions carry the flag from now on. The server reads it
+    // since it sent `SetCompression`, even with `WorldInfo` still on the way.
+    let compression = Compression::new(threshold as usize);
+    let packet = SetDrawDistanceConfigure::encode(&SetDrawDistanceConfigureData {}).unwrap();
+    write_frame(&mut client.send, &packet, Some(&compression))
+        .await
+        .unwrap();
+
+    client.frames.set_compression(Some(compression));
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    WorldInfo::decode(&packet, &ctx).unwrap();
+    let packet = next_frame(&mut server, &mut client.frames).await.unwrap();
+    ConfigureAcknowledged::decode(&packet, &ctx).unwrap();
+
+    server.game.shutdown();
+    transport.shutdown(TIMEOUT).await;
+}
@@ -0,0 +1,370 @@
+//! A whole game in one process, watched through the eyes of one client.
+//!
+//! The server runs with a `MemoryTransport` behind a `NetworkSimulator`,
+//! so the view shows what a real client would get over such a network.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use common::{
+    net::{
+        datagram::Tick,
+        packets::{
+            ChatBroadcast, ChatMessage, ChatMessageData, ConfigureAcknowledged, Leaderboard,
+            LeaderboardEntryData, Login, LoginData, LoginSuccess, Packet, ReconnectToken,
+            RemoveEntities, SetDrawDistanceConfigure, SetDrawDistanceConfigureData,
+        },
+        version::{Feature, Features, PROTOCOL_VERSION},
+    },
+    world::{
+        chunk::Tile,
+        generator::{Generator, GeneratorConfig},
+        map::{Map, MapError},
+        types::{ChunkSize, GridPos},
+        world::World as CommonWorld,
+    },
+};
+use protocol::{
+    codec::Codec,
+    context::DecodeContext,
+    error::ProtocolError,
+    primitives::{string::StringProto, uvarint::UVarInt, varint::VarInt},
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+use venomized_client::positions::PositionUpdates;
+use venomized_server::{
+    game::Game,
+    net::{
+        NetMetrics,
+        session::SessionManager,
+        transport::{
+            ConnectionIds,
+            memory::{MemoryClient, MemoryTransport, Received},
+            simulator::NetworkSimulator,
+        },
+    },
+    world::World,
+};
+
+use crate::options::Options;
+
+/// The same as the real server
+pub const TICK_DURATION: Duration = Duration::from_millis(100);
+
+/// Size of the generated arena, fits into a terminal
+const ARENA_WIDTH: u32 = 64;
+const ARENA_HEIGHT: u32 = 32;
+
+/// Chat lines kept for the panel
+const CHAT_HISTORY: usize = 50;
+
+/// A random bot says something about this often, in ticks
+const BOT_CHAT_INTERVAL: u32 = 40;
+
+/// What the bots say, the last one to show the filter
+const BOT_LINES: &[&str] = &[
+    "hi all",
+    "anyone seen an apple?",
+    "gg",
+    "lag again...",
+    "watch out for the walls",
+    "oh shit",
+];
+
+/// How far a client got, the same packet id means
+/// different packets in different stages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Login,
+    Configure,
+    Play,
+}
+
+impl Stage {
+    /// Follows the stage changes of the server, returns the packet id
+    fn follow(&mut self, packet: &[u8]) -> Result<i32, ProtocolError> {
+        let id = VarInt::decode(&mut &packet[..])?.0;
+        *self = match (*self, id) {
+            (Stage::Login, LoginSuccess::ID) => Stage::Configure,
+            (Stage::Configure, ConfigureAcknowledged::ID) => Stage::Play,
+            (stage, _) => stage,
+        };
+        Ok(id)
+    }
+}
+
+struct Client {
+    connection: MemoryClient,
+    stage: Stage,
+}
+
+/// A line of the chat panel
+#[derive(Debug, Clone)]
+pub struct ChatLine {
+    /// Empty for announcements of the server
+    pub sender: String,
+    pub message: String,
+}
+
+/// The last known position of an entity
+#[derive(Debug, Clone)]
+pub struct Head {
+    pub pos: GridPos,
+    pub direction: u8,
+
+    /// Stamped by the server
+    pub tick: Tick,
+}
+
+/// What reached the observer
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub frames: u64,
+    pub datagrams: u64,
+
+    /// Older than an update already applied
+    pub stale: u64,
+
+    /// Sum of the ages of applied updates in ticks, for the average
+    pub age_total: u64,
+}
+
+impl Stats {
+    /// Average of `current tick - stamped tick` of applied updates
+    pub fn average_age(&self) -> f64 {
+        match self.datagrams - self.stale {
+            0 => 0.0,
+            applied => self.age_total as f64 / applied as f64,
+        }
+    }
+}
+
+pub struct App {
+    pub options: Options,
+    pub seed: u64,
+
+    game: Game,
+    simulator: NetworkSimulator,
+
+    /// Whose view is shown
+    observer: Client,
+    bots: Vec<Client>,
+
+    /// Picks what the bots say and when
+    rng: StdRng,
+
+    /// Drawn under the heads
+    pub walls: Vec<Vec<bool>>,
+    positions: PositionUpdates,
+    pub heads: HashMap<i64, Head>,
+    pub stats: Stats,
+
+    /// What the observer read in the chat, oldest first
+    pub chat: VecDeque<ChatLine>,
+    /// The latest leaderboard, best first
+    pub leaderboard: Vec<LeaderboardEntryData>,
+
+    /// The same as the tick of the game
+    pub tick: Tick,
+}
+
+impl App {
+    pub fn new(options: Options) -> Result<App, MapError> {
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let world = match &options.map {
+            Some(path) => Map::load(path)?,
+            None => {
+                let config = GeneratorConfig {
+                    seed,
+                    ..GeneratorConfig::default()
+                };
+                Generator::generate_map(ARENA_WIDTH, ARENA_HEIGHT, config)?
+            }
+        }
+        .into_world(ChunkSize::default())?;
+        let walls = walls(&world);
+
+        let game = Game::new(World::from_world(world), SessionManager::new());
+        let simulator = NetworkSimulator::new(options.conditions.clone(), seed, game.net_events());
+        let transport = MemoryTransport::new(simulator.net_events(), ConnectionIds::new());
+
+        let connect = || Client {
+            connection: transport.connect(),
+            stage: Stage::Login,
+        };
+        let observer = connect();
+        let bots = (0..options.bots).map(|_| connect()).collect();
+        let app = App {
+            options,
+            seed,
+            game,
+            simulator,
+            observer,
+            bots,
+            rng: StdRng::seed_from_u64(seed),
+            walls,
+            positions: PositionUpdates::new(),
+            heads: HashMap::new(),
+            stats: Stats::default(),
+            chat: VecDeque::new(),
+            leaderboard: Vec::new(),
+            tick: 0,
+        };
+        for (i, client) in app.clients().enumerate() {
+            let username = match i {
+                0 => "viz".to_string(),
+                i => format!("bot{i}"),
+            };
+            join(&client.connection, &username).expect("Login fits into a packet");
+        }
+        Ok(app)
+    }
+
+    fn clients(&self) -> impl Iterator<Item = &Client> {
+        std::iter::once(&self.observer).chain(&self.bots)
+    }
+
+    pub fn tick(&mut self, now: Instant) -> Result<(), ProtocolError> {
+        self.simulator.pump(now);
+        self.game.tick();
+        self.tick = self.tick.wrapping_add(1);
+        self.simulator.pump(now);
+
+        if self.tick.is_multiple_of(BOT_CHAT_INTERVAL) {
+            self.bot_chat()?;
+        }
+        Ok(())
+    }
+
+    /// A random bot in `Play` says a random line
+    fn bot_chat(&mut self) -> Result<(), ProtocolError> {
+        let Some(bot) = self.bots.choose(&mut self.rng) else {
+            return Ok(());
+        };
+        if bot.stage != Stage::Play {
+            return Ok(());
+        }
+        let line = BOT_LINES[self.rng.gen_range(0..BOT_LINES.len())];
+        bot.connection.send::<ChatMessage>(&ChatMessageData {
+            message: StringProto(line.to_string()),
+        })
+    }
+
+    /// Outbound queues of the server
+    pub fn metrics(&self) -> NetMetrics {
+        self.game.metrics()
+    }
+
+    /// Delivers what's due and reads what reached the clients
+    pub fn pump(&mut self, now: Instant) -> Result<(), ProtocolError> {
+        self.simulator.pump(now);
+
+        // Nobody looks at them, they only must not pile up
+        for bot in &mut self.bots {
+            while let Some(received) = bot.connection.try_recv()? {
+                if let Received::Reliable(packet) = received {
+                    bot.stage.follow(&packet)?;
+                }
+            }
+        }
+
+        while let Some(received) = self.observer.connection.try_recv()? {
+            match received {
+                Received::Reliable(packet) => {
+                    self.stats.frames += 1;
+                    self.receive_frame(&packet)?;
+                }
+                Received::Datagram { tick, packet } => {
+                    self.stats.datagrams += 1;
+                    self.receive_datagram(tick, &packet)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn receive_frame(&mut self, packet: &[u8]) -> Result<(), ProtocolError> {
+        let id = self.observer.stage.follow(packet)?;
+        if id == LoginSuccess::ID {
+            self.positions.reset();
+        }
+        if self.observer.stage != Stage::Play {
+            return Ok(());
+        }
+        match id {
+            ChatBroadcast::ID => {
+                let data = ChatBroadcast::decode(packet, &DecodeContext::DEFAULT)?;
+                if self.chat.len() == CHAT_HISTORY {
+                    self.chat.pop_front();
+                }
+                self.chat.push_back(ChatLine {
+                    sender: data.sender.0,
+                    message: data.message.0,
+                });
+            }
+            Leaderboard::ID => {
+                let data = Leaderboard::decode(packet, &DecodeContext::DEFAULT)?;
+                self.leaderboard = data.entries.data;
+            }
+            RemoveEntities::ID => {
+                let data = RemoveEntities::decode(packet, &DecodeContext::DEFAULT)?;
+                for id in data.entities.data {
+                    self.heads.remove(&id.0);
+                    self.positions.remove(id.0);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn receive_datagram(&mut self, tick: Tick, packet: &[u8]) -> Result<(), ProtocolError> {
+        let Some(update) = self
+            .positions
+            .receive_packet(tick, packet, &DecodeContext::DEFAULT)?
+        else {
+            self.stats.stale += 1;
+            return Ok(());
+        };
+        self.stats.age_total += self.tick.wrapping_sub(tick) as u64;
+        self.heads.insert(
+            update.id.0,
+            Head {
+                pos: GridPos {
+                    x: update.x.0,
+                    y: update.y.0,
+                },
+                direction: update.direction.0,
+                tick,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Logs in with the chat and the leaderboard and acknowledges the configuration right away,
+/// the server handles both in order
+fn join(client: &MemoryClient, username: &str) -> Result<(), ProtocolError> {
+    client.send::<Login>(&LoginData {
+        protocol_version: UVarInt(PROTOCOL_VERSION),
+        client_name: StringProto("viz".to_string()),
+        username: StringProto(username.to_string()),
+        auth_token: StringProto(String::new()),
+        features: Features::from_iter([Feature::Chat, Feature::Leaderboard]),
+        reconnect_token: ReconnectToken::from(0),
+    })?;
+    client.send::<SetDrawDistanceConfigure>(&SetDrawDistanceConfigureData {})
+}
+
+/// Rows of the world, `true` for walls
+fn walls(world: &CommonWorld) -> Vec<Vec<bool>> {
+    (0..world.height)
+        .map(|y| {
+            (0..world.width)
+                .map(|x| world.get_tile(&GridPos { x, y }) == Some(&Tile::Wall))
+                .collect()
+        })
+        .collect()
+}
@@ -1,3 +1,73 @@
-fn main() {
-    println!("Hello, world!");
+//! Runs a local game behind a simulated network and shows
+//! what one of its clients sees. See `options` for the flags.
+
+mod app;
+mod options;
+mod ui;
+
+use std::{
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use ratatui::crossterm::event::{self, Event, KeyCode};
+
+use crate::{
+    app::{App, TICK_DURATION},
+    options::{Options, USAGE},
+};
+
+/// How often packets are delivered and the screen redrawn between ticks
+const FRAME_DURATION: Duration = Duration::from_millis(10);
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut app = match App::new(options) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("Failed to load the map: {err:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut app);
+    ratatui::restore();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(
+    terminal: &mut ratatui::DefaultTerminal,
+    app: &mut App,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut next_tick = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_tick {
+            app.tick(now)?;
+            next_tick += TICK_DURATION;
+        }
+        app.pump(now)?;
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        if event::poll(FRAME_DURATION)?
+            && let Event::Key(key) = event::read()?
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            return Ok(());
+        }
+    }
 }
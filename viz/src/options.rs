@@ -0,0 +1,148 @@
+//! Command line of `viz`.
+//!
+//! ```text
+//! viz [--latency MS] [--jitter MS] [--loss %] [--duplication %]
+//!     [--reordering %] [--seed N] [--bots N] [map]
+//! ```
+//!
+//! Without any network flag the simulator changes nothing.
+
+use std::{fmt, time::Duration};
+
+use venomized_server::net::transport::simulator::NetworkConditions;
+
+pub const USAGE: &str = "Usage: viz [--latency MS] [--jitter MS] [--loss %] \
+    [--duplication %] [--reordering %] [--seed N] [--bots N] [map]";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub conditions: NetworkConditions,
+
+    /// For the simulator and the generated arena, random if not given
+    pub seed: Option<u64>,
+
+    /// Clients besides the one whose view is shown
+    pub bots: u32,
+
+    /// Generated arena if not given
+    pub map: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            conditions: NetworkConditions::PERFECT,
+            seed: None,
+            bots: 3,
+            map: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OptionsError {
+    UnknownFlag(String),
+    MissingValue(String),
+    InvalidValue { flag: String, value: String },
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionsError::UnknownFlag(flag) => write!(f, "unknown flag {flag}"),
+            OptionsError::MissingValue(flag) => write!(f, "{flag} needs a value"),
+            OptionsError::InvalidValue { flag, value } => {
+                write!(f, "invalid value {value:?} for {flag}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
+impl Options {
+    /// Parses the arguments without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, OptionsError> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                options.map = Some(arg);
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| OptionsError::MissingValue(arg.clone()))?;
+            let conditions = &mut options.conditions;
+            match arg.as_str() {
+                "--latency" => conditions.latency = millis(&arg, &value)?,
+                "--jitter" => conditions.jitter = millis(&arg, &value)?,
+                "--loss" => conditions.loss = percent(&arg, &value)?,
+                "--duplication" => conditions.duplication = percent(&arg, &value)?,
+                "--reordering" => conditions.reordering = percent(&arg, &value)?,
+                "--seed" => options.seed = Some(number(&arg, &value)?),
+                "--bots" => options.bots = number(&arg, &value)?,
+                _ => return Err(OptionsError::UnknownFlag(arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, OptionsError> {
+    value.parse().map_err(|_| OptionsError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn millis(flag: &str, value: &str) -> Result<Duration, OptionsError> {
+    number(flag, value).map(Duration::from_millis)
+}
+
+/// `0` to `100`, as a probability
+fn percent(flag: &str, value: &str) -> Result<f64, OptionsError> {
+    let percent: f64 = number(flag, value)?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(OptionsError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        });
+    }
+    Ok(percent / 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+
+    fn parse(args: &str) -> Result<Options, OptionsError> {
+        Options::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn options_network_flags() {
+        let options = parse("--latency 120 --jitter 30 --loss 5 arena.png --bots 1").unwrap();
+        assert_eq!(options.conditions.latency, Duration::from_millis(120));
+        assert_eq!(options.conditions.jitter, Duration::from_millis(30));
+        assert_eq!(options.conditions.loss, 0.05);
+        assert_eq!(options.conditions.duplication, 0.0);
+        assert_eq!(options.bots, 1);
+        assert_eq!(options.map.as_deref(), Some("arena.png"));
+
+        assert_eq!(parse("").unwrap(), Options::default());
+    }
+
+    #[test]
+    fn options_invalid() {
+        assert_matches!(parse("--loss 101"), Err(OptionsError::InvalidValue { .. }));
+        assert_matches!(
+            parse("--latency -1"),
+            Err(OptionsError::InvalidValue { .. })
+        );
+        assert_matches!(parse("--jitter"), Err(OptionsError::MissingValue(_)));
+        assert_matches!(parse("--lag 1"), Err(OptionsError::UnknownFlag(_)));
+    }
+}
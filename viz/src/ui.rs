@@ -0,0 +1,155 @@
+//! Drawing of the `App`: the arena as the observer knows it,
+//! what the network did to its packets, the best players
+//! and what the players said.
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+};
+
+use common::net::{datagram::Tick, packets::LEADERBOARD_SIZE};
+
+use crate::app::App;
+
+/// Height of the chat panel, borders included
+const CHAT_HEIGHT: u16 = 8;
+
+/// Ticks in a second, for the survival time
+const TICKS_PER_SECOND: Tick = 10;
+
+/// A head not updated for this many ticks is drawn dimmed
+const OUTDATED_TICKS: u32 = 3;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let [top, chat] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(CHAT_HEIGHT)]).areas(frame.area());
+    let [arena, side] = Layout::horizontal([Constraint::Min(0), Constraint::Length(32)]).areas(top);
+    // Borders and the header around the rows
+    let [network, leaderboard] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(LEADERBOARD_SIZE as u16 + 3),
+    ])
+    .areas(side);
+
+    frame.render_widget(
+        Paragraph::new(arena_lines(app)).block(Block::bordered().title(" Arena ")),
+        arena,
+    );
+    frame.render_widget(
+        Paragraph::new(network_lines(app)).block(Block::bordered().title(" Network (q to quit) ")),
+        network,
+    );
+    frame.render_widget(
+        Paragraph::new(leaderboard_lines(app)).block(Block::bordered().title(" Leaderboard ")),
+        leaderboard,
+    );
+    frame.render_widget(
+        Paragraph::new(chat_lines(app, chat.height.saturating_sub(2)))
+            .block(Block::bordered().title(" Chat ")),
+        chat,
+    );
+}
+
+fn arena_lines(app: &App) -> Vec<Line<'static>> {
+    let mut rows: Vec<Vec<Span>> = app
+        .walls
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&wall| match wall {
+                    true => Span::styled("#", Style::new().fg(Color::DarkGray)),
+                    false => Span::raw(" "),
+                })
+                .collect()
+        })
+        .collect();
+
+    for head in app.heads.values() {
+        let Some(cell) = rows
+            .get_mut(head.pos.y as usize)
+            .and_then(|row| row.get_mut(head.pos.x as usize))
+        else {
+            continue;
+        };
+        let symbol = match head.direction {
+            0 => "^",
+            1 => "v",
+            2 => "<",
+            _ => ">",
+        };
+        let color = match app.tick.wrapping_sub(head.tick) > OUTDATED_TICKS {
+            true => Color::DarkGray,
+            false => Color::Green,
+        };
+        *cell = Span::styled(symbol, Style::new().fg(color));
+    }
+
+    rows.into_iter().map(Line::from).collect()
+}
+
+fn network_lines(app: &App) -> Vec<Line<'static>> {
+    let conditions = &app.options.conditions;
+    let stats = &app.stats;
+    let queues = app.metrics();
+    [
+        format!("latency      {:?}", conditions.latency),
+        format!("jitter       {:?}", conditions.jitter),
+        format!("loss         {:.1}%", conditions.loss * 100.0),
+        format!("duplication  {:.1}%", conditions.duplication * 100.0),
+        format!("reordering   {:.1}%", conditions.reordering * 100.0),
+        format!("seed         {}", app.seed),
+        String::new(),
+        format!("tick         {}", app.tick),
+        format!("frames       {}", stats.frames),
+        format!("datagrams    {}", stats.datagrams),
+        format!("stale        {}", stats.stale),
+        format!("average age  {:.2} ticks", stats.average_age()),
+        String::new(),
+        format!("queued       {} B", queues.queued_bytes),
+        format!("coalesced    {}", queues.coalesced),
+        format!("dropped      {}", queues.dropped),
+        format!("kicked       {}", queues.kicked),
+    ]
+    .into_iter()
+    .map(Line::from)
+    .collect()
+}
+
+fn leaderboard_lines(app: &App) -> Vec<Line<'static>> {
+    let header = Line::styled(
+        format!("{:<16} {:>2} {:>3} {:>4}", "player", "k", "len", "time"),
+        Style::new().fg(Color::DarkGray),
+    );
+    let rows = app.leaderboard.iter().map(|entry| {
+        Line::from(format!(
+            "{:<16} {:>2} {:>3} {:>3}s",
+            entry.username.0,
+            entry.kills.0,
+            entry.length.0,
+            entry.survived.0 / TICKS_PER_SECOND,
+        ))
+    });
+    std::iter::once(header).chain(rows).collect()
+}
+
+/// The newest lines that fit into `height`
+fn chat_lines(app: &App, height: u16) -> Vec<Line<'static>> {
+    let skip = app.chat.len().saturating_sub(height as usize);
+    app.chat
+        .iter()
+        .skip(skip)
+        .map(|line| match line.sender.is_empty() {
+            true => Line::styled(
+                format!("* {}", line.message),
+                Style::new().fg(Color::Yellow),
+            ),
+            false => Line::from(vec![
+                Span::styled(format!("<{}> ", line.sender), Style::new().fg(Color::Green)),
+                Span::raw(line.message.clone()),
+            ]),
+        })
+        .collect()
+}